@@ -0,0 +1,343 @@
+use std::{collections::BTreeMap, env, net::SocketAddr, sync::Arc};
+
+use axum::{
+	extract::{ConnectInfo, Extension, OriginalUri, Query},
+	http::{header, Request, StatusCode},
+	middleware::{self, Next},
+	response::{IntoResponse, Response},
+	routing::{get, post},
+	Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use subtle::ConstantTimeEq;
+
+use crate::{
+	auth_log::{log_auth_failure, AuthFailure},
+	client_ip::{client_ip, ClientIpHeader},
+	environment::redacted_config,
+	jobs::{JobStatus, Jobs},
+	maintenance::Maintenance,
+	memory::{report, Caches, MemoryReport},
+	search::search,
+	slo::{Slo, SloSummary},
+	uri_mappings::UriMappings,
+	validate::{Conflict, Validator},
+};
+
+#[derive(Serialize)]
+struct MaintenanceStatus {
+	enabled: bool,
+	notice: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Links {
+	standard: Vec<StandardLink>,
+	pattern: Vec<PatternLink>,
+}
+
+#[derive(Serialize)]
+struct StandardLink {
+	path: String,
+	target: String,
+	/// The link to share, if the canonical URL is set
+	short_url: Option<String>,
+	note: Option<String>,
+}
+
+#[derive(Serialize)]
+struct PatternLink {
+	place: usize,
+	regex: String,
+	target: String,
+	note: Option<String>,
+	/// Whether the pattern is skipped for taking too long to match
+	quarantined: bool,
+}
+
+#[derive(Serialize)]
+struct Validation {
+	valid: bool,
+	conflicts: Vec<Conflict>,
+}
+
+/// Results in the shape of an Alfred Script Filter
+#[derive(Serialize)]
+struct AlfredResults {
+	items: Vec<AlfredItem>,
+}
+
+#[derive(Serialize)]
+struct AlfredItem {
+	uid: String,
+	title: String,
+	subtitle: String,
+	arg: String,
+	autocomplete: String,
+}
+
+/// Results in the shape of a Raycast list
+#[derive(Serialize)]
+struct RaycastResults {
+	items: Vec<RaycastItem>,
+}
+
+#[derive(Serialize)]
+struct RaycastItem {
+	id: String,
+	title: String,
+	subtitle: String,
+	url: String,
+}
+
+#[derive(Deserialize)]
+struct SearchQuery {
+	#[serde(default)]
+	q: String,
+}
+
+#[derive(Deserialize)]
+struct MaintenanceChange {
+	enabled: bool,
+	notice: Option<String>,
+}
+
+/// How many links a search returns
+const SEARCH_LIMIT: usize = 20;
+/// How many of the slowest and flakiest links are reported
+const SLO_LIMIT: usize = 10;
+
+/// The configuration the instance loaded, with secrets hidden
+#[derive(Clone, Serialize)]
+struct Config {
+	variables: BTreeMap<String, String>,
+}
+
+/// The URL links are shared with, if one was set
+#[derive(Clone)]
+struct CanonicalUrl(Option<String>);
+
+/// Create the routes for managing the running instance. Every route requires the admin token
+pub fn router(
+	token: String,
+	maintenance: Arc<Maintenance>,
+	uri_mappings: Arc<UriMappings>,
+	jobs: Arc<Jobs>,
+	validator: Validator,
+	canonical_url: Option<String>,
+	caches: Caches,
+) -> Router {
+	let token = Arc::new(token);
+
+	let routes = Router::new()
+		// `GET /api/maintenance` to see if maintenance mode is on
+		// `POST /api/maintenance` to turn maintenance mode on or off
+		.route("/maintenance", get(get_maintenance).post(set_maintenance))
+		// `GET /api/links` to list the mappings with their notes
+		.route("/links", get(get_links))
+		// `GET /api/jobs` to see how the background jobs are doing
+		.route("/jobs", get(get_jobs))
+		// `POST /api/validate` to check a `.env` file of new mappings against the live ones
+		.route("/validate", post(validate))
+		// `GET /api/search/alfred?q=<query>` to fuzzy search the links for Alfred
+		.route("/search/alfred", get(search_alfred))
+		// `GET /api/search/raycast?q=<query>` to fuzzy search the links for Raycast
+		.route("/search/raycast", get(search_raycast))
+		// `GET /api/slo` to see which links are the slowest and which error the most
+		.route("/slo", get(get_slo))
+		// `GET /api/debug/memory` to see how many mappings there are and how full the caches are
+		.route("/debug/memory", get(get_memory))
+		// `GET /api/config` to see the configuration that was loaded, with secrets hidden
+		.route("/config", get(get_config));
+	// `GET /api/debug/pprof/*` for CPU profiles, only if it was built with them
+	#[cfg(feature = "profiling")]
+	let routes = routes.merge(crate::profiling::router());
+
+	routes
+		.layer(Extension(maintenance))
+		.layer(Extension(uri_mappings))
+		.layer(Extension(jobs))
+		.layer(Extension(Arc::new(validator)))
+		.layer(Extension(CanonicalUrl(canonical_url)))
+		.layer(Extension(caches.slo.clone()))
+		.layer(Extension(Arc::new(caches)))
+		// The routes are made once everything has loaded, so this is what the instance runs with
+		.layer(Extension(Arc::new(Config {
+			variables: redacted_config(env::vars_os()),
+		})))
+		.route_layer(middleware::from_fn(move |request, next| {
+			require_token(request, next, token.clone())
+		}))
+}
+
+/// Reject any request that doesn't have the admin token as a bearer token, logging who sent it
+async fn require_token<B>(request: Request<B>, next: Next<B>, token: Arc<String>) -> Response {
+	let authorized = request
+		.headers()
+		.get(header::AUTHORIZATION)
+		.and_then(|value| value.to_str().ok())
+		.and_then(|value| value.strip_prefix("Bearer "))
+		.is_some_and(|given| given.as_bytes().ct_eq(token.as_bytes()).into());
+
+	if authorized {
+		return next.run(request).await;
+	}
+
+	let extensions = request.extensions();
+	if let Some(ConnectInfo(peer)) = extensions.get::<ConnectInfo<SocketAddr>>() {
+		let header = extensions
+			.get::<ClientIpHeader>()
+			.and_then(|ClientIpHeader(header)| header.as_ref());
+		let path = extensions
+			.get::<OriginalUri>()
+			.map_or_else(|| request.uri().path(), |OriginalUri(uri)| uri.path());
+		log_auth_failure(
+			AuthFailure::AdminToken,
+			client_ip(request.headers(), *peer, header),
+			path,
+		);
+	}
+	StatusCode::UNAUTHORIZED.into_response()
+}
+
+async fn get_maintenance(
+	Extension(maintenance): Extension<Arc<Maintenance>>,
+) -> Json<MaintenanceStatus> {
+	let notice = maintenance.notice();
+	Json(MaintenanceStatus {
+		enabled: notice.is_some(),
+		notice,
+	})
+}
+
+async fn set_maintenance(
+	Extension(maintenance): Extension<Arc<Maintenance>>,
+	Json(change): Json<MaintenanceChange>,
+) -> Json<MaintenanceStatus> {
+	if change.enabled {
+		maintenance.enable(change.notice);
+	} else {
+		maintenance.disable();
+	}
+	get_maintenance(Extension(maintenance)).await
+}
+
+async fn get_links(
+	Extension(uri_mappings): Extension<Arc<UriMappings>>,
+	Extension(CanonicalUrl(canonical_url)): Extension<CanonicalUrl>,
+) -> Json<Links> {
+	let mut standard: Vec<StandardLink> = uri_mappings
+		.standard
+		.iter()
+		.map(|(path, uri)| StandardLink {
+			path: path.clone(),
+			target: uri.to_string(),
+			short_url: canonical_url.as_ref().map(|url| format!("{url}/{path}")),
+			note: uri_mappings.standard_notes.get(path).cloned(),
+		})
+		.collect();
+	standard.sort_by(|a, b| a.path.cmp(&b.path));
+
+	let pattern = uri_mappings
+		.pattern
+		.iter()
+		.enumerate()
+		.map(|(place, (regex, uri))| PatternLink {
+			place,
+			regex: regex.to_string(),
+			target: uri.clone(),
+			note: uri_mappings.pattern_notes.get(&place).cloned(),
+			quarantined: uri_mappings.is_quarantined(place),
+		})
+		.collect();
+
+	Json(Links { standard, pattern })
+}
+
+/// Find the standard links that best match the query, with where they go and the link to open
+fn search_links(
+	uri_mappings: &UriMappings,
+	canonical_url: Option<&str>,
+	query: &str,
+) -> Vec<(String, String, String)> {
+	search(
+		query,
+		uri_mappings.standard.keys().map(String::as_str),
+		SEARCH_LIMIT,
+	)
+	.into_iter()
+	.map(|path| {
+		let target = uri_mappings.standard[path].to_string();
+		// Opening the short link keeps the hits counted, so prefer it when it's known
+		let url = canonical_url.map_or_else(|| target.clone(), |url| format!("{url}/{path}"));
+		(path.to_string(), target, url)
+	})
+	.collect()
+}
+
+async fn search_alfred(
+	Extension(uri_mappings): Extension<Arc<UriMappings>>,
+	Extension(CanonicalUrl(canonical_url)): Extension<CanonicalUrl>,
+	Query(query): Query<SearchQuery>,
+) -> Json<AlfredResults> {
+	let items = search_links(&uri_mappings, canonical_url.as_deref(), &query.q)
+		.into_iter()
+		.map(|(path, target, url)| AlfredItem {
+			uid: path.clone(),
+			title: path.clone(),
+			subtitle: target,
+			arg: url,
+			autocomplete: path,
+		})
+		.collect();
+	Json(AlfredResults { items })
+}
+
+async fn search_raycast(
+	Extension(uri_mappings): Extension<Arc<UriMappings>>,
+	Extension(CanonicalUrl(canonical_url)): Extension<CanonicalUrl>,
+	Query(query): Query<SearchQuery>,
+) -> Json<RaycastResults> {
+	let items = search_links(&uri_mappings, canonical_url.as_deref(), &query.q)
+		.into_iter()
+		.map(|(path, target, url)| RaycastItem {
+			id: path.clone(),
+			title: path,
+			subtitle: target,
+			url,
+		})
+		.collect();
+	Json(RaycastResults { items })
+}
+
+async fn get_jobs(Extension(jobs): Extension<Arc<Jobs>>) -> Json<Vec<JobStatus>> {
+	Json(jobs.statuses())
+}
+
+async fn get_slo(Extension(slo): Extension<Arc<Slo>>) -> Json<SloSummary> {
+	Json(slo.summary(SLO_LIMIT))
+}
+
+async fn get_memory(
+	Extension(uri_mappings): Extension<Arc<UriMappings>>,
+	Extension(caches): Extension<Arc<Caches>>,
+) -> Json<MemoryReport> {
+	Json(report(&uri_mappings, &caches))
+}
+
+async fn get_config(Extension(config): Extension<Arc<Config>>) -> Json<Config> {
+	Json(config.as_ref().clone())
+}
+
+async fn validate(
+	Extension(validator): Extension<Arc<Validator>>,
+	proposed: String,
+) -> Result<Json<Validation>, (StatusCode, String)> {
+	let conflicts = validator
+		.validate(&proposed)
+		.map_err(|error| (StatusCode::BAD_REQUEST, error))?;
+	Ok(Json(Validation {
+		valid: conflicts.is_empty(),
+		conflicts,
+	}))
+}
@@ -0,0 +1,60 @@
+use std::{fmt, net::IpAddr};
+
+use crate::clock::{format_timestamp, unix_now};
+
+/// What a client failed to authenticate with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthFailure {
+	/// A missing or wrong admin token
+	#[cfg(feature = "admin")]
+	AdminToken,
+	/// A private link with a token that isn't its signature
+	LinkSignature,
+}
+
+impl fmt::Display for AuthFailure {
+	fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+		formatter.write_str(match self {
+			#[cfg(feature = "admin")]
+			AuthFailure::AdminToken => "admin_token",
+			AuthFailure::LinkSignature => "link_signature",
+		})
+	}
+}
+
+/// Log a client that failed to authenticate, in a format that stays the same between versions
+/// so `fail2ban` or `CrowdSec` can ban clients that keep failing
+pub fn log_auth_failure(failure: AuthFailure, client: IpAddr, path: &str) {
+	println!("{}", format_auth_failure(unix_now(), failure, client, path));
+}
+
+/// The path is quoted and escaped, so a client can't forge a line with someone else's address
+fn format_auth_failure(at: u64, failure: AuthFailure, client: IpAddr, path: &str) -> String {
+	format!(
+		"{} urshort auth_failure client={client} reason={failure} path={path:?}",
+		format_timestamp(at)
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::net::Ipv4Addr;
+
+	use super::*;
+
+	#[test]
+	fn format_auth_failures() {
+		let client = IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7));
+		assert_eq!(
+			format_auth_failure(1_700_000_000, AuthFailure::LinkSignature, client, "/internal"),
+			"2023-11-14T22:13:20Z urshort auth_failure client=203.0.113.7 reason=link_signature path=\"/internal\""
+		);
+		assert!(!format_auth_failure(
+			0,
+			AuthFailure::LinkSignature,
+			client,
+			"a\n1970-01-01T00:00:00Z urshort auth_failure client=192.0.2.1"
+		)
+		.contains('\n'));
+	}
+}
@@ -0,0 +1,165 @@
+use std::{
+	collections::HashMap,
+	net::IpAddr,
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Mutex,
+	},
+	time::{Duration, Instant},
+};
+
+use reqwest::Client;
+use serde::Deserialize;
+
+/// How long to wait on `CrowdSec` before letting the client through
+const LOOKUP_TIMEOUT: Duration = Duration::from_secs(1);
+/// The most clients to remember decisions for, so the cache can't grow without limit
+const MAX_CACHED_CLIENTS: usize = 100_000;
+
+/// A decision `CrowdSec` made about an IP
+#[derive(Deserialize)]
+struct Decision {
+	#[serde(rename = "type")]
+	kind: String,
+}
+
+/// Asks a local `CrowdSec` API if clients should be turned away, remembering the answers for a
+/// while so most requests don't wait on it
+pub struct Bouncer {
+	client: Client,
+	decisions_url: String,
+	api_key: String,
+	/// How long flagged clients are kept waiting before being turned away
+	tarpit: Option<Duration>,
+	cache_for: Duration,
+	decisions: Mutex<HashMap<IpAddr, (Instant, bool)>>,
+	/// Whether the last lookup reached `CrowdSec`, so losing it is only printed once
+	reachable: AtomicBool,
+}
+
+impl Bouncer {
+	/// Create a new `Bouncer` for the `CrowdSec` API at the URL, like `http://127.0.0.1:8080`
+	pub fn new(
+		url: &str,
+		api_key: String,
+		tarpit: Option<Duration>,
+		cache_for: Duration,
+	) -> Result<Bouncer, String> {
+		let client = Client::builder()
+			.timeout(LOOKUP_TIMEOUT)
+			.build()
+			.map_err(|error| error.to_string())?;
+
+		Ok(Bouncer {
+			client,
+			decisions_url: format!("{}/v1/decisions", url.trim_end_matches('/')),
+			api_key,
+			tarpit,
+			cache_for,
+			decisions: Mutex::new(HashMap::new()),
+			reachable: AtomicBool::new(true),
+		})
+	}
+
+	/// Check if `CrowdSec` wants the client turned away, after keeping it waiting if there's a
+	/// tarpit. Clients are let through while `CrowdSec` can't be reached
+	pub async fn is_flagged(&self, client: IpAddr) -> bool {
+		let now = Instant::now();
+		let cached = self
+			.decisions
+			.lock()
+			.unwrap()
+			.get(&client)
+			.filter(|(checked, _)| now.duration_since(*checked) < self.cache_for)
+			.map(|(_, flagged)| *flagged);
+
+		let flagged = if let Some(flagged) = cached {
+			flagged
+		} else {
+			let flagged = self.look_up(client).await;
+			self.remember(client, now, flagged);
+			flagged
+		};
+		if flagged {
+			if let Some(tarpit) = self.tarpit {
+				tokio::time::sleep(tarpit).await;
+			}
+		}
+		flagged
+	}
+
+	async fn look_up(&self, client: IpAddr) -> bool {
+		let decisions = async {
+			self.client
+				.get(&self.decisions_url)
+				.query(&[("ip", client.to_string())])
+				.header("X-Api-Key", &self.api_key)
+				.send()
+				.await?
+				.error_for_status()?
+				.text()
+				.await
+		};
+		match decisions
+			.await
+			.map_err(|error| error.to_string())
+			.and_then(|body| parse_decisions(&body))
+		{
+			Ok(flagged) => {
+				if !self.reachable.swap(true, Ordering::Relaxed) {
+					println!("CrowdSec can be reached again");
+					println!();
+				}
+				flagged
+			}
+			Err(error) => {
+				if self.reachable.swap(false, Ordering::Relaxed) {
+					println!("Letting clients through, because CrowdSec couldn't be asked about them: {error}");
+					println!();
+				}
+				false
+			}
+		}
+	}
+
+	fn remember(&self, client: IpAddr, checked: Instant, flagged: bool) {
+		let mut decisions = self.decisions.lock().unwrap();
+		if decisions.len() >= MAX_CACHED_CLIENTS {
+			decisions.retain(|_, (at, _)| checked.duration_since(*at) < self.cache_for);
+			if decisions.len() >= MAX_CACHED_CLIENTS {
+				decisions.clear();
+			}
+		}
+		decisions.insert(client, (checked, flagged));
+	}
+}
+
+/// Check if the decisions `CrowdSec` returned for an IP turn it away. It returns `null` when there
+/// are none, and only bans and captchas count, since a captcha can't be shown for a redirect
+fn parse_decisions(body: &str) -> Result<bool, String> {
+	let decisions: Option<Vec<Decision>> =
+		serde_json::from_str(body).map_err(|error| error.to_string())?;
+	Ok(decisions
+		.unwrap_or_default()
+		.iter()
+		.any(|decision| decision.kind == "ban" || decision.kind == "captcha"))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn flag_banned_clients() {
+		assert_eq!(parse_decisions("null"), Ok(false));
+		assert_eq!(parse_decisions("[]"), Ok(false));
+		assert_eq!(
+			parse_decisions(
+				r#"[{"id":1,"origin":"crowdsec","type":"ban","scope":"Ip","value":"203.0.113.7","duration":"3h59m"}]"#
+			),
+			Ok(true)
+		);
+		assert_eq!(parse_decisions(r#"[{"type":"throttle"}]"#), Ok(false));
+		assert!(parse_decisions("<html>").is_err());
+	}
+}
@@ -0,0 +1,89 @@
+use axum::http::Uri;
+
+use crate::{html, pages::Page};
+
+/// A list of links shown together on one page. Written as comma separated entries of
+/// `<title> <uri>`, where the title is everything before the last space
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Bundle {
+	links: Vec<(String, Uri)>,
+}
+
+impl Bundle {
+	/// Parse links like `Docs https://docs.example.com/, Chat https://chat.example.com/`, with the
+	/// targets parsed the same as any other, since they go straight into the page
+	pub fn parse(
+		s: &str,
+		parse_target: impl Fn(&str) -> Result<Uri, String>,
+	) -> Result<Bundle, String> {
+		let links = s
+			.split(',')
+			.filter(|entry| !entry.trim().is_empty())
+			.map(|entry| {
+				let entry = entry.trim();
+				let (name, uri) = entry
+					.rsplit_once(char::is_whitespace)
+					.ok_or_else(|| format!("'{entry}' is not like '<title> <uri>'"))?;
+				Ok((name.trim().to_string(), parse_target(uri)?))
+			})
+			.collect::<Result<Vec<_>, String>>()?;
+
+		if links.is_empty() {
+			return Err("there are no links".to_string());
+		}
+		Ok(Bundle { links })
+	}
+
+	/// Render the landing page for the bundle
+	pub fn page(&self, title: &str) -> Page {
+		let content = format!("\t<h1>{}</h1>\n{}", html::escape(title), self.list());
+		Page::new(title, content)
+	}
+
+	/// Render the links as a list, for placing inside of a page
+	pub fn list(&self) -> String {
+		let links: Vec<String> = self
+			.links
+			.iter()
+			.map(|(name, uri)| {
+				format!(
+					"\t\t<li><a href=\"{}\">{}</a></li>",
+					html::escape(&uri.to_string()),
+					html::escape(name)
+				)
+			})
+			.collect();
+		format!("\t<ul class=\"links\">\n{}\n\t</ul>", links.join("\n"))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::targets::TargetPolicy;
+
+	#[test]
+	fn render_bundles() {
+		let policy = TargetPolicy::default()
+			.with_base("https://example.com/")
+			.unwrap();
+		let parse = |s| Bundle::parse(s, |target| policy.parse(target));
+		let bundle = parse(
+			"Team Docs https://docs.example.com/, Chat <3 https://chat.example.com/?a=1&b=2, Setup /setup",
+		)
+		.unwrap();
+
+		let page = bundle.page("Onboarding & Setup");
+		assert_eq!(page.title, "Onboarding &amp; Setup");
+		let page = page.content;
+		assert!(page.contains("<h1>Onboarding &amp; Setup</h1>"));
+		assert!(page.contains("<a href=\"https://docs.example.com/\">Team Docs</a>"));
+		assert!(page.contains("<a href=\"https://chat.example.com/?a=1&amp;b=2\">Chat &lt;3</a>"));
+		assert!(page.contains("<a href=\"https://example.com/setup\">Setup</a>"));
+
+		assert!(parse("").is_err());
+		assert!(parse("https://example.com/").is_err());
+		assert!(parse("Docs http://[broken").is_err());
+		assert!(parse("Docs javascript:alert(1)").is_err());
+	}
+}
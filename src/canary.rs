@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use axum::http::{header, HeaderMap, Uri};
+
+use crate::{
+	resolver::{Resolution, ResolveRequest, Resolver, Target},
+	uri_mappings::LinkOptions,
+};
+
+/// The header that asks for the canary targets
+pub const CANARY_HEADER: &str = "x-urshort-canary";
+/// The cookie that asks for the canary targets, so they can be tried in a browser
+pub const CANARY_COOKIE: &str = "urshort_canary";
+
+/// Check if the request asked for the canary targets
+fn wants_canary(headers: &HeaderMap) -> bool {
+	if headers
+		.get(CANARY_HEADER)
+		.is_some_and(|value| !value.is_empty())
+	{
+		return true;
+	}
+
+	headers
+		.get_all(header::COOKIE)
+		.iter()
+		.filter_map(|value| value.to_str().ok())
+		.flat_map(|cookies| cookies.split(';'))
+		.filter_map(|cookie| cookie.trim().split_once('='))
+		.any(|(name, value)| name == CANARY_COOKIE && !value.is_empty())
+}
+
+/// Resolves standard paths to a staged target, but only for requests that ask for it, so a
+/// change can be checked before it's made for everyone
+pub struct CanaryResolver {
+	canaries: HashMap<String, Uri>,
+	/// The options of the standard mappings, which still apply to their canaries
+	options: HashMap<String, LinkOptions>,
+}
+
+impl CanaryResolver {
+	/// Create a new `CanaryResolver` from targets keyed by path, with the options of the
+	/// standard mappings they stand in for
+	pub fn new(canaries: HashMap<String, Uri>, options: HashMap<String, LinkOptions>) -> Self {
+		CanaryResolver { canaries, options }
+	}
+}
+
+#[async_trait]
+impl Resolver for CanaryResolver {
+	async fn resolve(&self, request: &ResolveRequest<'_>) -> Option<Resolution> {
+		let uri = self.canaries.get(request.path.as_ref())?;
+		if !wants_canary(request.headers) {
+			return None;
+		}
+
+		let options = self
+			.options
+			.get(request.path.as_ref())
+			.cloned()
+			.unwrap_or_default();
+		if !options.allows(request.client) {
+			return None;
+		}
+		Some(Resolution {
+			target: Target::Redirect(uri.clone()),
+			options,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{
+		borrow::Cow,
+		net::{IpAddr, Ipv4Addr},
+		str::FromStr,
+	};
+
+	use axum::http::HeaderValue;
+
+	use super::*;
+
+	#[tokio::test]
+	async fn resolve_canaries() {
+		let canary = Uri::from_str("https://example.com/new").unwrap();
+		let resolver = CanaryResolver::new(
+			HashMap::from([
+				("open".to_string(), canary.clone()),
+				("internal".to_string(), canary.clone()),
+			]),
+			HashMap::from([(
+				"internal".to_string(),
+				LinkOptions {
+					allowed: Some("10.0.0.0/8".parse().unwrap()),
+					..LinkOptions::default()
+				},
+			)]),
+		);
+		let query = HashMap::new();
+		let resolve = |path: &'static str, headers: HeaderMap| {
+			let resolver = &resolver;
+			let query = &query;
+			async move {
+				let request = ResolveRequest {
+					path: Cow::Borrowed(path),
+					query,
+					headers: &headers,
+					client: IpAddr::V4(Ipv4Addr::LOCALHOST),
+				};
+				resolver.resolve(&request).await.map(|x| x.target)
+			}
+		};
+
+		// Only requests asking for the canary get it
+		assert_eq!(resolve("open", HeaderMap::new()).await, None);
+		let mut headers = HeaderMap::new();
+		headers.insert(CANARY_HEADER, HeaderValue::from_static("1"));
+		assert_eq!(
+			resolve("open", headers.clone()).await,
+			Some(Target::Redirect(canary.clone()))
+		);
+		let mut cookies = HeaderMap::new();
+		cookies.insert(
+			header::COOKIE,
+			HeaderValue::from_static("theme=dark; urshort_canary=1"),
+		);
+		assert_eq!(
+			resolve("open", cookies).await,
+			Some(Target::Redirect(canary))
+		);
+
+		// The standard mapping's allowed clients still apply
+		assert_eq!(resolve("internal", headers).await, None);
+	}
+}
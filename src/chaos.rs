@@ -0,0 +1,79 @@
+use std::{
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		Arc,
+	},
+	time::Duration,
+};
+
+use axum::{
+	http::{Request, StatusCode},
+	middleware::Next,
+	response::{IntoResponse, Response},
+};
+
+/// Makes requests slow or fail on purpose, to test the monitoring and clients in front of the
+/// links
+pub struct Chaos {
+	latency: Option<Duration>,
+	/// The percent of requests to fail, from 0 to 100
+	error_percent: u64,
+	error_status: StatusCode,
+	requests: AtomicU64,
+}
+
+impl Chaos {
+	/// Create a new `Chaos` that adds the latency to every request and fails the percent of them
+	pub fn new(
+		latency: Option<Duration>,
+		error_percent: u64,
+		error_status: StatusCode,
+	) -> Result<Chaos, String> {
+		if error_percent > 100 {
+			return Err(format!("{error_percent} isn't a percent from 0 to 100"));
+		}
+		Ok(Chaos {
+			latency,
+			error_percent,
+			error_status,
+			requests: AtomicU64::new(0),
+		})
+	}
+
+	/// Check if the next request should fail. Failures are spread evenly, so any stretch of
+	/// requests fails close to the percent of them
+	fn should_fail(&self) -> bool {
+		let request = self.requests.fetch_add(1, Ordering::Relaxed);
+		(request + 1) * self.error_percent / 100 > request * self.error_percent / 100
+	}
+}
+
+/// Delay the request and then fail it or pass it on, as the chaos says
+pub async fn inject<B>(request: Request<B>, next: Next<B>, chaos: Arc<Chaos>) -> Response {
+	if let Some(latency) = chaos.latency {
+		tokio::time::sleep(latency).await;
+	}
+	if chaos.should_fail() {
+		return chaos.error_status.into_response();
+	}
+	next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn fail_percent_of_requests() {
+		let chaos = Chaos::new(None, 25, StatusCode::SERVICE_UNAVAILABLE).unwrap();
+		let failed: Vec<bool> = (0..8).map(|_| chaos.should_fail()).collect();
+		assert_eq!(
+			failed,
+			vec![false, false, false, true, false, false, false, true]
+		);
+
+		let calm = Chaos::new(None, 0, StatusCode::SERVICE_UNAVAILABLE).unwrap();
+		assert!((0..100).all(|_| !calm.should_fail()));
+		assert!(Chaos::new(None, 101, StatusCode::SERVICE_UNAVAILABLE).is_err());
+	}
+}
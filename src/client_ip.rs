@@ -0,0 +1,62 @@
+use std::net::{IpAddr, SocketAddr};
+
+use axum::http::{header::HeaderName, HeaderMap};
+
+/// The header the client's IP is read from, for routes that find it themselves
+#[cfg(feature = "admin")]
+#[derive(Clone)]
+pub struct ClientIpHeader(pub Option<HeaderName>);
+
+/// Find the IP of the client that made a request. If a header is given, such as `X-Forwarded-For`
+/// from a reverse proxy, the last address in it is used. Otherwise the address of the connection
+/// is used
+pub fn client_ip(headers: &HeaderMap, peer: SocketAddr, header: Option<&HeaderName>) -> IpAddr {
+	header
+		.and_then(|header| headers.get_all(header).iter().next_back())
+		.and_then(|value| value.to_str().ok())
+		.and_then(|value| value.rsplit(',').next())
+		.and_then(|value| value.trim().parse::<IpAddr>().ok())
+		.unwrap_or_else(|| peer.ip())
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{net::Ipv4Addr, str::FromStr};
+
+	use axum::http::HeaderValue;
+
+	use super::*;
+
+	#[test]
+	fn find_client_ip() {
+		let peer = SocketAddr::from(([10, 0, 0, 1], 4000));
+		let forwarded = HeaderName::from_static("x-forwarded-for");
+
+		let mut headers = HeaderMap::new();
+
+		// Without a header the connection is used
+		assert_eq!(client_ip(&headers, peer, None), peer.ip());
+		assert_eq!(client_ip(&headers, peer, Some(&forwarded)), peer.ip());
+
+		// The last address in the header is the one the proxy added
+		headers.insert(
+			forwarded.clone(),
+			HeaderValue::from_static("203.0.113.7, 198.51.100.2"),
+		);
+		assert_eq!(
+			client_ip(&headers, peer, Some(&forwarded)),
+			IpAddr::V4(Ipv4Addr::new(198, 51, 100, 2))
+		);
+		assert_eq!(client_ip(&headers, peer, None), peer.ip());
+
+		// Garbage falls back to the connection
+		headers.insert(forwarded.clone(), HeaderValue::from_static("unknown"));
+		assert_eq!(client_ip(&headers, peer, Some(&forwarded)), peer.ip());
+
+		headers.insert(forwarded.clone(), HeaderValue::from_static("2001:db8::1"));
+		assert_eq!(
+			client_ip(&headers, peer, Some(&forwarded)),
+			IpAddr::from_str("2001:db8::1").unwrap()
+		);
+	}
+}
@@ -0,0 +1,175 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// The time of the week in UTC, for mappings that change depending on when they're used
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UtcTime {
+	/// Days since Monday, from 0 to 6
+	pub weekday: u8,
+	pub hour: u8,
+	pub minute: u8,
+}
+
+/// The number of seconds since the Unix epoch
+pub fn unix_now() -> u64 {
+	SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map_or(0, |since| since.as_secs())
+}
+
+/// Parse a UTC timestamp, like `2024-01-01T09:00:00Z`, into seconds since the Unix epoch. The
+/// seconds can be left off
+pub fn parse_timestamp(timestamp: &str) -> Result<u64, String> {
+	let invalid = || format!("'{timestamp}' is not a UTC time, like '2024-01-01T09:00:00Z'");
+	let (date, time) = timestamp
+		.trim()
+		.strip_suffix(['Z', 'z'])
+		.and_then(|timestamp| timestamp.split_once(['T', 't', ' ']))
+		.ok_or_else(invalid)?;
+
+	let numbers = |text: &str| -> Option<Vec<u64>> {
+		text.split(['-', ':'])
+			.map(|part| part.parse::<u64>().ok())
+			.collect()
+	};
+	let (year, month, day) = match numbers(date).as_deref() {
+		Some(&[year, month, day]) if year >= 1970 && (1..=12).contains(&month) => {
+			(year, month, day)
+		}
+		_ => return Err(invalid()),
+	};
+	let (hour, minute, second) = match numbers(time).as_deref() {
+		Some(&[hour, minute]) => (hour, minute, 0),
+		Some(&[hour, minute, second]) => (hour, minute, second),
+		_ => return Err(invalid()),
+	};
+	let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+	let days_in_month = match month {
+		2 if leap => 29,
+		2 => 28,
+		4 | 6 | 9 | 11 => 30,
+		_ => 31,
+	};
+	if day == 0 || day > days_in_month || hour > 23 || minute > 59 || second > 59 {
+		return Err(invalid());
+	}
+
+	// Count the days from the epoch, with years starting in March so leap days come last
+	let (year, month) = if month > 2 {
+		(year, month - 3)
+	} else {
+		(year - 1, month + 9)
+	};
+	let days =
+		year * 365 + year / 4 - year / 100 + year / 400 + (month * 153 + 2) / 5 + day - 1 - 719_468;
+	Ok(days * SECONDS_PER_DAY + hour * 3600 + minute * 60 + second)
+}
+
+/// The year, month, and day of the month at a number of seconds since the Unix epoch
+#[allow(clippy::cast_possible_truncation)]
+pub fn date_from_unix(seconds: u64) -> (u64, u8, u8) {
+	// The reverse of counting the days in `parse_timestamp`
+	let days = seconds / SECONDS_PER_DAY + 719_468;
+	let era = days / 146_097;
+	let day_of_era = days % 146_097;
+	let year_of_era =
+		(day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+	let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+	let month = (5 * day_of_year + 2) / 153;
+	let day = day_of_year - (153 * month + 2) / 5 + 1;
+	let (year, month) = if month < 10 {
+		(era * 400 + year_of_era, month + 3)
+	} else {
+		(era * 400 + year_of_era + 1, month - 9)
+	};
+	(year, month as u8, day as u8)
+}
+
+/// Format a number of seconds since the Unix epoch as a UTC timestamp
+pub fn format_timestamp(seconds: u64) -> String {
+	let (year, month, day) = date_from_unix(seconds);
+	let time = UtcTime::from_unix(seconds);
+	format!(
+		"{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+		time.hour,
+		time.minute,
+		seconds % 60
+	)
+}
+
+impl UtcTime {
+	/// The current time
+	pub fn now() -> UtcTime {
+		UtcTime::from_unix(unix_now())
+	}
+
+	/// The time at a number of seconds since the Unix epoch
+	#[allow(clippy::cast_possible_truncation)]
+	pub fn from_unix(seconds: u64) -> UtcTime {
+		let days = seconds / SECONDS_PER_DAY;
+		let seconds = seconds % SECONDS_PER_DAY;
+
+		UtcTime {
+			// The epoch was on a Thursday
+			weekday: ((days + 3) % 7) as u8,
+			hour: (seconds / 3600) as u8,
+			minute: (seconds / 60 % 60) as u8,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn time_from_unix() {
+		assert_eq!(
+			UtcTime::from_unix(0),
+			UtcTime {
+				weekday: 3,
+				hour: 0,
+				minute: 0
+			}
+		);
+
+		// 2024-01-01 was a Monday
+		assert_eq!(
+			UtcTime::from_unix(1_704_067_200 + 13 * 3600 + 37 * 60 + 59),
+			UtcTime {
+				weekday: 0,
+				hour: 13,
+				minute: 37
+			}
+		);
+		assert_eq!(UtcTime::from_unix(1_704_067_200 - 1).weekday, 6);
+	}
+
+	#[test]
+	fn parse_timestamps() {
+		assert_eq!(parse_timestamp("1970-01-01T00:00:00Z"), Ok(0));
+		assert_eq!(
+			parse_timestamp("2024-01-01T13:37Z"),
+			Ok(1_704_067_200 + 13 * 3600 + 37 * 60)
+		);
+		assert_eq!(parse_timestamp("2024-02-29 09:00:30Z"), Ok(1_709_197_230));
+		assert_eq!(parse_timestamp("2000-03-01T00:00:00Z"), Ok(951_868_800));
+
+		assert!(parse_timestamp("2023-02-29T09:00:00Z").is_err());
+		assert!(parse_timestamp("2024-01-01T09:00:00").is_err());
+		assert!(parse_timestamp("2024-01-01T24:00:00Z").is_err());
+		assert!(parse_timestamp("tomorrow").is_err());
+
+		for timestamp in [
+			"1970-01-01T00:00:00Z",
+			"2000-02-29T23:59:59Z",
+			"2024-12-31T12:00:00Z",
+		] {
+			assert_eq!(
+				format_timestamp(parse_timestamp(timestamp).unwrap()),
+				timestamp
+			);
+		}
+	}
+}
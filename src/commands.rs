@@ -0,0 +1,479 @@
+use std::{collections::BTreeMap, env, fs, path::Path};
+
+#[cfg(feature = "admin")]
+use serde::Deserialize;
+
+#[cfg(feature = "admin")]
+use crate::search::search;
+use crate::{
+	clock::format_timestamp,
+	environment::{is_secret, read_env_file, ENV_PREFIX, HIDDEN_VALUE},
+	events::{decode, list_segments},
+	lint::{Linter, Severity},
+	mock,
+	purge::Purger,
+	signing::{Signer, SIGNED_TOKEN_QUERY_NAME},
+	uri_mappings::UriMappings,
+};
+
+/// Print the signed path for each of the private paths given, as a full URL if the canonical one
+/// is known
+pub fn sign(
+	paths: &[String],
+	signer: Option<Signer>,
+	canonical: Option<&str>,
+) -> Result<(), String> {
+	let signer = signer.ok_or("A signing secret must be set to sign paths")?;
+	if paths.is_empty() {
+		return Err("No paths were given to sign".to_string());
+	}
+
+	for path in paths {
+		println!(
+			"{}/{path}?{SIGNED_TOKEN_QUERY_NAME}={}",
+			canonical.unwrap_or_default(),
+			signer.sign(path)
+		);
+	}
+	Ok(())
+}
+
+/// Print anything suspicious about the targets, keyed by the variable they were set with. Fails
+/// if anything was an error
+pub fn check(targets: &[(String, String)], linter: &Linter) -> Result<(), String> {
+	let mut errors = 0;
+	let mut warnings = 0;
+	for (name, target) in targets {
+		for finding in linter.lint(target) {
+			println!("{} {name}: {}", finding.severity, finding.message);
+			match finding.severity {
+				Severity::Error => errors += 1,
+				Severity::Warning => warnings += 1,
+				Severity::Off => {}
+			}
+		}
+	}
+
+	println!(
+		"Checked {} targets, found {errors} errors and {warnings} warnings",
+		targets.len()
+	);
+	if errors > 0 {
+		return Err("Some targets have errors".to_string());
+	}
+	Ok(())
+}
+
+/// The standard links listed by the admin API
+#[cfg(feature = "admin")]
+#[derive(Deserialize)]
+struct ListedLinks {
+	standard: Vec<ListedLink>,
+}
+
+#[cfg(feature = "admin")]
+#[derive(Deserialize)]
+struct ListedLink {
+	path: String,
+	target: String,
+	short_url: Option<String>,
+}
+
+/// Find the link that best matches the query, from the instance's admin API, and open it in the
+/// default browser
+#[cfg(feature = "admin")]
+pub async fn open(query: &[String], instance: &str, token: Option<String>) -> Result<(), String> {
+	let token = token.ok_or("The admin token must be set to find links")?;
+	if query.is_empty() {
+		return Err("Give the link to open".to_string());
+	}
+	let query = query.join(" ");
+
+	let links: ListedLinks = reqwest::Client::new()
+		.get(format!("{instance}/api/links"))
+		.bearer_auth(token)
+		.send()
+		.await
+		.and_then(reqwest::Response::error_for_status)
+		.map_err(|error| format!("Could not list the links, because {error}"))?
+		.json()
+		.await
+		.map_err(|error| format!("Could not read the links, because {error}"))?;
+
+	let best = search(
+		&query,
+		links.standard.iter().map(|link| link.path.as_str()),
+		1,
+	);
+	let link = best
+		.first()
+		.and_then(|path| links.standard.iter().find(|link| link.path == *path))
+		.ok_or_else(|| format!("No links match '{query}'"))?;
+
+	// Opening the short link keeps the hit counted, so prefer it when it's known
+	let url = link.short_url.as_deref().unwrap_or(&link.target);
+	println!("Opening /{} ({url})", link.path);
+	open_in_browser(url)
+}
+
+/// Open the URL with whatever the system uses for links
+#[cfg(feature = "admin")]
+fn open_in_browser(url: &str) -> Result<(), String> {
+	let mut command = if cfg!(target_os = "macos") {
+		std::process::Command::new("open")
+	} else if cfg!(windows) {
+		let mut command = std::process::Command::new("cmd");
+		command.args(["/C", "start", ""]);
+		command
+	} else {
+		std::process::Command::new("xdg-open")
+	};
+	let status = command
+		.arg(url)
+		.status()
+		.map_err(|error| format!("Could not open the browser, because {error}"))?;
+	if !status.success() {
+		return Err(format!(
+			"Could not open the browser, it exited with {status}"
+		));
+	}
+	Ok(())
+}
+
+/// Print a `go` shell function, for Bash or Zsh, that opens links from the instance
+pub fn shell_helper(instance: &str) {
+	print!("{}", shell_function(instance));
+}
+
+/// Write the `go` shell function for the instance. `go <key>` opens the link and `go -p <key>`
+/// prints where it goes
+fn shell_function(instance: &str) -> String {
+	let instance = format!("'{}'", instance.replace('\'', "'\\''"));
+	format!(
+		r#"go() {{
+	local print=
+	if [ "$1" = "-p" ]; then
+		print=1
+		shift
+	fi
+	if [ -z "$1" ]; then
+		echo "Usage: go [-p] <key>" >&2
+		return 1
+	fi
+
+	local url={instance}/"$1"
+	if [ -n "$print" ]; then
+		curl -s -o /dev/null -w '%{{redirect_url}}\n' "$url"
+	elif [ "$(uname)" = "Darwin" ]; then
+		open "$url"
+	else
+		xdg-open "$url" >/dev/null 2>&1
+	fi
+}}
+"#
+	)
+}
+
+/// Purge the short URLs of the keys from the CDN, after their mappings were changed
+pub async fn purge(
+	keys: &[String],
+	purger: Option<Purger>,
+	canonical: Option<&str>,
+) -> Result<(), String> {
+	let purger = purger.ok_or("A CDN and its token must be set to purge links")?;
+	let canonical = canonical.ok_or("The canonical URL must be set to know what to purge")?;
+	if keys.is_empty() {
+		return Err("No keys were given to purge".to_string());
+	}
+
+	let urls: Vec<String> = keys
+		.iter()
+		.map(|key| format!("{canonical}/{}", key.trim_start_matches('/')))
+		.collect();
+	purger.purge(&urls).await?;
+	for url in &urls {
+		println!("Purged {url}");
+	}
+	Ok(())
+}
+
+/// Print the Grafana dashboard for the metrics that are exposed
+pub fn dashboards() {
+	print!("{}", std::include_str!("../assets/grafana-dashboard.json"));
+}
+
+/// Print the configuration that was added, removed, or changed between two `.env` files, or
+/// between the environment and a file
+pub fn diff(paths: &[String]) -> Result<(), String> {
+	let (old, new) = match paths {
+		[new] => (
+			env::vars_os()
+				.filter_map(|(name, value)| {
+					let name = name.into_string().ok()?;
+					name.starts_with(ENV_PREFIX)
+						.then(|| (name, value.to_string_lossy().into_owned()))
+				})
+				.collect(),
+			read_config_file(new)?,
+		),
+		[old, new] => (read_config_file(old)?, read_config_file(new)?),
+		_ => return Err("Give the new file, or the old and new files, to diff".to_string()),
+	};
+
+	for line in diff_lines(&old, &new) {
+		println!("{line}");
+	}
+	Ok(())
+}
+
+/// Serve the redirects in a fixture file, given with `--from`, and nothing else. The port can be
+/// given with `--port`
+pub fn mock(args: &[String], default_port: u16) -> Result<(), String> {
+	let mut from = None;
+	let mut port = default_port;
+	let mut args = args.iter();
+	while let Some(arg) = args.next() {
+		match (arg.as_str(), args.next()) {
+			("--from", Some(path)) => from = Some(path),
+			("--port", Some(value)) => {
+				port = value
+					.parse()
+					.map_err(|_| format!("'{value}' isn't a port"))?;
+			}
+			_ => return Err(format!("Unknown argument '{arg}'")),
+		}
+	}
+	let from = from.ok_or("Give the fixture file with --from")?;
+
+	let json = fs::read_to_string(from)
+		.map_err(|error| format!("Could not read '{from}', because {error}"))?;
+	let fixtures = mock::parse_fixtures(&json)
+		.map_err(|error| format!("Could not load '{from}', because {error}"))?;
+	println!("Loaded {} fixtures from {from}", fixtures.len());
+
+	tokio::runtime::Builder::new_current_thread()
+		.enable_all()
+		.build()
+		.map_err(|error| format!("Could not start the runtime, because {error}"))?
+		.block_on(mock::serve(fixtures, port))
+}
+
+/// Print the redirects in an event log, given either one segment or the whole log directory
+pub fn events(paths: &[String]) -> Result<(), String> {
+	let [path] = paths else {
+		return Err("Give the event log directory or segment to print".to_string());
+	};
+	let path = Path::new(path);
+	let segments = if path.is_dir() {
+		list_segments(path)
+			.map_err(|error| format!("Could not list '{}', because {error}", path.display()))?
+	} else {
+		vec![path.to_path_buf()]
+	};
+
+	for segment in segments {
+		let bytes = fs::read(&segment)
+			.map_err(|error| format!("Could not read '{}', because {error}", segment.display()))?;
+		for event in decode(&bytes) {
+			println!(
+				"{} /{} {}",
+				format_timestamp(event.at / 1000),
+				event.path,
+				event.target
+			);
+		}
+	}
+	Ok(())
+}
+
+/// Run the request paths of an access log against the mappings, and print the ones that would
+/// now be missing or that didn't used to resolve
+pub fn replay(paths: &[String], mappings: &UriMappings) -> Result<(), String> {
+	let [path] = paths else {
+		return Err("Give the access log to replay".to_string());
+	};
+	let log = fs::read_to_string(path)
+		.map_err(|error| format!("Could not read '{path}', because {error}"))?;
+
+	let requests = count_requests(&log);
+	let lines = replay_lines(&requests, mappings);
+	for line in &lines {
+		println!("{line}");
+	}
+	println!(
+		"Replayed {} paths, {} would resolve differently",
+		requests.len(),
+		lines.len()
+	);
+	Ok(())
+}
+
+/// Count the requests for each path in an access log, with the status of the last one
+fn count_requests(log: &str) -> BTreeMap<String, (usize, u16)> {
+	let mut requests = BTreeMap::new();
+	for (path, status) in log.lines().filter_map(parse_log_line) {
+		let (hits, last) = requests.entry(path).or_insert((0, status));
+		*hits += 1;
+		*last = status;
+	}
+	requests
+}
+
+/// Find the path and status of a request in a line of an access log, in the common or combined
+/// log format
+fn parse_log_line(line: &str) -> Option<(String, u16)> {
+	let (_, rest) = line.split_once('"')?;
+	let (request, rest) = rest.split_once('"')?;
+	let mut request = request.split_whitespace();
+	let (_method, target) = (request.next()?, request.next()?);
+	let status = rest.split_whitespace().next()?.parse().ok()?;
+
+	let path = target.split(['?', '#']).next()?.strip_prefix('/')?;
+	// The index page and assets aren't links
+	if path.is_empty() || path.starts_with("static/") {
+		return None;
+	}
+	Some((path.to_string(), status))
+}
+
+/// Describe each path, sorted by path, that used to redirect but would now be missing, or that
+/// used to be missing but would now redirect
+fn replay_lines(requests: &BTreeMap<String, (usize, u16)>, mappings: &UriMappings) -> Vec<String> {
+	requests
+		.iter()
+		.filter_map(|(path, (hits, status))| {
+			let resolved = mappings.match_with_options(path).ok();
+			match (resolved, *status) {
+				(None, 300..=399) => Some(format!(
+					"- /{path}: redirected {hits} times, but would now be missing"
+				)),
+				(Some((uri, _)), 404) => Some(format!(
+					"+ /{path}: was missing {hits} times, but would now go to {uri}"
+				)),
+				_ => None,
+			}
+		})
+		.collect()
+}
+
+/// Read the configuration out of a `.env` file
+fn read_config_file(path: &str) -> Result<BTreeMap<String, String>, String> {
+	Ok(read_env_file(Path::new(path))?
+		.into_iter()
+		.filter(|(name, _)| name.starts_with(ENV_PREFIX))
+		.collect())
+}
+
+/// Describe each difference, sorted by name
+fn diff_lines(old: &BTreeMap<String, String>, new: &BTreeMap<String, String>) -> Vec<String> {
+	let show = |name: &str, value: &str| {
+		if is_secret(name) {
+			HIDDEN_VALUE.to_string()
+		} else {
+			value.to_string()
+		}
+	};
+
+	let mut names: Vec<&String> = old.keys().chain(new.keys()).collect();
+	names.sort();
+	names.dedup();
+	names
+		.into_iter()
+		.filter_map(|name| match (old.get(name), new.get(name)) {
+			(None, Some(value)) => Some(format!("+ {name}={}", show(name, value))),
+			(Some(value), None) => Some(format!("- {name}={}", show(name, value))),
+			(Some(old), Some(new)) if old != new => Some(format!(
+				"~ {name}: {} -> {}",
+				show(name, old),
+				show(name, new)
+			)),
+			_ => None,
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn replay_access_log() {
+		use std::{collections::HashMap, str::FromStr};
+
+		use axum::http::Uri;
+
+		let log = r#"203.0.113.1 - - [10/Oct/2024:13:55:36 +0000] "GET /docs HTTP/1.1" 307 0
+203.0.113.1 - - [10/Oct/2024:13:55:37 +0000] "GET /old?utm_source=mail HTTP/1.1" 307 0 "-" "curl/8.0"
+203.0.113.2 - - [10/Oct/2024:13:55:38 +0000] "GET /old HTTP/1.1" 307 0
+203.0.113.2 - - [10/Oct/2024:13:55:39 +0000] "GET /new HTTP/1.1" 404 512
+203.0.113.2 - - [10/Oct/2024:13:55:40 +0000] "GET / HTTP/1.1" 200 512
+203.0.113.2 - - [10/Oct/2024:13:55:41 +0000] "GET /static/style.css HTTP/1.1" 200 512
+not a log line"#;
+		let requests = count_requests(log);
+		assert_eq!(requests.len(), 3);
+
+		let mappings = UriMappings::new(
+			HashMap::from([
+				(
+					"docs".to_string(),
+					Uri::from_str("https://example.com/docs").unwrap(),
+				),
+				(
+					"new".to_string(),
+					Uri::from_str("https://example.com/new").unwrap(),
+				),
+			]),
+			Vec::new(),
+		);
+		assert_eq!(
+			replay_lines(&requests, &mappings),
+			vec![
+				"+ /new: was missing 1 times, but would now go to https://example.com/new",
+				"- /old: redirected 2 times, but would now be missing",
+			]
+		);
+	}
+
+	#[test]
+	fn write_shell_function() {
+		let function = shell_function("https://go.example.com");
+		assert!(function.starts_with("go() {\n"));
+		assert!(function.contains("local url='https://go.example.com'/\"$1\"\n"));
+		assert!(function.contains("-w '%{redirect_url}\\n'"));
+
+		let function = shell_function("http://it's");
+		assert!(function.contains("local url='http://it'\\''s'/\"$1\"\n"));
+	}
+
+	#[test]
+	fn diff_config() {
+		let vars = |vars: &[(&str, &str)]| {
+			vars.iter()
+				.map(|(name, value)| ((*name).to_string(), (*value).to_string()))
+				.collect::<BTreeMap<_, _>>()
+		};
+		let old = vars(&[
+			("URSHORT_STANDARD_URI_docs", "https://example.com/docs"),
+			("URSHORT_STANDARD_URI_old", "https://example.com/old"),
+			("URSHORT_ADMIN_TOKEN", "first"),
+			("URSHORT_PORT", "8080"),
+		]);
+		let new = vars(&[
+			("URSHORT_STANDARD_URI_docs", "https://docs.example.com/"),
+			("URSHORT_STANDARD_URI_new", "https://example.com/new"),
+			("URSHORT_ADMIN_TOKEN", "second"),
+			("URSHORT_PORT", "8080"),
+		]);
+
+		assert_eq!(
+			diff_lines(&old, &new),
+			vec![
+				"~ URSHORT_ADMIN_TOKEN: (hidden) -> (hidden)",
+				"~ URSHORT_STANDARD_URI_docs: https://example.com/docs -> https://docs.example.com/",
+				"+ URSHORT_STANDARD_URI_new=https://example.com/new",
+				"- URSHORT_STANDARD_URI_old=https://example.com/old",
+			]
+		);
+		assert!(diff_lines(&old, &old).is_empty());
+	}
+}
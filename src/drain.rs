@@ -0,0 +1,150 @@
+use std::{sync::Arc, time::Duration};
+
+use axum::{
+	http::{header, HeaderValue, Request, StatusCode},
+	middleware::{self, Next},
+	response::{IntoResponse, Response},
+	Router,
+};
+use tokio::{signal, sync::watch};
+
+use crate::listeners::Bound;
+
+/// Whether the server is shutting down, so connections are closed instead of kept alive
+struct Drain {
+	sender: watch::Sender<bool>,
+	receiver: watch::Receiver<bool>,
+}
+
+impl Default for Drain {
+	fn default() -> Drain {
+		let (sender, receiver) = watch::channel(false);
+		Drain { sender, receiver }
+	}
+}
+
+impl Drain {
+	fn is_draining(&self) -> bool {
+		*self.receiver.borrow()
+	}
+
+	/// Start draining, so the listeners stop taking new connections
+	fn start(&self, timeout: Duration) {
+		let _ = self.sender.send(true);
+		println!(
+			"Shutting down, after the requests in flight finish or {}s pass",
+			timeout.as_secs()
+		);
+		println!();
+	}
+
+	/// Wait until draining starts
+	async fn started(&self) {
+		let mut receiver = self.receiver.clone();
+		while !*receiver.borrow() {
+			if receiver.changed().await.is_err() {
+				return;
+			}
+		}
+	}
+}
+
+/// Serve the app on every listener, and the admin app on the admin listeners, until the process
+/// is asked to stop. Then stop taking new connections and wait up to the timeout for the
+/// requests in flight to finish
+pub async fn serve(app: Router, admin: Option<Router>, listeners: Vec<Bound>, timeout: Duration) {
+	let drain = Arc::new(Drain::default());
+	let close_connections = |app: Router| {
+		let closing = drain.clone();
+		app.layer(middleware::from_fn(move |request, next| {
+			close_when_draining(request, next, closing.clone())
+		}))
+	};
+	let app = close_connections(app);
+	let admin = close_connections(admin.unwrap_or_default());
+
+	let servers: Vec<_> = listeners
+		.into_iter()
+		.map(|listener| {
+			let app = if listener.is_admin() {
+				admin.clone()
+			} else {
+				app.clone()
+			};
+			let drain = drain.clone();
+			tokio::spawn(listener.serve(app, async move { drain.started().await }))
+		})
+		.collect();
+	let finished = async {
+		for server in servers {
+			if let Ok(Err(error)) = server.await {
+				println!("Stopped listening, because {error}");
+				println!();
+			}
+		}
+	};
+	let timed_out = async {
+		shutdown_signal().await;
+		drain.start(timeout);
+		tokio::time::sleep(timeout).await;
+	};
+	tokio::select! {
+		() = finished => {},
+		() = timed_out => {
+			println!("Stopped with requests still in flight, because they took longer than {}s", timeout.as_secs());
+			println!();
+		}
+	}
+}
+
+/// Tell clients to close their connection while draining, so a load balancer sends their next
+/// request to another instance. Requests that start while draining are turned away
+async fn close_when_draining<B>(request: Request<B>, next: Next<B>, drain: Arc<Drain>) -> Response {
+	let mut response = if drain.is_draining() {
+		StatusCode::SERVICE_UNAVAILABLE.into_response()
+	} else {
+		next.run(request).await
+	};
+	if drain.is_draining() {
+		response
+			.headers_mut()
+			.insert(header::CONNECTION, HeaderValue::from_static("close"));
+	}
+	response
+}
+
+/// Wait for Ctrl-C or, on Unix, `SIGTERM` like container runtimes send
+async fn shutdown_signal() {
+	#[cfg(unix)]
+	match signal::unix::signal(signal::unix::SignalKind::terminate()) {
+		Ok(mut terminate) => {
+			tokio::select! {
+				_ = signal::ctrl_c() => {},
+				_ = terminate.recv() => {},
+			}
+			return;
+		}
+		Err(error) => {
+			println!("Can only shut down gracefully with Ctrl-C, because {error}");
+			println!();
+		}
+	}
+	let _ = signal::ctrl_c().await;
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[tokio::test]
+	async fn start_draining() {
+		let drain = Drain::default();
+		assert!(!drain.is_draining());
+
+		drain.start(Duration::from_secs(30));
+		assert!(drain.is_draining());
+		tokio::time::timeout(Duration::from_secs(1), drain.started())
+			.await
+			.unwrap();
+	}
+}
@@ -0,0 +1,194 @@
+use std::{
+	collections::HashMap,
+	net::{IpAddr, Ipv6Addr},
+	sync::Mutex,
+	time::{Duration, Instant},
+};
+
+/// The most clients to track, so a flood of addresses can't use up all the memory
+const MAX_TRACKED_CLIENTS: usize = 100_000;
+
+/// Limits for how many paths without a mapping a single client can request
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GuardSettings {
+	/// Misses allowed within the window before responses are slowed down
+	pub miss_limit: u32,
+	/// How long misses are remembered for
+	pub window: Duration,
+	/// Delay added for every miss past the limit
+	pub delay: Duration,
+	/// Misses within the window that will get the client banned
+	pub ban_limit: u32,
+	/// How long a ban lasts
+	pub ban: Duration,
+}
+
+/// What should happen to a client after a miss
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Penalty {
+	None,
+	Delay(Duration),
+	Banned,
+}
+
+/// Tracks clients that rapidly request paths with no mapping, to protect private codes from being
+/// discovered by brute force
+pub struct EnumerationGuard {
+	settings: GuardSettings,
+	clients: Mutex<HashMap<IpAddr, ClientMisses>>,
+}
+
+struct ClientMisses {
+	first_miss: Instant,
+	misses: u32,
+	banned_until: Option<Instant>,
+}
+
+impl EnumerationGuard {
+	/// Create a new `EnumerationGuard` that has not seen any clients
+	pub fn new(settings: GuardSettings) -> EnumerationGuard {
+		EnumerationGuard {
+			settings,
+			clients: Mutex::new(HashMap::new()),
+		}
+	}
+
+	/// How many clients are being tracked for their misses
+	#[cfg(feature = "admin")]
+	pub fn tracked_clients(&self) -> usize {
+		self.clients.lock().unwrap().len()
+	}
+
+	/// Check if the client is currently banned
+	pub fn is_banned(&self, client: IpAddr, now: Instant) -> bool {
+		let clients = self.clients.lock().unwrap();
+		matches!(clients.get(&tracked_as(client)), Some(ClientMisses { banned_until: Some(until), .. }) if *until > now)
+	}
+
+	/// Record that the client requested a path with no mapping and get the penalty for it
+	pub fn record_miss(&self, client: IpAddr, now: Instant) -> Penalty {
+		let client = tracked_as(client);
+		let mut clients = self.clients.lock().unwrap();
+		if clients.len() >= MAX_TRACKED_CLIENTS && !clients.contains_key(&client) {
+			clients.retain(|_, record| self.is_remembered(record, now));
+			if clients.len() >= MAX_TRACKED_CLIENTS {
+				clients.clear();
+			}
+		}
+		let record = clients.entry(client).or_insert(ClientMisses {
+			first_miss: now,
+			misses: 0,
+			banned_until: None,
+		});
+
+		// Start counting again once the window has passed
+		if now.duration_since(record.first_miss) > self.settings.window {
+			record.first_miss = now;
+			record.misses = 0;
+		}
+		record.misses += 1;
+
+		if record.misses >= self.settings.ban_limit {
+			record.banned_until = Some(now + self.settings.ban);
+			return Penalty::Banned;
+		}
+
+		if record.misses > self.settings.miss_limit {
+			return Penalty::Delay(
+				self.settings.delay * (record.misses - self.settings.miss_limit),
+			);
+		}
+
+		Penalty::None
+	}
+
+	/// Forget clients whose misses and bans have both expired
+	pub fn prune(&self, now: Instant) {
+		let mut clients = self.clients.lock().unwrap();
+		clients.retain(|_, record| self.is_remembered(record, now));
+	}
+
+	/// Whether the client's misses or ban haven't expired yet
+	fn is_remembered(&self, record: &ClientMisses, now: Instant) -> bool {
+		now.duration_since(record.first_miss) <= self.settings.window
+			|| matches!(record.banned_until, Some(until) if until > now)
+	}
+}
+
+/// The address a client is tracked by. IPv6 clients are tracked by their /64, since anyone with
+/// one usually has all the addresses in it
+fn tracked_as(client: IpAddr) -> IpAddr {
+	match client {
+		IpAddr::V6(address) => match address.to_ipv4_mapped() {
+			Some(address) => IpAddr::V4(address),
+			None => IpAddr::V6(Ipv6Addr::from(u128::from(address) & !u128::from(u64::MAX))),
+		},
+		IpAddr::V4(_) => client,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::net::Ipv4Addr;
+
+	use super::*;
+
+	#[test]
+	fn penalize_repeated_misses() {
+		let guard = EnumerationGuard::new(GuardSettings {
+			miss_limit: 2,
+			window: Duration::from_mins(1),
+			delay: Duration::from_millis(100),
+			ban_limit: 5,
+			ban: Duration::from_mins(5),
+		});
+		let client = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+		let other_client = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2));
+		let now = Instant::now();
+
+		// Misses under the limit aren't penalized
+		assert_eq!(guard.record_miss(client, now), Penalty::None);
+		assert_eq!(guard.record_miss(client, now), Penalty::None);
+
+		// Delays grow with every miss past the limit
+		assert_eq!(
+			guard.record_miss(client, now),
+			Penalty::Delay(Duration::from_millis(100))
+		);
+		assert_eq!(
+			guard.record_miss(client, now),
+			Penalty::Delay(Duration::from_millis(200))
+		);
+
+		// Other clients are tracked separately
+		assert_eq!(guard.record_miss(other_client, now), Penalty::None);
+
+		// Too many misses gets the client banned until the ban runs out
+		assert!(!guard.is_banned(client, now));
+		assert_eq!(guard.record_miss(client, now), Penalty::Banned);
+		assert!(guard.is_banned(client, now));
+		assert!(!guard.is_banned(other_client, now));
+		assert!(!guard.is_banned(client, now + Duration::from_secs(301)));
+
+		// Misses are forgotten after the window
+		let later = now + Duration::from_secs(61);
+		assert_eq!(guard.record_miss(other_client, later), Penalty::None);
+
+		// Only expired clients are pruned
+		guard.prune(now + Duration::from_secs(200));
+		assert!(guard.is_banned(client, now + Duration::from_secs(200)));
+		assert_eq!(guard.clients.lock().unwrap().len(), 1);
+		guard.prune(now + Duration::from_secs(400));
+		assert_eq!(guard.clients.lock().unwrap().len(), 0);
+
+		// Addresses in the same IPv6 /64 count as one client
+		let first: IpAddr = "2001:db8:1:2::1".parse().unwrap();
+		let second: IpAddr = "2001:db8:1:2:ffff::9".parse().unwrap();
+		for _ in 0..4 {
+			guard.record_miss(first, now);
+		}
+		assert_eq!(guard.record_miss(second, now), Penalty::Banned);
+		assert!(guard.is_banned(first, now));
+		assert!(!guard.is_banned("2001:db8:1:3::1".parse().unwrap(), now));
+	}
+}
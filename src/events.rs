@@ -0,0 +1,277 @@
+use std::{
+	collections::HashMap,
+	fs::{self, File, OpenOptions},
+	io::{self, Write},
+	path::{Path, PathBuf},
+	thread,
+	time::{SystemTime, UNIX_EPOCH},
+};
+
+use tokio::sync::mpsc::{self, Receiver, Sender};
+
+/// How many events can wait to be written before new ones are dropped
+const QUEUE_SIZE: usize = 4096;
+/// The extension of the segment files, which are named after when they were started
+const SEGMENT_EXTENSION: &str = "events";
+/// How many paths are sampled separately. The rest are sampled together
+const MAX_SAMPLED_PATHS: usize = 1000;
+
+/// A redirect that was served
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Event {
+	/// Milliseconds since the Unix epoch
+	pub at: u64,
+	pub path: String,
+	pub target: String,
+}
+
+/// Records redirects to an append-only log on disk, without holding up the request
+pub struct EventLog {
+	sender: Sender<Event>,
+}
+
+impl EventLog {
+	/// Start writing events to segments in the directory, rotating to a new one once a segment
+	/// reaches its size and only keeping the newest segments. Only 1 in every `sample_rate`
+	/// events for each path is written
+	pub fn start(
+		dir: PathBuf,
+		segment_size: u64,
+		keep: usize,
+		sample_rate: u64,
+	) -> io::Result<EventLog> {
+		fs::create_dir_all(&dir)?;
+		let (sender, receiver) = mpsc::channel(QUEUE_SIZE);
+		let segments = Segments {
+			dir,
+			segment_size,
+			keep,
+			current: None,
+		};
+		let sampler = Sampler::new(sample_rate);
+		thread::Builder::new()
+			.name("event-log".to_string())
+			.spawn(move || write_events(receiver, segments, sampler))?;
+		Ok(EventLog { sender })
+	}
+
+	/// Queue the redirect to be written. It's dropped if the writer has fallen behind
+	pub fn record(&self, path: &str, target: &str) {
+		let at = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.map_or(0, |since| {
+				u64::try_from(since.as_millis()).unwrap_or(u64::MAX)
+			});
+		let _ = self.sender.try_send(Event {
+			at,
+			path: path.to_string(),
+			target: target.to_string(),
+		});
+	}
+}
+
+fn write_events(mut receiver: Receiver<Event>, mut segments: Segments, mut sampler: Sampler) {
+	while let Some(event) = receiver.blocking_recv() {
+		if !sampler.sample(&event.path) {
+			continue;
+		}
+		if let Err(error) = segments.write(&encode(&event)) {
+			println!("Event was not logged, because {error}");
+			segments.current = None;
+		}
+	}
+}
+
+/// Picks which events are written, so the hottest links don't fill the log
+struct Sampler {
+	rate: u64,
+	/// How many events have been seen for each path
+	seen: HashMap<String, u64>,
+	/// How many events have been seen for the paths past the most that are sampled separately
+	others: u64,
+}
+
+impl Sampler {
+	fn new(rate: u64) -> Sampler {
+		Sampler {
+			rate: rate.max(1),
+			seen: HashMap::new(),
+			others: 0,
+		}
+	}
+
+	/// Whether the event for the path should be written. The first event for a path always is
+	fn sample(&mut self, path: &str) -> bool {
+		if self.rate == 1 {
+			return true;
+		}
+		let seen = if self.seen.contains_key(path) || self.seen.len() < MAX_SAMPLED_PATHS {
+			self.seen.entry(path.to_string()).or_insert(0)
+		} else {
+			&mut self.others
+		};
+		let sampled = *seen % self.rate == 0;
+		*seen += 1;
+		sampled
+	}
+}
+
+/// The segment files in the log directory, with the one being written to
+struct Segments {
+	dir: PathBuf,
+	segment_size: u64,
+	keep: usize,
+	current: Option<(File, u64)>,
+}
+
+impl Segments {
+	fn write(&mut self, record: &[u8]) -> io::Result<()> {
+		let length = record.len() as u64;
+		let full = self
+			.current
+			.as_ref()
+			.is_none_or(|(_, size)| *size > 0 && size + length > self.segment_size);
+		if full {
+			self.rotate(SystemTime::now())?;
+		}
+
+		if let Some((file, size)) = &mut self.current {
+			file.write_all(record)?;
+			*size += length;
+		}
+		Ok(())
+	}
+
+	/// Start a new segment, removing the oldest ones past how many are kept
+	fn rotate(&mut self, now: SystemTime) -> io::Result<()> {
+		let started = now
+			.duration_since(UNIX_EPOCH)
+			.map_or(0, |since| since.as_millis());
+		let path = self.dir.join(format!("{started:020}.{SEGMENT_EXTENSION}"));
+		let file = OpenOptions::new().create(true).append(true).open(&path)?;
+		let size = file.metadata()?.len();
+		self.current = Some((file, size));
+
+		let segments = list_segments(&self.dir)?;
+		for old in segments
+			.iter()
+			.take(segments.len().saturating_sub(self.keep.max(1)))
+		{
+			fs::remove_file(old)?;
+		}
+		Ok(())
+	}
+}
+
+/// The segments in the directory, oldest first
+pub fn list_segments(dir: &Path) -> io::Result<Vec<PathBuf>> {
+	let mut segments: Vec<PathBuf> = fs::read_dir(dir)?
+		.filter_map(Result::ok)
+		.map(|entry| entry.path())
+		.filter(|path| {
+			path.extension()
+				.is_some_and(|extension| extension == SEGMENT_EXTENSION)
+		})
+		.collect();
+	segments.sort();
+	Ok(segments)
+}
+
+/// Encode an event as a record: the time as a little-endian `u64`, then the path and target,
+/// each as a little-endian `u16` length followed by that many bytes of UTF-8
+pub fn encode(event: &Event) -> Vec<u8> {
+	let mut record = Vec::with_capacity(12 + event.path.len() + event.target.len());
+	record.extend_from_slice(&event.at.to_le_bytes());
+	for text in [&event.path, &event.target] {
+		let bytes = &text.as_bytes()[..text.len().min(usize::from(u16::MAX))];
+		#[allow(clippy::cast_possible_truncation)]
+		record.extend_from_slice(&(bytes.len() as u16).to_le_bytes());
+		record.extend_from_slice(bytes);
+	}
+	record
+}
+
+/// Decode the records in a segment. A record cut off at the end, like one still being written,
+/// is left out
+pub fn decode(mut bytes: &[u8]) -> Vec<Event> {
+	let mut events = Vec::new();
+	while let Some(event) = decode_event(&mut bytes) {
+		events.push(event);
+	}
+	events
+}
+
+fn decode_event(bytes: &mut &[u8]) -> Option<Event> {
+	let at = u64::from_le_bytes(take(bytes, 8)?.try_into().ok()?);
+	let path = take_text(bytes)?;
+	let target = take_text(bytes)?;
+	Some(Event { at, path, target })
+}
+
+fn take_text(bytes: &mut &[u8]) -> Option<String> {
+	let length = u16::from_le_bytes(take(bytes, 2)?.try_into().ok()?);
+	Some(String::from_utf8_lossy(take(bytes, usize::from(length))?).into_owned())
+}
+
+fn take<'a>(bytes: &mut &'a [u8], length: usize) -> Option<&'a [u8]> {
+	if bytes.len() < length {
+		return None;
+	}
+	let (taken, rest) = bytes.split_at(length);
+	*bytes = rest;
+	Some(taken)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+
+	use super::*;
+
+	#[test]
+	fn rotate_segments() {
+		let event = Event {
+			at: 1_700_000_000_000,
+			path: "blog".to_string(),
+			target: "https://example.com/blog".to_string(),
+		};
+		let record = encode(&event);
+		assert_eq!(decode(&record), vec![event.clone()]);
+		assert_eq!(decode(&record[..record.len() - 1]), vec![]);
+
+		let dir = std::env::temp_dir().join(format!("urshort-events-{}", std::process::id()));
+		let _ = fs::remove_dir_all(&dir);
+		fs::create_dir_all(&dir).unwrap();
+		let mut segments = Segments {
+			dir: dir.clone(),
+			segment_size: record.len() as u64 * 2,
+			keep: 2,
+			current: None,
+		};
+		for second in 0..3 {
+			segments
+				.rotate(UNIX_EPOCH + Duration::from_secs(second))
+				.unwrap();
+			segments.write(&record).unwrap();
+			segments.write(&record).unwrap();
+		}
+		// The third write would go past the size, so it starts a segment of its own
+		segments.write(&record).unwrap();
+
+		let kept = list_segments(&dir).unwrap();
+		assert_eq!(kept.len(), 2);
+		assert_eq!(decode(&fs::read(&kept[0]).unwrap()).len(), 2);
+		assert_eq!(decode(&fs::read(&kept[1]).unwrap()).len(), 1);
+		fs::remove_dir_all(&dir).unwrap();
+	}
+
+	#[test]
+	fn sample_hot_links() {
+		let mut sampler = Sampler::new(3);
+		let written: Vec<bool> = ["hot", "hot", "cold", "hot", "hot"]
+			.into_iter()
+			.map(|path| sampler.sample(path))
+			.collect();
+		assert_eq!(written, vec![true, false, true, false, true]);
+	}
+}
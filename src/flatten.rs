@@ -0,0 +1,119 @@
+use std::{collections::HashMap, str::FromStr, sync::RwLock, time::Duration};
+
+use axum::http::{header, Uri};
+use reqwest::{redirect, Client, Url};
+
+/// How long to wait on each hop before giving up on the target
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+/// The most redirects to follow from a target, so loops end
+pub const MAX_HOPS: usize = 5;
+
+/// Finds where targets that redirect end up, so links can be sent there directly
+pub struct Flattener {
+	targets: Vec<Uri>,
+	client: Client,
+	finals: RwLock<HashMap<Uri, Uri>>,
+}
+
+impl Flattener {
+	/// Create a new `Flattener` for the targets, which aren't flattened until checked
+	pub fn new(targets: Vec<Uri>) -> Result<Flattener, String> {
+		let client = Client::builder()
+			.redirect(redirect::Policy::none())
+			.timeout(CHECK_TIMEOUT)
+			.build()
+			.map_err(|error| error.to_string())?;
+
+		Ok(Flattener {
+			targets,
+			client,
+			finals: RwLock::new(HashMap::new()),
+		})
+	}
+
+	/// Get where the target ends up, if it was found to redirect
+	pub fn get(&self, uri: &Uri) -> Option<Uri> {
+		self.finals.read().unwrap().get(uri).cloned()
+	}
+
+	/// How many targets were found to redirect
+	#[cfg(feature = "admin")]
+	pub fn flattened(&self) -> usize {
+		self.finals.read().unwrap().len()
+	}
+
+	/// Follow the redirects of every target. Targets that stop redirecting, or end up somewhere
+	/// that doesn't work, are sent to as they are again
+	pub async fn check(&self) {
+		let mut finals = HashMap::new();
+		for uri in &self.targets {
+			if let Some(destination) = self.follow(uri).await {
+				finals.insert(uri.clone(), destination);
+			}
+		}
+		*self.finals.write().unwrap() = finals;
+	}
+
+	/// Follow the redirects from the target, to where it ends up if that works
+	async fn follow(&self, uri: &Uri) -> Option<Uri> {
+		let mut current = Url::parse(&uri.to_string()).ok()?;
+		for _ in 0..=MAX_HOPS {
+			let response = self.client.head(current.clone()).send().await.ok()?;
+			let status = response.status();
+			if status.is_success() {
+				let destination = Uri::from_str(current.as_str()).ok()?;
+				return (destination != *uri).then_some(destination);
+			}
+			if !status.is_redirection() {
+				return None;
+			}
+			let location = response.headers().get(header::LOCATION)?.to_str().ok()?;
+			current = current.join(location).ok()?;
+		}
+		None
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::net::SocketAddr;
+
+	use axum::{
+		http::StatusCode,
+		response::{IntoResponse, Redirect},
+		routing::get,
+		Router,
+	};
+
+	use super::*;
+
+	#[tokio::test]
+	async fn flatten_redirects() {
+		let app = Router::new()
+			.route("/old", get(|| async { Redirect::temporary("/moved") }))
+			.route("/moved", get(|| async { Redirect::permanent("/new") }))
+			.route("/new", get(|| async { "New" }))
+			.route("/gone", get(|| async { Redirect::temporary("/missing") }))
+			.route(
+				"/missing",
+				get(|| async { StatusCode::NOT_FOUND.into_response() }),
+			)
+			.route("/loop", get(|| async { Redirect::temporary("/loop") }));
+		let server = axum::Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0)))
+			.serve(app.into_make_service());
+		let address = server.local_addr();
+		tokio::spawn(server);
+		let uri = |path: &str| Uri::from_str(&format!("http://{address}{path}")).unwrap();
+
+		let flattener =
+			Flattener::new(vec![uri("/old"), uri("/new"), uri("/gone"), uri("/loop")]).unwrap();
+		assert_eq!(flattener.get(&uri("/old")), None);
+
+		flattener.check().await;
+		assert_eq!(flattener.get(&uri("/old")), Some(uri("/new")));
+		// Targets that don't redirect, or end up broken, are left alone
+		assert_eq!(flattener.get(&uri("/new")), None);
+		assert_eq!(flattener.get(&uri("/gone")), None);
+		assert_eq!(flattener.get(&uri("/loop")), None);
+	}
+}
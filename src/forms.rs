@@ -0,0 +1,204 @@
+use std::{collections::HashMap, fmt::Write, str::FromStr};
+
+use crate::{
+	html,
+	pages::Page,
+	resolver::{Resolution, ResolveRequest, Resolver, Target},
+	targets::TargetPolicy,
+	uri_mappings::LinkOptions,
+};
+use async_trait::async_trait;
+
+/// A redirect with `{name}` placeholders, that shows a form to fill them in until they're all
+/// given in the query
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Form {
+	template: String,
+	fields: Vec<String>,
+}
+
+impl Form {
+	/// Fill in the placeholders, if all of them have a value
+	pub fn fill(&self, values: &HashMap<String, String>) -> Option<String> {
+		let mut filled = String::with_capacity(self.template.len());
+		let mut rest = self.template.as_str();
+		while let Some(start) = rest.find('{') {
+			let end = start + rest[start..].find('}')?;
+			let value = values
+				.get(&rest[start + 1..end])
+				.filter(|x| !x.is_empty())?;
+			filled.push_str(&rest[..start]);
+			filled.push_str(&percent_encode(value));
+			rest = &rest[end + 1..];
+		}
+		filled.push_str(rest);
+		Some(filled)
+	}
+
+	/// Render the form, keeping any values that were already given
+	pub fn page(&self, title: &str, values: &HashMap<String, String>) -> Page {
+		let mut content = format!(
+			"\t<h1>{}</h1>\n\t<form method=\"get\">\n",
+			html::escape(title)
+		);
+		for field in &self.fields {
+			let value = values.get(field).map_or(String::new(), |x| html::escape(x));
+			let _ = writeln!(
+				content,
+				"\t\t<p><label>{field} <input name=\"{field}\" value=\"{value}\" required></label></p>"
+			);
+		}
+		content.push_str("\t\t<p><button type=\"submit\">Go</button></p>\n\t</form>");
+		Page::new(title, content)
+	}
+}
+
+impl FromStr for Form {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let mut fields: Vec<String> = Vec::new();
+		let mut rest = s;
+		while let Some(start) = rest.find('{') {
+			let end = start
+				+ rest[start..]
+					.find('}')
+					.ok_or_else(|| format!("'{s}' has a '{{' that isn't closed"))?;
+			let field = &rest[start + 1..end];
+			if field.is_empty()
+				|| !field
+					.chars()
+					.all(|x| x.is_ascii_alphanumeric() || x == '_' || x == '-')
+			{
+				return Err(format!("'{{{field}}}' is not a valid field name"));
+			}
+			if !fields.iter().any(|x| x == field) {
+				fields.push(field.to_string());
+			}
+			rest = &rest[end + 1..];
+		}
+
+		if fields.is_empty() {
+			return Err(format!("'{s}' has no fields, like '{{ticket}}'"));
+		}
+		Ok(Form {
+			template: s.to_string(),
+			fields,
+		})
+	}
+}
+
+/// Encode everything other than unreserved characters, so values stay inside of their part of the
+/// URI
+fn percent_encode(value: &str) -> String {
+	let mut encoded = String::with_capacity(value.len());
+	for byte in value.bytes() {
+		if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+			encoded.push(char::from(byte));
+		} else {
+			let _ = write!(encoded, "%{byte:02X}");
+		}
+	}
+	encoded
+}
+
+/// Resolves paths with a form to its redirect once it's filled in, or to the form until then
+pub struct FormResolver {
+	forms: HashMap<String, Form>,
+	/// Since the target is only known once it's filled in, it's checked then
+	targets: TargetPolicy,
+}
+
+impl FormResolver {
+	/// Create a new `FormResolver` from forms keyed by path
+	pub fn new(forms: HashMap<String, Form>, targets: TargetPolicy) -> FormResolver {
+		FormResolver { forms, targets }
+	}
+}
+
+#[async_trait]
+impl Resolver for FormResolver {
+	async fn resolve(&self, request: &ResolveRequest<'_>) -> Option<Resolution> {
+		let form = self.forms.get(request.path.as_ref())?;
+
+		let target = match form.fill(request.query) {
+			Some(filled) => Target::Redirect(self.targets.parse(&filled).ok()?),
+			None => Target::Page(form.page(&request.path, request.query)),
+		};
+		Some(Resolution {
+			target,
+			options: LinkOptions::default(),
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{
+		borrow::Cow,
+		net::{IpAddr, Ipv4Addr},
+	};
+
+	use axum::http::{HeaderMap, Uri};
+
+	use super::*;
+
+	#[tokio::test]
+	async fn fill_in_forms() {
+		let form =
+			Form::from_str("https://example.com/{project}/browse/{ticket}?from={project}").unwrap();
+		assert_eq!(form.fields, vec!["project", "ticket"]);
+
+		let mut values = HashMap::from([("ticket".to_string(), "ABC-1 &/".to_string())]);
+		assert_eq!(form.fill(&values), None);
+		let page = form.page("jira", &values).content;
+		assert!(page.contains("<input name=\"project\" value=\"\" required>"));
+		assert!(page.contains("<input name=\"ticket\" value=\"ABC-1 &amp;/\" required>"));
+
+		values.insert("project".to_string(), "core".to_string());
+		assert_eq!(
+			form.fill(&values).as_deref(),
+			Some("https://example.com/core/browse/ABC-1%20%26%2F?from=core")
+		);
+
+		// Empty values still need to be filled in
+		values.insert("project".to_string(), String::new());
+		assert_eq!(form.fill(&values), None);
+
+		assert!(Form::from_str("https://example.com/").is_err());
+		assert!(Form::from_str("https://example.com/{ticket").is_err());
+		assert!(Form::from_str("https://example.com/{<b>}").is_err());
+
+		// Filled in targets still have to be allowed
+		let resolver = FormResolver::new(
+			HashMap::from([
+				("jira".to_string(), form),
+				(
+					"run".to_string(),
+					Form::from_str("javascript:alert({code})").unwrap(),
+				),
+			]),
+			TargetPolicy::default(),
+		);
+		values.insert("project".to_string(), "core".to_string());
+		values.insert("code".to_string(), "1".to_string());
+		let headers = HeaderMap::new();
+		let resolve = |path| {
+			let request = ResolveRequest {
+				path: Cow::Borrowed(path),
+				query: &values,
+				headers: &headers,
+				client: IpAddr::V4(Ipv4Addr::LOCALHOST),
+			};
+			let resolver = &resolver;
+			async move { resolver.resolve(&request).await.map(|x| x.target) }
+		};
+		assert_eq!(
+			resolve("jira").await,
+			Some(Target::Redirect(Uri::from_static(
+				"https://example.com/core/browse/ABC-1%20%26%2F?from=core"
+			)))
+		);
+		assert_eq!(resolve("run").await, None);
+	}
+}
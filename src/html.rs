@@ -0,0 +1,85 @@
+/// Escape text so it can be safely placed inside of HTML
+pub fn escape(text: &str) -> String {
+	let mut escaped = String::with_capacity(text.len());
+	for character in text.chars() {
+		match character {
+			'&' => escaped.push_str("&amp;"),
+			'<' => escaped.push_str("&lt;"),
+			'>' => escaped.push_str("&gt;"),
+			'"' => escaped.push_str("&quot;"),
+			'\'' => escaped.push_str("&#39;"),
+			_ => escaped.push(character),
+		}
+	}
+	escaped
+}
+
+/// Add a notice to the top of the content of a page
+pub fn with_notice(content: &str, notice: &str) -> String {
+	format!(
+		"\t<p class=\"notice\" role=\"status\">{}</p>\n{content}",
+		escape(notice)
+	)
+}
+
+/// Fill in the `{{name}}` placeholders of a template with their values, in a single pass so values
+/// can't add placeholders of their own. Unknown placeholders are left as is
+pub fn fill(template: &str, values: &[(&str, &str)]) -> String {
+	let mut filled = String::with_capacity(template.len());
+	let mut rest = template;
+	while let Some(start) = rest.find("{{") {
+		filled.push_str(&rest[..start]);
+		rest = &rest[start..];
+
+		let value = rest.find("}}").and_then(|end| {
+			let name = &rest[2..end];
+			values
+				.iter()
+				.find(|(key, _)| *key == name)
+				.map(|(_, value)| (*value, end + 2))
+		});
+		if let Some((value, length)) = value {
+			filled.push_str(value);
+			rest = &rest[length..];
+		} else {
+			filled.push_str("{{");
+			rest = &rest[2..];
+		}
+	}
+	filled.push_str(rest);
+	filled
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn escape_html() {
+		assert_eq!(escape("plain text"), "plain text");
+		assert_eq!(
+			escape("<script>alert('&\"')</script>"),
+			"&lt;script&gt;alert(&#39;&amp;&quot;&#39;)&lt;/script&gt;"
+		);
+	}
+
+	#[test]
+	fn add_notice() {
+		assert_eq!(
+			with_notice("\t<h1>Hi</h1>", "Down <now>"),
+			"\t<p class=\"notice\" role=\"status\">Down &lt;now&gt;</p>\n\t<h1>Hi</h1>"
+		);
+	}
+
+	#[test]
+	fn fill_template() {
+		assert_eq!(
+			fill(
+				"<title>{{title}}</title><h1>{{title}}</h1>{{body}}{{unknown}}",
+				&[("title", "{{body}}"), ("body", "<p>Hi</p>")]
+			),
+			"<title>{{body}}</title><h1>{{body}}</h1><p>Hi</p>{{unknown}}"
+		);
+		assert_eq!(fill("{{open", &[("open", "x")]), "{{open");
+	}
+}
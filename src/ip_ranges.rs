@@ -0,0 +1,134 @@
+use std::{net::IpAddr, str::FromStr};
+
+/// A block of IP addresses in CIDR notation, such as `192.168.1.0/24`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IpRange {
+	network: IpAddr,
+	prefix: u8,
+}
+
+impl IpRange {
+	/// Check if the address is inside of the range
+	pub fn contains(&self, ip: IpAddr) -> bool {
+		match (self.network, normalize(ip)) {
+			(IpAddr::V4(network), IpAddr::V4(ip)) => {
+				let mask = u32::MAX
+					.checked_shl(32 - u32::from(self.prefix))
+					.unwrap_or(0);
+				u32::from(network) & mask == u32::from(ip) & mask
+			}
+			(IpAddr::V6(network), IpAddr::V6(ip)) => {
+				let mask = u128::MAX
+					.checked_shl(128 - u32::from(self.prefix))
+					.unwrap_or(0);
+				u128::from(network) & mask == u128::from(ip) & mask
+			}
+			_ => false,
+		}
+	}
+}
+
+impl FromStr for IpRange {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let (network, prefix) = match s.trim().split_once('/') {
+			Some((network, prefix)) => (network, Some(prefix)),
+			None => (s.trim(), None),
+		};
+
+		let network = normalize(
+			network
+				.parse::<IpAddr>()
+				.map_err(|_| format!("'{s}' is not a valid IP address"))?,
+		);
+		let max_prefix = if network.is_ipv4() { 32 } else { 128 };
+		let prefix = match prefix {
+			Some(prefix) => prefix
+				.parse::<u8>()
+				.ok()
+				.filter(|prefix| *prefix <= max_prefix)
+				.ok_or_else(|| format!("'{s}' does not have a valid prefix length"))?,
+			None => max_prefix,
+		};
+
+		Ok(IpRange { network, prefix })
+	}
+}
+
+/// A list of IP ranges, written as comma separated CIDRs
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct IpRanges(Vec<IpRange>);
+
+impl IpRanges {
+	/// Check if the address is inside of any of the ranges
+	pub fn contains(&self, ip: IpAddr) -> bool {
+		self.0.iter().any(|range| range.contains(ip))
+	}
+}
+
+impl FromStr for IpRanges {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		s.split(',')
+			.filter(|range| !range.trim().is_empty())
+			.map(IpRange::from_str)
+			.collect::<Result<Vec<_>, _>>()
+			.map(IpRanges)
+	}
+}
+
+/// Treat IPv4 addresses mapped into IPv6 as the IPv4 address they are
+fn normalize(ip: IpAddr) -> IpAddr {
+	match ip {
+		IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(ip, IpAddr::V4),
+		IpAddr::V4(_) => ip,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn ip(ip: &str) -> IpAddr {
+		ip.parse().unwrap()
+	}
+
+	#[test]
+	fn parse_ip_ranges() {
+		assert!(IpRange::from_str("10.0.0.0/8").is_ok());
+		assert!(IpRange::from_str(" 192.168.1.1 ").is_ok());
+		assert!(IpRange::from_str("2001:db8::/32").is_ok());
+		assert!(IpRange::from_str("0.0.0.0/0").is_ok());
+
+		assert!(IpRange::from_str("10.0.0.0/33").is_err());
+		assert!(IpRange::from_str("10.0.0.0/").is_err());
+		assert!(IpRange::from_str("10.0.0/8").is_err());
+		assert!(IpRange::from_str("office").is_err());
+
+		assert!(IpRanges::from_str("10.0.0.0/8, 192.168.1.0/24").is_ok());
+		assert!(IpRanges::from_str("10.0.0.0/8,office").is_err());
+		assert_eq!(IpRanges::from_str(""), Ok(IpRanges::default()));
+	}
+
+	#[test]
+	fn match_ip_ranges() {
+		let ranges = IpRanges::from_str("10.0.0.0/8,192.168.1.7,2001:db8::/32").unwrap();
+
+		assert!(ranges.contains(ip("10.1.2.3")));
+		assert!(ranges.contains(ip("192.168.1.7")));
+		assert!(ranges.contains(ip("2001:db8::1")));
+		assert!(ranges.contains(ip("::ffff:10.0.0.1")));
+
+		assert!(!ranges.contains(ip("11.0.0.1")));
+		assert!(!ranges.contains(ip("192.168.1.8")));
+		assert!(!ranges.contains(ip("2001:db9::1")));
+
+		// Everything or nothing
+		assert!(IpRanges::from_str("0.0.0.0/0")
+			.unwrap()
+			.contains(ip("203.0.113.1")));
+		assert!(!IpRanges::default().contains(ip("203.0.113.1")));
+	}
+}
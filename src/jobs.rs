@@ -0,0 +1,315 @@
+use std::{
+	fmt,
+	future::Future,
+	str::FromStr,
+	sync::{Arc, Mutex},
+	time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+use crate::clock::{date_from_unix, format_timestamp, unix_now, UtcTime};
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+/// How far ahead to look for the next run, so an expression like `0 0 31 2 *` can't loop forever
+const MAX_DAYS_AHEAD: u64 = 366 * 5;
+
+/// The values a single field of a cron expression matches
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Field {
+	values: u64,
+	/// If it was `*`, which matters for how the days are matched
+	any: bool,
+}
+
+impl Field {
+	fn parse(field: &str, min: u8, max: u8) -> Result<Field, String> {
+		let invalid = || format!("'{field}' is not a cron field from {min} to {max}");
+		let mut values = 0;
+		for part in field.split(',') {
+			let (range, step) = match part.split_once('/') {
+				Some((range, step)) => {
+					let step: u8 = step.parse().map_err(|_| invalid())?;
+					(range, step)
+				}
+				None => (part, 1),
+			};
+			let (start, end) = if range == "*" {
+				(min, max)
+			} else if let Some((start, end)) = range.split_once('-') {
+				(
+					start.parse().map_err(|_| invalid())?,
+					end.parse().map_err(|_| invalid())?,
+				)
+			} else {
+				let value = range.parse().map_err(|_| invalid())?;
+				// A single value with a step runs from it to the end, like `5/15`
+				(value, if part.contains('/') { max } else { value })
+			};
+			if step == 0 || start < min || end > max || start > end {
+				return Err(invalid());
+			}
+			for value in (start..=end).step_by(step.into()) {
+				values |= 1 << value;
+			}
+		}
+		Ok(Field {
+			values,
+			any: field == "*",
+		})
+	}
+
+	fn matches(self, value: u8) -> bool {
+		self.values & (1 << value) != 0
+	}
+}
+
+/// A standard five field cron expression, in UTC
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cron {
+	expression: String,
+	minute: Field,
+	hour: Field,
+	day: Field,
+	month: Field,
+	weekday: Field,
+}
+
+impl FromStr for Cron {
+	type Err = String;
+
+	fn from_str(expression: &str) -> Result<Self, Self::Err> {
+		let fields: Vec<&str> = expression.split_whitespace().collect();
+		let &[minute, hour, day, month, weekday] = fields.as_slice() else {
+			return Err(format!(
+				"'{expression}' is not a cron expression, like '*/5 * * * *'"
+			));
+		};
+
+		let mut weekday = Field::parse(weekday, 0, 7)?;
+		// Both 0 and 7 are Sunday
+		if weekday.matches(7) {
+			weekday.values |= 1;
+		}
+		Ok(Cron {
+			expression: fields.join(" "),
+			minute: Field::parse(minute, 0, 59)?,
+			hour: Field::parse(hour, 0, 23)?,
+			day: Field::parse(day, 1, 31)?,
+			month: Field::parse(month, 1, 12)?,
+			weekday,
+		})
+	}
+}
+
+impl fmt::Display for Cron {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(&self.expression)
+	}
+}
+
+impl Cron {
+	/// Check if the day at a number of seconds since the Unix epoch matches
+	fn matches_day(&self, seconds: u64) -> bool {
+		let (_, month, day) = date_from_unix(seconds);
+		// Cron counts the days of the week from Sunday
+		let weekday = (UtcTime::from_unix(seconds).weekday + 1) % 7;
+		if !self.month.matches(month) {
+			return false;
+		}
+		// If both days are set, either one can match
+		match (self.day.any, self.weekday.any) {
+			(false, false) => self.day.matches(day) || self.weekday.matches(weekday),
+			_ => self.day.matches(day) && self.weekday.matches(weekday),
+		}
+	}
+
+	/// The next time it matches after a number of seconds since the Unix epoch
+	pub fn next_after(&self, seconds: u64) -> Option<u64> {
+		let mut time = (seconds / 60 + 1) * 60;
+		let end = time + MAX_DAYS_AHEAD * SECONDS_PER_DAY;
+		while time < end {
+			if !self.matches_day(time) {
+				time = (time / SECONDS_PER_DAY + 1) * SECONDS_PER_DAY;
+				continue;
+			}
+			let UtcTime { hour, minute, .. } = UtcTime::from_unix(time);
+			if !self.hour.matches(hour) {
+				time = (time / 3600 + 1) * 3600;
+				continue;
+			}
+			if self.minute.matches(minute) {
+				return Some(time);
+			}
+			time += 60;
+		}
+		None
+	}
+}
+
+/// When a job runs
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum JobSchedule {
+	/// Right away, then again each time the interval has passed
+	Every(Duration),
+	/// Whenever the expression matches
+	Cron(Cron),
+	/// At the time, in seconds since the Unix epoch, and never again
+	Once(u64),
+}
+
+impl JobSchedule {
+	/// When to run first, in seconds since the Unix epoch
+	fn first(&self, now: u64) -> Option<u64> {
+		match self {
+			JobSchedule::Every(_) => Some(now),
+			JobSchedule::Cron(cron) => cron.next_after(now),
+			JobSchedule::Once(at) => Some(*at),
+		}
+	}
+
+	/// When to run next after running, in seconds since the Unix epoch
+	fn next(&self, now: u64) -> Option<u64> {
+		match self {
+			JobSchedule::Every(interval) => Some(now + interval.as_secs().max(1)),
+			JobSchedule::Cron(cron) => cron.next_after(now),
+			JobSchedule::Once(_) => None,
+		}
+	}
+}
+
+impl fmt::Display for JobSchedule {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			JobSchedule::Every(interval) => write!(f, "every {} seconds", interval.as_secs()),
+			JobSchedule::Cron(cron) => write!(f, "{cron}"),
+			JobSchedule::Once(at) => write!(f, "once at {}", format_timestamp(*at)),
+		}
+	}
+}
+
+/// What a job has done so far
+#[derive(Clone, Debug, Serialize)]
+pub struct JobStatus {
+	pub name: String,
+	pub schedule: String,
+	pub runs: u64,
+	pub last_run: Option<String>,
+	pub last_duration_ms: Option<u128>,
+	/// Why the last run failed, if it did
+	pub last_error: Option<String>,
+	pub next_run: Option<String>,
+}
+
+/// Runs the background jobs and keeps track of how they're doing
+#[derive(Default)]
+pub struct Jobs {
+	statuses: Mutex<Vec<JobStatus>>,
+}
+
+impl Jobs {
+	/// Start running the job on the schedule
+	pub fn spawn<F, Fut>(self: &Arc<Self>, name: &str, schedule: JobSchedule, job: F)
+	where
+		F: Fn() -> Fut + Send + 'static,
+		Fut: Future<Output = Result<(), String>> + Send,
+	{
+		let index = {
+			let mut statuses = self.statuses.lock().unwrap();
+			statuses.push(JobStatus {
+				name: name.to_string(),
+				schedule: schedule.to_string(),
+				runs: 0,
+				last_run: None,
+				last_duration_ms: None,
+				last_error: None,
+				next_run: None,
+			});
+			statuses.len() - 1
+		};
+
+		let jobs = self.clone();
+		tokio::spawn(async move {
+			let mut next = schedule.first(unix_now());
+			while let Some(at) = next {
+				jobs.update(index, |status| status.next_run = Some(format_timestamp(at)));
+				tokio::time::sleep(Duration::from_secs(at.saturating_sub(unix_now()))).await;
+
+				let started = Instant::now();
+				let result = job().await;
+				let duration = started.elapsed();
+
+				next = schedule.next(unix_now());
+				jobs.update(index, |status| {
+					status.runs += 1;
+					status.last_run = Some(format_timestamp(at));
+					status.last_duration_ms = Some(duration.as_millis());
+					status.last_error = result.err();
+					status.next_run = None;
+				});
+			}
+		});
+	}
+
+	fn update(&self, index: usize, change: impl FnOnce(&mut JobStatus)) {
+		if let Some(status) = self.statuses.lock().unwrap().get_mut(index) {
+			change(status);
+		}
+	}
+
+	/// The status of every job
+	#[cfg_attr(not(feature = "admin"), allow(dead_code))]
+	pub fn statuses(&self) -> Vec<JobStatus> {
+		self.statuses.lock().unwrap().clone()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::clock::parse_timestamp;
+
+	use super::*;
+
+	#[test]
+	fn find_next_cron_run() {
+		let next = |expression: &str, after: &str| {
+			let cron = Cron::from_str(expression).unwrap();
+			cron.next_after(parse_timestamp(after).unwrap())
+				.map(format_timestamp)
+		};
+
+		// 2024-01-01 was a Monday
+		assert_eq!(
+			next("*/15 * * * *", "2024-01-01T10:07:30Z").as_deref(),
+			Some("2024-01-01T10:15:00Z")
+		);
+		assert_eq!(
+			next("0 9 * * 1-5", "2024-01-05T09:00:00Z").as_deref(),
+			Some("2024-01-08T09:00:00Z")
+		);
+		assert_eq!(
+			next("30 2 29 2 *", "2024-03-01T00:00:00Z").as_deref(),
+			Some("2028-02-29T02:30:00Z")
+		);
+		// When both days are set either can match
+		assert_eq!(
+			next("0 0 15 * 7", "2024-01-01T00:00:00Z").as_deref(),
+			Some("2024-01-07T00:00:00Z")
+		);
+		assert_eq!(next("0 0 31 2 *", "2024-01-01T00:00:00Z"), None);
+
+		assert!(Cron::from_str("* * * *").is_err());
+		assert!(Cron::from_str("60 * * * *").is_err());
+		assert!(Cron::from_str("*/0 * * * *").is_err());
+		assert_eq!(
+			Cron::from_str("0  9 * * *").unwrap().to_string(),
+			"0 9 * * *"
+		);
+
+		// One off jobs run at their time, even if it's passed, and then stop
+		let once = JobSchedule::Once(parse_timestamp("2024-01-01T10:00:00Z").unwrap());
+		assert_eq!(once.first(0), parse_timestamp("2024-01-01T10:00:00Z").ok());
+		assert_eq!(once.next(0), None);
+		assert_eq!(once.to_string(), "once at 2024-01-01T10:00:00Z");
+	}
+}
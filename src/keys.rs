@@ -0,0 +1,33 @@
+use unicode_normalization::UnicodeNormalization;
+
+/// Normalize a key, so ones with emoji or accents match however the browser sent them. Keys are
+/// put in NFC form, and emoji variation selectors, which some browsers add and others don't, are
+/// removed. Returns `None` if the key was already normalized
+pub fn normalize_key(key: &str) -> Option<String> {
+	// Nearly every key is ASCII, which is always normalized
+	if key.is_ascii() {
+		return None;
+	}
+
+	let normalized: String = key
+		.nfc()
+		.filter(|x| !matches!(x, '\u{FE0E}' | '\u{FE0F}'))
+		.collect();
+	(normalized != key).then_some(normalized)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn normalize_keys() {
+		assert_eq!(normalize_key("docs"), None);
+		assert_eq!(normalize_key("🍕"), None);
+		assert_eq!(normalize_key("🍕\u{FE0F}"), Some("🍕".to_string()));
+		assert_eq!(normalize_key("❤\u{FE0E}"), Some("❤".to_string()));
+		// An e followed by a combining accent is the same as é
+		assert_eq!(normalize_key("cafe\u{301}"), Some("café".to_string()));
+		assert_eq!(normalize_key("café"), None);
+	}
+}
@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use axum::http::{header, Uri};
+
+use crate::{
+	locales::preferred_languages,
+	resolver::{Resolution, ResolveRequest, Resolver, Target},
+	uri_mappings::LinkOptions,
+};
+
+/// The targets of a link in other languages. Written as comma separated entries of
+/// `<language> <uri>`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Translations {
+	targets: Vec<(String, Uri)>,
+}
+
+impl Translations {
+	/// Parse targets like `fr https://example.com/fr, de https://example.com/de`, with the
+	/// targets parsed the same as any other
+	pub fn parse(
+		s: &str,
+		parse_target: impl Fn(&str) -> Result<Uri, String>,
+	) -> Result<Translations, String> {
+		let targets = s
+			.split(',')
+			.filter(|entry| !entry.trim().is_empty())
+			.map(|entry| {
+				let entry = entry.trim();
+				let (language, uri) = entry
+					.split_once(char::is_whitespace)
+					.ok_or_else(|| format!("'{entry}' is not like '<language> <uri>'"))?;
+				Ok((language.to_ascii_lowercase(), parse_target(uri.trim())?))
+			})
+			.collect::<Result<Vec<_>, String>>()?;
+
+		if targets.is_empty() {
+			return Err("there are no languages".to_string());
+		}
+		Ok(Translations { targets })
+	}
+
+	/// Get the target in the most preferred language of the `Accept-Language` header, if there
+	/// is one for any of them
+	pub fn pick(&self, accept_language: &str) -> Option<&Uri> {
+		preferred_languages(accept_language)
+			.iter()
+			.find_map(|language| {
+				// Fall back from a regional variant like `pt-br` to `pt`
+				let primary = language.split('-').next().unwrap_or_default();
+				[language.as_str(), primary].iter().find_map(|language| {
+					self.targets
+						.iter()
+						.find(|(translation, _)| translation == language)
+						.map(|(_, uri)| uri)
+				})
+			})
+	}
+}
+
+/// Resolves standard paths to a target in the client's language, when there's one for it. Other
+/// clients get the standard target
+pub struct LanguageResolver {
+	translations: HashMap<String, Translations>,
+	/// The options of the standard mappings, which still apply to their translations
+	options: HashMap<String, LinkOptions>,
+}
+
+impl LanguageResolver {
+	/// Create a new `LanguageResolver` from translations keyed by path, with the options of the
+	/// standard mappings they're for
+	pub fn new(
+		translations: HashMap<String, Translations>,
+		options: HashMap<String, LinkOptions>,
+	) -> Self {
+		LanguageResolver {
+			translations,
+			options,
+		}
+	}
+}
+
+#[async_trait]
+impl Resolver for LanguageResolver {
+	async fn resolve(&self, request: &ResolveRequest<'_>) -> Option<Resolution> {
+		let translations = self.translations.get(request.path.as_ref())?;
+		let accept_language = request
+			.headers
+			.get(header::ACCEPT_LANGUAGE)?
+			.to_str()
+			.ok()?;
+		let uri = translations.pick(accept_language)?;
+
+		let options = self
+			.options
+			.get(request.path.as_ref())
+			.cloned()
+			.unwrap_or_default();
+		if !options.allows(request.client) {
+			return None;
+		}
+		Some(Resolution {
+			target: Target::Redirect(uri.clone()),
+			options,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::targets::TargetPolicy;
+
+	#[test]
+	fn pick_translations() {
+		let policy = TargetPolicy::default();
+		let parse = |s| Translations::parse(s, |target| policy.parse(target));
+		let translations = parse(
+			"fr https://example.com/fr/docs, pt-BR https://example.com/pt-br/docs, de https://bücher.example/de/docs",
+		)
+		.unwrap();
+		let pick = |accept_language| translations.pick(accept_language).map(ToString::to_string);
+
+		assert_eq!(
+			pick("fr-CA, en;q=0.8").as_deref(),
+			Some("https://example.com/fr/docs")
+		);
+		assert_eq!(
+			pick("en, de;q=0.5").as_deref(),
+			Some("https://xn--bcher-kva.example/de/docs")
+		);
+		assert_eq!(
+			pick("pt-br").as_deref(),
+			Some("https://example.com/pt-br/docs")
+		);
+		assert_eq!(pick("en-US, en"), None);
+		assert_eq!(pick(""), None);
+
+		assert!(parse("").is_err());
+		assert!(parse("https://example.com/").is_err());
+		assert!(parse("fr http://[broken").is_err());
+		assert!(parse("fr javascript:alert(1)").is_err());
+	}
+}
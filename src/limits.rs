@@ -0,0 +1,121 @@
+use std::{
+	error::Error,
+	io,
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc,
+	},
+	time::Duration,
+};
+
+use axum::{
+	body::{Body, Bytes},
+	http::{header, HeaderValue, Request, StatusCode},
+	middleware::{self, Next},
+	response::{IntoResponse, Response},
+	Router,
+};
+use futures_util::stream;
+use hyper::{body::HttpBody, server::Builder};
+use tokio::time::{self, Instant};
+
+/// The smallest buffer hyper can read headers into
+pub const MIN_HEADER_SIZE: usize = 8192;
+
+/// How much and how slowly clients can send, so they can't tie up connections by sending
+/// requests a trickle at a time
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Limits {
+	/// The most bytes a request's headers can take
+	pub max_header_size: Option<usize>,
+	/// How long a client gets to send a request's headers before the connection is closed
+	pub header_read_timeout: Option<Duration>,
+	/// How long a client gets to send a request's body before the request fails
+	pub body_read_timeout: Option<Duration>,
+}
+
+impl Limits {
+	/// Set the header limits on a server
+	pub fn configure<I>(&self, mut builder: Builder<I>) -> Builder<I> {
+		if let Some(size) = self.max_header_size {
+			builder = builder.http1_max_buf_size(size);
+		}
+		if let Some(timeout) = self.header_read_timeout {
+			builder = builder.http1_header_read_timeout(timeout);
+		}
+		builder
+	}
+
+	/// Fail requests to the app whose body takes too long to arrive, if there's a limit
+	pub fn apply(&self, app: Router) -> Router {
+		match self.body_read_timeout {
+			Some(timeout) => app.layer(middleware::from_fn(move |request, next| {
+				limit_body(request, next, timeout)
+			})),
+			None => app,
+		}
+	}
+}
+
+/// Answer with `408` if the body didn't arrive in time, whatever the handler made of the body
+/// being cut off
+async fn limit_body(request: Request<Body>, next: Next<Body>, timeout: Duration) -> Response {
+	let timed_out = Arc::new(AtomicBool::new(false));
+	let request = request.map(|body| deadline_body(body, timeout, timed_out.clone()));
+
+	let response = next.run(request).await;
+	if timed_out.load(Ordering::Relaxed) {
+		return (
+			StatusCode::REQUEST_TIMEOUT,
+			[(header::CONNECTION, HeaderValue::from_static("close"))],
+		)
+			.into_response();
+	}
+	response
+}
+
+/// Pass the body on, failing it if it hasn't all arrived within the timeout of it first being
+/// read. Handlers that wait before reading the body aren't counted against the client
+fn deadline_body(body: Body, timeout: Duration, timed_out: Arc<AtomicBool>) -> Body {
+	Body::wrap_stream(stream::unfold(Some((body, None)), move |state| {
+		let timed_out = timed_out.clone();
+		async move {
+			let (mut body, deadline) = state?;
+			let deadline = deadline.unwrap_or_else(|| Instant::now() + timeout);
+			tokio::select! {
+				biased;
+				chunk = body.data() => chunk.map(|chunk| {
+					(chunk.map_err(Box::<dyn Error + Send + Sync>::from), Some((body, Some(deadline))))
+				}),
+				() = time::sleep_until(deadline) => {
+					timed_out.store(true, Ordering::Relaxed);
+					let error = io::Error::new(io::ErrorKind::TimedOut, "the body took too long to send");
+					Some((Err::<Bytes, _>(error.into()), None))
+				}
+			}
+		}
+	}))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[tokio::test]
+	async fn cut_off_slow_bodies() {
+		let (_sender, body) = Body::channel();
+		let timed_out = Arc::new(AtomicBool::new(false));
+		let slow = deadline_body(body, Duration::from_millis(10), timed_out.clone());
+		assert!(hyper::body::to_bytes(slow).await.is_err());
+		assert!(timed_out.load(Ordering::Relaxed));
+
+		let timed_out = Arc::new(AtomicBool::new(false));
+		let fast = deadline_body(
+			Body::from("all of it"),
+			Duration::from_secs(1),
+			timed_out.clone(),
+		);
+		assert_eq!(hyper::body::to_bytes(fast).await.unwrap(), "all of it");
+		assert!(!timed_out.load(Ordering::Relaxed));
+	}
+}
@@ -0,0 +1,284 @@
+use std::{fmt, net::IpAddr, str::FromStr};
+
+use reqwest::Url;
+
+/// Targets longer than this are flagged, since some clients won't follow them
+const MAX_TARGET_LENGTH: usize = 2048;
+/// Query parameters that only exist to track who followed a link
+const TRACKING_PARAMETERS: [&str; 10] = [
+	"fbclid", "gclid", "dclid", "msclkid", "mc_cid", "mc_eid", "_hsenc", "_hsmi", "igshid", "yclid",
+];
+
+/// How much a finding matters
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+	Off,
+	Warning,
+	Error,
+}
+
+impl FromStr for Severity {
+	type Err = String;
+
+	fn from_str(severity: &str) -> Result<Self, Self::Err> {
+		match severity.trim().to_ascii_lowercase().as_str() {
+			"off" => Ok(Severity::Off),
+			"warning" => Ok(Severity::Warning),
+			"error" => Ok(Severity::Error),
+			_ => Err(format!(
+				"'{severity}' is not a severity, like 'off', 'warning', or 'error'"
+			)),
+		}
+	}
+}
+
+impl fmt::Display for Severity {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			Severity::Off => "off",
+			Severity::Warning => "warning",
+			Severity::Error => "error",
+		})
+	}
+}
+
+/// Something that might be wrong with a target
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rule {
+	/// The host is `localhost` or a private address, which won't work for most clients
+	PrivateHost,
+	/// There's no scheme, and no base URL to resolve it against
+	MissingScheme,
+	/// The query has tracking parameters, like `utm_source`
+	TrackingParameters,
+	/// The target is very long
+	TooLong,
+}
+
+impl Rule {
+	pub const ALL: [Rule; 4] = [
+		Rule::PrivateHost,
+		Rule::MissingScheme,
+		Rule::TrackingParameters,
+		Rule::TooLong,
+	];
+
+	/// The name used to configure the rule
+	pub fn name(self) -> &'static str {
+		match self {
+			Rule::PrivateHost => "private_host",
+			Rule::MissingScheme => "missing_scheme",
+			Rule::TrackingParameters => "tracking_parameters",
+			Rule::TooLong => "too_long",
+		}
+	}
+
+	fn default_severity(self) -> Severity {
+		match self {
+			Rule::PrivateHost | Rule::TrackingParameters => Severity::Warning,
+			Rule::MissingScheme | Rule::TooLong => Severity::Error,
+		}
+	}
+}
+
+/// A problem found with a target
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Finding {
+	pub rule: Rule,
+	pub severity: Severity,
+	pub message: String,
+}
+
+/// Checks targets for anything suspicious
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Linter {
+	severities: Vec<(Rule, Severity)>,
+	/// If relative targets are fine, because there's a base URL
+	relative: bool,
+}
+
+impl Default for Linter {
+	fn default() -> Self {
+		Linter {
+			severities: Rule::ALL
+				.iter()
+				.map(|rule| (*rule, rule.default_severity()))
+				.collect(),
+			relative: false,
+		}
+	}
+}
+
+impl Linter {
+	/// Change how much a rule matters
+	pub fn with_severity(mut self, rule: Rule, severity: Severity) -> Linter {
+		for (existing, existing_severity) in &mut self.severities {
+			if *existing == rule {
+				*existing_severity = severity;
+			}
+		}
+		self
+	}
+
+	/// Allow targets without a scheme, because they'll be resolved against a base URL
+	pub fn with_relative(mut self) -> Linter {
+		self.relative = true;
+		self
+	}
+
+	fn severity(&self, rule: Rule) -> Severity {
+		self.severities
+			.iter()
+			.find(|(existing, _)| *existing == rule)
+			.map_or(Severity::Off, |(_, severity)| *severity)
+	}
+
+	/// Find anything suspicious about the target
+	pub fn lint(&self, target: &str) -> Vec<Finding> {
+		let mut findings = Vec::new();
+		let mut found = |rule: Rule, message: String| {
+			let severity = self.severity(rule);
+			if severity != Severity::Off {
+				findings.push(Finding {
+					rule,
+					severity,
+					message,
+				});
+			}
+		};
+
+		if target.len() > MAX_TARGET_LENGTH {
+			found(
+				Rule::TooLong,
+				format!(
+					"is {} characters long, over the {MAX_TARGET_LENGTH} some clients allow",
+					target.len()
+				),
+			);
+		}
+
+		let url = match Url::parse(target.trim()) {
+			Ok(url) => Ok(url),
+			Err(_) if self.relative => {
+				Url::parse("http://base.invalid/").and_then(|base| base.join(target.trim()))
+			}
+			Err(error) => {
+				found(
+					Rule::MissingScheme,
+					format!("has no scheme or base URL to resolve it ({error})"),
+				);
+				return findings;
+			}
+		}
+		.ok();
+		let Some(url) = url else {
+			return findings;
+		};
+
+		if let Some(host) = url.host_str() {
+			if is_private_host(host) {
+				found(
+					Rule::PrivateHost,
+					format!("points to '{host}', which most clients can't reach"),
+				);
+			}
+		}
+
+		let tracking: Vec<String> = url
+			.query_pairs()
+			.map(|(name, _)| name.to_string())
+			.filter(|name| {
+				name.to_ascii_lowercase().starts_with("utm_")
+					|| TRACKING_PARAMETERS.contains(&name.to_ascii_lowercase().as_str())
+			})
+			.collect();
+		if !tracking.is_empty() {
+			found(
+				Rule::TrackingParameters,
+				format!("has tracking parameters: {}", tracking.join(", ")),
+			);
+		}
+
+		findings
+	}
+}
+
+/// Check if the host is only reachable locally or on a private network
+fn is_private_host(host: &str) -> bool {
+	let host = host.trim_start_matches('[').trim_end_matches(']');
+	if host == "localhost" || host.ends_with(".localhost") {
+		return true;
+	}
+	match host.parse::<IpAddr>() {
+		Ok(IpAddr::V4(ip)) => {
+			ip.is_loopback() || ip.is_private() || ip.is_link_local() || ip.is_unspecified()
+		}
+		Ok(IpAddr::V6(ip)) => {
+			let first = ip.segments()[0];
+			ip.is_loopback()
+				|| ip.is_unspecified()
+				// Unique local and link local addresses
+				|| first & 0xfe00 == 0xfc00
+				|| first & 0xffc0 == 0xfe80
+		}
+		Err(_) => false,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn lint_targets() {
+		let linter = Linter::default();
+		let rules = |linter: &Linter, target: &str| -> Vec<(Rule, Severity)> {
+			linter
+				.lint(target)
+				.into_iter()
+				.map(|finding| (finding.rule, finding.severity))
+				.collect()
+		};
+
+		assert!(rules(&linter, "https://example.com/docs?page=2").is_empty());
+		assert_eq!(
+			rules(&linter, "http://localhost:8080/"),
+			vec![(Rule::PrivateHost, Severity::Warning)]
+		);
+		assert_eq!(
+			rules(&linter, "http://192.168.1.10/"),
+			vec![(Rule::PrivateHost, Severity::Warning)]
+		);
+		assert_eq!(
+			rules(&linter, "http://[fd00::1]/"),
+			vec![(Rule::PrivateHost, Severity::Warning)]
+		);
+		assert_eq!(
+			rules(&linter, "https://example.com/?utm_source=mail&fbclid=1"),
+			vec![(Rule::TrackingParameters, Severity::Warning)]
+		);
+		assert_eq!(
+			rules(&linter, "example.com/docs"),
+			vec![(Rule::MissingScheme, Severity::Error)]
+		);
+		assert_eq!(
+			rules(
+				&linter,
+				&format!("https://example.com/{}", "a".repeat(3000))
+			),
+			vec![(Rule::TooLong, Severity::Error)]
+		);
+
+		// Severities can be changed, and relative targets allowed
+		let linter = Linter::default()
+			.with_severity(Rule::PrivateHost, Severity::Off)
+			.with_severity(Rule::TrackingParameters, Severity::Error)
+			.with_relative();
+		assert!(rules(&linter, "http://localhost/").is_empty());
+		assert!(rules(&linter, "/docs/setup").is_empty());
+		assert_eq!(
+			rules(&linter, "/docs?utm_medium=x"),
+			vec![(Rule::TrackingParameters, Severity::Error)]
+		);
+	}
+}
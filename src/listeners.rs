@@ -0,0 +1,532 @@
+use std::{
+	fmt, fs,
+	io::{self, BufReader},
+	net::{Ipv4Addr, SocketAddr, TcpListener as StdTcpListener},
+	path::PathBuf,
+	pin::Pin,
+	sync::Arc,
+	task::{Context, Poll},
+	time::Duration,
+};
+
+use axum::{
+	extract::connect_info::Connected,
+	http::{header, uri::Authority, HeaderMap, StatusCode, Uri},
+	response::{IntoResponse, Response},
+	routing::any,
+	Router,
+};
+use hyper::server::{accept, conn::AddrIncoming};
+use tokio::{
+	io::{AsyncRead, AsyncWrite, ReadBuf},
+	net::{TcpListener, TcpStream},
+	sync::mpsc,
+};
+use tokio_rustls::{
+	rustls::{Certificate, PrivateKey, ServerConfig},
+	server::TlsStream,
+	TlsAcceptor,
+};
+
+use crate::limits::Limits;
+
+/// How long a client gets to finish the TLS handshake
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+/// How many connections can finish their handshake before being served
+const HANDSHAKE_QUEUE_SIZE: usize = 64;
+
+/// What a listener serves
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scheme {
+	/// The links over HTTP
+	Http,
+	/// The links over HTTPS
+	Https,
+	/// A redirect to HTTPS for every request
+	Redirect,
+	/// The admin API over HTTP, kept off the listeners that serve the links
+	Admin,
+}
+
+/// Where a listener listens
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Address {
+	Tcp(SocketAddr),
+	/// A Unix socket at the path, only for the admin API
+	Unix(PathBuf),
+}
+
+impl fmt::Display for Address {
+	fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Address::Tcp(address) => write!(formatter, "{address}"),
+			Address::Unix(path) => write!(formatter, "{}", path.display()),
+		}
+	}
+}
+
+/// An address to listen on, and what to serve there
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Listener {
+	pub scheme: Scheme,
+	pub address: Address,
+}
+
+impl fmt::Display for Listener {
+	fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+		let scheme = match (self.scheme, &self.address) {
+			(_, Address::Unix(_)) => "unix",
+			(Scheme::Http, _) => "http",
+			(Scheme::Https, _) => "https",
+			(Scheme::Redirect, _) => "redirect",
+			(Scheme::Admin, _) => "admin",
+		};
+		write!(formatter, "{scheme}://{}", self.address)
+	}
+}
+
+/// Parse a comma separated list of listeners, like
+/// `redirect://0.0.0.0:80,https://0.0.0.0:443,unix:///run/urshort/admin.sock`
+pub fn parse_listeners(value: &str) -> Result<Vec<Listener>, String> {
+	let listeners = value
+		.split(',')
+		.map(str::trim)
+		.filter(|listener| !listener.is_empty())
+		.map(|listener| {
+			let (scheme, address) = listener
+				.split_once("://")
+				.ok_or_else(|| format!("'{listener}' needs a scheme, like http://"))?;
+			if scheme == "unix" {
+				if !cfg!(unix) {
+					return Err("Unix sockets can't be used on this system".to_string());
+				}
+				return Ok(Listener {
+					scheme: Scheme::Admin,
+					address: Address::Unix(PathBuf::from(address)),
+				});
+			}
+			let scheme = match scheme {
+				"http" => Scheme::Http,
+				"https" => Scheme::Https,
+				"redirect" => Scheme::Redirect,
+				"admin" => Scheme::Admin,
+				_ => {
+					return Err(format!(
+						"'{scheme}' isn't http, https, redirect, admin, or unix"
+					))
+				}
+			};
+			let address = address
+				.parse()
+				.map_err(|_| format!("'{address}' isn't an IP address and port"))?;
+			Ok(Listener {
+				scheme,
+				address: Address::Tcp(address),
+			})
+		})
+		.collect::<Result<Vec<Listener>, String>>()?;
+	if listeners.is_empty() {
+		return Err("there are no listeners".to_string());
+	}
+	Ok(listeners)
+}
+
+/// Load the certificate chain and private key HTTPS is served with, from PEM files
+pub fn load_tls_config(certificate: &str, key: &str) -> Result<Arc<ServerConfig>, String> {
+	let read = |path: &str| {
+		fs::read(path).map_err(|error| format!("could not read '{path}', because {error}"))
+	};
+	let certificates = rustls_pemfile::certs(&mut BufReader::new(read(certificate)?.as_slice()))
+		.map_err(|error| format!("'{certificate}' isn't PEM, because {error}"))?;
+	if certificates.is_empty() {
+		return Err(format!("'{certificate}' has no certificates"));
+	}
+	let key = rustls_pemfile::read_all(&mut BufReader::new(read(key)?.as_slice()))
+		.map_err(|error| format!("'{key}' isn't PEM, because {error}"))?
+		.into_iter()
+		.find_map(|item| match item {
+			rustls_pemfile::Item::PKCS8Key(key) | rustls_pemfile::Item::RSAKey(key) => Some(key),
+			rustls_pemfile::Item::X509Certificate(_) => None,
+		})
+		.ok_or_else(|| format!("'{key}' has no PKCS #8 or RSA private key"))?;
+
+	let mut config = ServerConfig::builder()
+		.with_safe_defaults()
+		.with_no_client_auth()
+		.with_single_cert(
+			certificates.into_iter().map(Certificate).collect(),
+			PrivateKey(key),
+		)
+		.map_err(|error| format!("the certificate can't be used, because {error}"))?;
+	config.alpn_protocols = vec![b"http/1.1".to_vec()];
+	Ok(Arc::new(config))
+}
+
+/// A listener that's been bound to its address, ready to serve
+pub struct Bound {
+	incoming: Incoming,
+	limits: Limits,
+}
+
+enum Incoming {
+	Http(AddrIncoming),
+	Https(TcpListener, TlsAcceptor),
+	/// Redirects to HTTPS on the port
+	Redirect(AddrIncoming, u16),
+	Admin(AddrIncoming),
+	/// The admin API on a Unix socket
+	#[cfg(unix)]
+	Unix(tokio::net::UnixListener),
+}
+
+/// Bind every listener, so any address that can't be used is found before serving starts
+pub fn bind(
+	listeners: &[Listener],
+	tls: Option<&Arc<ServerConfig>>,
+	limits: Limits,
+) -> Result<Vec<Bound>, String> {
+	let https_port = listeners
+		.iter()
+		.find_map(|listener| match listener.address {
+			Address::Tcp(address) if listener.scheme == Scheme::Https => Some(address.port()),
+			_ => None,
+		})
+		.unwrap_or(443);
+	listeners
+		.iter()
+		.map(|listener| {
+			let failed = |error: &dyn fmt::Display| {
+				format!("Could not listen on {listener}, because {error}")
+			};
+			let address = match &listener.address {
+				Address::Tcp(address) => *address,
+				Address::Unix(path) => {
+					return Ok(Bound {
+						incoming: bind_unix(path).map_err(|error| failed(&error))?,
+						limits,
+					})
+				}
+			};
+			let bind = || AddrIncoming::bind(&address).map_err(|error| failed(&error));
+			let incoming = match listener.scheme {
+				Scheme::Http => Incoming::Http(bind()?),
+				Scheme::Admin => Incoming::Admin(bind()?),
+				Scheme::Redirect => Incoming::Redirect(bind()?, https_port),
+				Scheme::Https => {
+					let tls = tls
+						.cloned()
+						.ok_or_else(|| failed(&"there's no certificate"))?;
+					let listener = StdTcpListener::bind(address)
+						.and_then(|listener| {
+							listener.set_nonblocking(true)?;
+							TcpListener::from_std(listener)
+						})
+						.map_err(|error| failed(&error))?;
+					Incoming::Https(listener, TlsAcceptor::from(tls))
+				}
+			};
+			Ok(Bound { incoming, limits })
+		})
+		.collect()
+}
+
+/// Bind the Unix socket, replacing one left behind by an earlier run. Anything else at the path
+/// is left alone
+#[cfg(unix)]
+fn bind_unix(path: &std::path::Path) -> io::Result<Incoming> {
+	use std::os::unix::fs::FileTypeExt;
+
+	if fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_socket()) {
+		fs::remove_file(path)?;
+	}
+	Ok(Incoming::Unix(tokio::net::UnixListener::bind(path)?))
+}
+
+#[cfg(not(unix))]
+fn bind_unix(_path: &std::path::Path) -> io::Result<Incoming> {
+	Err(io::Error::new(
+		io::ErrorKind::Unsupported,
+		"Unix sockets can't be used on this system",
+	))
+}
+
+impl Bound {
+	/// Whether the listener is for the admin API, instead of the links
+	pub fn is_admin(&self) -> bool {
+		match self.incoming {
+			Incoming::Admin(_) => true,
+			#[cfg(unix)]
+			Incoming::Unix(_) => true,
+			_ => false,
+		}
+	}
+
+	/// Serve the app, or the redirect to HTTPS, until the shutdown finishes
+	pub async fn serve<F>(self, app: Router, shutdown: F) -> Result<(), String>
+	where
+		F: std::future::Future<Output = ()>,
+	{
+		let limits = self.limits;
+		let app = limits.apply(app);
+		match self.incoming {
+			Incoming::Http(incoming) | Incoming::Admin(incoming) => {
+				limits
+					.configure(axum::Server::builder(incoming))
+					.serve(app.into_make_service_with_connect_info::<SocketAddr>())
+					.with_graceful_shutdown(shutdown)
+					.await
+			}
+			Incoming::Redirect(incoming, https_port) => {
+				limits
+					.configure(axum::Server::builder(incoming))
+					.serve(
+						redirect_router(https_port)
+							.into_make_service_with_connect_info::<SocketAddr>(),
+					)
+					.with_graceful_shutdown(shutdown)
+					.await
+			}
+			#[cfg(unix)]
+			Incoming::Unix(listener) => {
+				let incoming = accept::from_stream(accept_unix(listener));
+				limits
+					.configure(axum::Server::builder(incoming))
+					.serve(app.into_make_service_with_connect_info::<SocketAddr>())
+					.with_graceful_shutdown(shutdown)
+					.await
+			}
+			Incoming::Https(listener, acceptor) => {
+				let (sender, mut connections) = mpsc::channel(HANDSHAKE_QUEUE_SIZE);
+				tokio::spawn(accept_tls(listener, acceptor, sender));
+				let incoming = accept::poll_fn(move |context| {
+					connections
+						.poll_recv(context)
+						.map(|connection| connection.map(Ok::<_, io::Error>))
+				});
+				limits
+					.configure(axum::Server::builder(incoming))
+					.serve(app.into_make_service_with_connect_info::<SocketAddr>())
+					.with_graceful_shutdown(shutdown)
+					.await
+			}
+		}
+		.map_err(|error| error.to_string())
+	}
+}
+
+/// Accept connections and finish their handshakes, until the server stops taking them.
+/// Handshakes run on their own, so a slow client doesn't hold up the others
+async fn accept_tls(
+	listener: TcpListener,
+	acceptor: TlsAcceptor,
+	connections: mpsc::Sender<TlsConnection>,
+) {
+	loop {
+		let (stream, peer) = tokio::select! {
+			accepted = listener.accept() => match accepted {
+				Ok(accepted) => accepted,
+				Err(error) => {
+					println!("Could not accept a connection, because {error}");
+					println!();
+					tokio::time::sleep(Duration::from_millis(100)).await;
+					continue;
+				}
+			},
+			() = connections.closed() => return,
+		};
+		let acceptor = acceptor.clone();
+		let connections = connections.clone();
+		tokio::spawn(async move {
+			if let Ok(Ok(stream)) =
+				tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await
+			{
+				let _ = connections.send(TlsConnection { stream, peer }).await;
+			}
+		});
+	}
+}
+
+/// Accept connections to the Unix socket. Failing to accept one doesn't stop the others
+#[cfg(unix)]
+fn accept_unix(
+	listener: tokio::net::UnixListener,
+) -> impl futures_util::Stream<Item = io::Result<UnixConnection>> {
+	futures_util::stream::unfold(listener, |listener| async move {
+		loop {
+			match listener.accept().await {
+				Ok((stream, _)) => return Some((Ok(UnixConnection(stream)), listener)),
+				Err(error) => {
+					println!("Could not accept a connection, because {error}");
+					println!();
+					tokio::time::sleep(Duration::from_millis(100)).await;
+				}
+			}
+		}
+	})
+}
+
+/// A connection that finished its TLS handshake, with who it's from
+pub struct TlsConnection {
+	stream: TlsStream<TcpStream>,
+	peer: SocketAddr,
+}
+
+impl Connected<&TlsConnection> for SocketAddr {
+	fn connect_info(connection: &TlsConnection) -> SocketAddr {
+		connection.peer
+	}
+}
+
+impl AsyncRead for TlsConnection {
+	fn poll_read(
+		mut self: Pin<&mut Self>,
+		context: &mut Context<'_>,
+		buffer: &mut ReadBuf<'_>,
+	) -> Poll<io::Result<()>> {
+		Pin::new(&mut self.stream).poll_read(context, buffer)
+	}
+}
+
+impl AsyncWrite for TlsConnection {
+	fn poll_write(
+		mut self: Pin<&mut Self>,
+		context: &mut Context<'_>,
+		buffer: &[u8],
+	) -> Poll<io::Result<usize>> {
+		Pin::new(&mut self.stream).poll_write(context, buffer)
+	}
+
+	fn poll_flush(mut self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<io::Result<()>> {
+		Pin::new(&mut self.stream).poll_flush(context)
+	}
+
+	fn poll_shutdown(mut self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<io::Result<()>> {
+		Pin::new(&mut self.stream).poll_shutdown(context)
+	}
+}
+
+/// A connection to the admin API's Unix socket. It's always from this host, so it's given the
+/// loopback address for anything that needs the client's IP
+#[cfg(unix)]
+pub struct UnixConnection(tokio::net::UnixStream);
+
+#[cfg(unix)]
+impl Connected<&UnixConnection> for SocketAddr {
+	fn connect_info(_connection: &UnixConnection) -> SocketAddr {
+		SocketAddr::from((Ipv4Addr::LOCALHOST, 0))
+	}
+}
+
+#[cfg(unix)]
+impl AsyncRead for UnixConnection {
+	fn poll_read(
+		mut self: Pin<&mut Self>,
+		context: &mut Context<'_>,
+		buffer: &mut ReadBuf<'_>,
+	) -> Poll<io::Result<()>> {
+		Pin::new(&mut self.0).poll_read(context, buffer)
+	}
+}
+
+#[cfg(unix)]
+impl AsyncWrite for UnixConnection {
+	fn poll_write(
+		mut self: Pin<&mut Self>,
+		context: &mut Context<'_>,
+		buffer: &[u8],
+	) -> Poll<io::Result<usize>> {
+		Pin::new(&mut self.0).poll_write(context, buffer)
+	}
+
+	fn poll_flush(mut self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<io::Result<()>> {
+		Pin::new(&mut self.0).poll_flush(context)
+	}
+
+	fn poll_shutdown(mut self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<io::Result<()>> {
+		Pin::new(&mut self.0).poll_shutdown(context)
+	}
+}
+
+/// Redirect every request to the same place over HTTPS
+fn redirect_router(https_port: u16) -> Router {
+	Router::new().fallback(any(move |headers: HeaderMap, uri: Uri| async move {
+		redirect_to_https(&headers, &uri, https_port)
+	}))
+}
+
+fn redirect_to_https(headers: &HeaderMap, uri: &Uri, https_port: u16) -> Response {
+	let host = headers
+		.get(header::HOST)
+		.and_then(|host| host.to_str().ok())
+		.and_then(|host| host.parse::<Authority>().ok());
+	let Some(host) = host else {
+		return (
+			StatusCode::BAD_REQUEST,
+			"A Host header is needed to redirect to HTTPS",
+		)
+			.into_response();
+	};
+
+	let path = uri.path_and_query().map_or("/", |path| path.as_str());
+	let location = if https_port == 443 {
+		format!("https://{}{path}", host.host())
+	} else {
+		format!("https://{}:{https_port}{path}", host.host())
+	};
+	(
+		StatusCode::PERMANENT_REDIRECT,
+		[(header::LOCATION, location)],
+	)
+		.into_response()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parse_and_redirect_listeners() {
+		assert_eq!(
+			parse_listeners("redirect://0.0.0.0:80, https://[::]:443").unwrap(),
+			vec![
+				Listener {
+					scheme: Scheme::Redirect,
+					address: Address::Tcp("0.0.0.0:80".parse().unwrap()),
+				},
+				Listener {
+					scheme: Scheme::Https,
+					address: Address::Tcp("[::]:443".parse().unwrap()),
+				},
+			]
+		);
+		assert_eq!(
+			parse_listeners("admin://127.0.0.1:54028").unwrap()[0].scheme,
+			Scheme::Admin
+		);
+		let unix = &parse_listeners("unix:///run/urshort/admin.sock").unwrap()[0];
+		assert_eq!(unix.scheme, Scheme::Admin);
+		assert_eq!(unix.to_string(), "unix:///run/urshort/admin.sock");
+		assert!(parse_listeners("0.0.0.0:80").is_err());
+		assert!(parse_listeners("ftp://0.0.0.0:21").is_err());
+		assert!(parse_listeners("http://localhost:80").is_err());
+		assert!(parse_listeners("").is_err());
+
+		let mut headers = HeaderMap::new();
+		headers.insert(header::HOST, "example.com:80".parse().unwrap());
+		let uri: Uri = "/docs?page=2".parse().unwrap();
+		let response = redirect_to_https(&headers, &uri, 443);
+		assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+		assert_eq!(
+			response.headers()[header::LOCATION],
+			"https://example.com/docs?page=2"
+		);
+		assert_eq!(
+			redirect_to_https(&headers, &uri, 8443).headers()[header::LOCATION],
+			"https://example.com:8443/docs?page=2"
+		);
+		assert_eq!(
+			redirect_to_https(&HeaderMap::new(), &uri, 443).status(),
+			StatusCode::BAD_REQUEST
+		);
+	}
+}
@@ -0,0 +1,136 @@
+use std::{collections::HashMap, fs, io, path::Path};
+
+use axum::http::{header, HeaderMap};
+
+/// Translated versions of the built in pages, picked using the client's `Accept-Language`
+#[derive(Default)]
+pub struct Locales {
+	/// Pages by language, then by name
+	pages: HashMap<String, HashMap<String, String>>,
+}
+
+impl Locales {
+	/// Load the pages from a directory with a folder for each language, like `de/error.html`
+	pub fn load(dir: &Path) -> io::Result<Locales> {
+		let mut pages = HashMap::new();
+		for language in fs::read_dir(dir)? {
+			let language = language?;
+			if !language.file_type()?.is_dir() {
+				continue;
+			}
+
+			let mut language_pages = HashMap::new();
+			for page in fs::read_dir(language.path())? {
+				let path = page?.path();
+				if path.extension().and_then(|extension| extension.to_str()) != Some("html") {
+					continue;
+				}
+				if let Some(name) = path.file_stem().and_then(|name| name.to_str()) {
+					language_pages.insert(name.to_string(), fs::read_to_string(&path)?);
+				}
+			}
+
+			if !language_pages.is_empty() {
+				let name = language.file_name().to_string_lossy().to_ascii_lowercase();
+				pages.insert(name, language_pages);
+			}
+		}
+		Ok(Locales { pages })
+	}
+
+	/// Create `Locales` from `(language, name, page)` entries
+	#[cfg(test)]
+	pub fn from_pages(entries: &[(&str, &str, &str)]) -> Locales {
+		let mut pages: HashMap<String, HashMap<String, String>> = HashMap::new();
+		for (language, name, page) in entries {
+			pages
+				.entry((*language).to_string())
+				.or_default()
+				.insert((*name).to_string(), (*page).to_string());
+		}
+		Locales { pages }
+	}
+
+	/// The languages that have pages
+	pub fn languages(&self) -> impl Iterator<Item = &String> {
+		self.pages.keys()
+	}
+
+	/// Get the page in the client's most preferred language that has it, along with the language
+	pub fn page(&self, name: &str, headers: &HeaderMap) -> Option<(&str, &str)> {
+		let accept_language = headers
+			.get(header::ACCEPT_LANGUAGE)
+			.and_then(|value| value.to_str().ok())
+			.unwrap_or_default();
+
+		preferred_languages(accept_language)
+			.iter()
+			.find_map(|language| {
+				// Fall back from a regional variant like `pt-br` to `pt`
+				let primary = language.split('-').next().unwrap_or_default();
+				[language.as_str(), primary].iter().find_map(|language| {
+					let (language, pages) = self.pages.get_key_value(*language)?;
+					Some((language.as_str(), pages.get(name)?.as_str()))
+				})
+			})
+	}
+}
+
+/// Get the languages from an `Accept-Language` header, from most to least preferred
+pub fn preferred_languages(accept_language: &str) -> Vec<String> {
+	let mut languages: Vec<(String, f32)> = accept_language
+		.split(',')
+		.filter_map(|entry| {
+			let mut parts = entry.split(';');
+			let language = parts.next()?.trim().to_ascii_lowercase();
+			let quality = parts
+				.find_map(|part| part.trim().strip_prefix("q="))
+				.map_or(Some(1.0), |quality| quality.trim().parse().ok())?;
+			(!language.is_empty() && language != "*" && quality > 0.0)
+				.then_some((language, quality))
+		})
+		.collect();
+
+	// Stable, so languages with the same quality keep their order
+	languages.sort_by(|a, b| b.1.total_cmp(&a.1));
+	languages
+		.into_iter()
+		.map(|(language, _)| language)
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use axum::http::HeaderValue;
+
+	use super::*;
+
+	#[test]
+	fn pick_localized_pages() {
+		assert_eq!(
+			preferred_languages("fr-CH, fr;q=0.9, en;q=0.8, de;q=0.7, *;q=0.5, nl;q=0"),
+			vec!["fr-ch", "fr", "en", "de"]
+		);
+		assert_eq!(
+			preferred_languages("da, en-gb;q=0.8, en"),
+			vec!["da", "en", "en-gb"]
+		);
+		assert!(preferred_languages("").is_empty());
+
+		let locales = Locales::from_pages(&[("de", "error", "Fehler"), ("pt-br", "error", "Erro")]);
+		let page = |accept_language| {
+			let mut headers = HeaderMap::new();
+			headers.insert(
+				header::ACCEPT_LANGUAGE,
+				HeaderValue::from_static(accept_language),
+			);
+			locales.page("error", &headers)
+		};
+
+		assert_eq!(page("de-AT, en;q=0.5"), Some(("de", "Fehler")));
+		assert_eq!(page("fr, pt-BR;q=0.9, de;q=0.8"), Some(("pt-br", "Erro")));
+		assert_eq!(page("pt"), None);
+		assert_eq!(page("en"), None);
+		assert_eq!(locales.page("index", &HeaderMap::new()), None);
+	}
+}
@@ -0,0 +1,93 @@
+use std::{
+	sync::Mutex,
+	time::{Duration, Instant},
+};
+
+/// The most lines of one kind printed each window
+const LINES_PER_WINDOW: usize = 10;
+/// How long a window lasts
+const WINDOW: Duration = Duration::from_mins(1);
+
+/// Limits how often a line that can happen on every request is printed, so a burst of them can't
+/// flood the log. Each key, like the path, is printed once a window, and the lines that weren't
+/// printed are counted when the next window starts
+pub struct LogLimit {
+	/// What the lines are about, like `shadow differences`
+	what: &'static str,
+	window: Mutex<Window>,
+}
+
+struct Window {
+	started: Option<Instant>,
+	printed: Vec<String>,
+	skipped: u64,
+}
+
+impl LogLimit {
+	/// Create a new `LogLimit` for lines about `what`
+	pub const fn new(what: &'static str) -> LogLimit {
+		LogLimit {
+			what,
+			window: Mutex::new(Window {
+				started: None,
+				printed: Vec::new(),
+				skipped: 0,
+			}),
+		}
+	}
+
+	/// Print the line for the key, unless it's already been printed this window or too many
+	/// others have been
+	pub fn print(&self, key: &str, line: impl FnOnce() -> String) {
+		if self.allow(key, Instant::now()) {
+			println!("{}", line());
+		}
+	}
+
+	fn allow(&self, key: &str, now: Instant) -> bool {
+		let mut window = self.window.lock().unwrap();
+		if window
+			.started
+			.is_none_or(|started| now.duration_since(started) >= WINDOW)
+		{
+			if window.skipped > 0 {
+				println!(
+					"{} more {} weren't printed in the last minute",
+					window.skipped, self.what
+				);
+			}
+			window.started = Some(now);
+			window.printed.clear();
+			window.skipped = 0;
+		}
+
+		if window.printed.len() >= LINES_PER_WINDOW || window.printed.iter().any(|x| x == key) {
+			window.skipped += 1;
+			return false;
+		}
+		window.printed.push(key.to_string());
+		true
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn limit_lines() {
+		let log = LogLimit::new("tests");
+		let now = Instant::now();
+		assert!(log.allow("a", now));
+		assert!(!log.allow("a", now));
+		for key in 1..LINES_PER_WINDOW {
+			assert!(log.allow(&key.to_string(), now));
+		}
+		assert!(!log.allow("b", now));
+
+		// A new window starts over
+		let later = now + WINDOW;
+		assert!(log.allow("a", later));
+		assert!(log.allow("b", later));
+	}
+}
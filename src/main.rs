@@ -2,100 +2,2486 @@
 #![allow(clippy::unused_async)]
 
 use axum::{
-	extract::Path,
-	response::{Html, Redirect},
-	routing::get,
+	body::Body,
+	extract::{ConnectInfo, Path, Query, RawBody},
+	http::{
+		header::{self, HeaderName},
+		HeaderMap, HeaderValue, Method, StatusCode, Uri,
+	},
+	middleware,
+	response::{IntoResponse, Response},
+	routing::{any, get},
 	Router,
 };
-use dotenv::dotenv;
 
-use std::{env, future::Future, net::SocketAddr, sync::Arc};
+use serde::Serialize;
+use std::{
+	any::Any,
+	borrow::Cow,
+	collections::{BTreeMap, HashMap},
+	env,
+	ffi::OsString,
+	net::{IpAddr, SocketAddr},
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc, Mutex,
+	},
+	time::{Duration, Instant},
+};
+use tokio::runtime::{self, Runtime};
+use tokio_rustls::rustls::ServerConfig;
+use tower_http::catch_panic::CatchPanicLayer;
+
+/// Whether startup is described with one JSON object, instead of the banner
+static JSON_STARTUP: AtomicBool = AtomicBool::new(false);
+/// Warnings from loading the configuration, held back until it's known how startup is described,
+/// so they can be kept out of the JSON
+static WARNINGS: Mutex<Option<Vec<String>>> = Mutex::new(Some(Vec::new()));
+/// Paths the shadow mappings would send somewhere else, printed a few at a time
+static SHADOW_LOG: LogLimit = LogLimit::new("shadow differences");
+/// Redirects stopped by the target policy, printed a few at a time
+static BLOCKED_LOG: LogLimit = LogLimit::new("blocked redirects");
+
+/// Print a line of the startup banner, unless startup is being described with JSON instead
+macro_rules! banner {
+	($($arg:tt)*) => {
+		if !JSON_STARTUP.load(Ordering::Relaxed) {
+			println!($($arg)*);
+		}
+	};
+}
+
+/// Print a warning about the configuration, like a setting that was ignored. With no arguments,
+/// it's the blank line after one
+macro_rules! warning {
+	() => {
+		warn(String::new())
+	};
+	($($arg:tt)*) => {
+		warn(format!($($arg)*))
+	};
+}
 
+#[cfg(feature = "admin")]
+mod admin;
+mod auth_log;
+mod bouncer;
+mod bundles;
+mod canary;
+mod chaos;
+mod client_ip;
+mod clock;
+mod commands;
+mod drain;
+mod enumeration_guard;
 mod environment;
+mod events;
+mod flatten;
+mod forms;
+mod html;
+mod ip_ranges;
+mod jobs;
+mod keys;
+mod languages;
+mod limits;
+mod lint;
+mod listeners;
+mod locales;
+mod log_limit;
+mod maintenance;
+mod markdown;
+#[cfg(feature = "admin")]
+mod memory;
+#[cfg(feature = "metrics")]
+mod metrics;
+mod mirrors;
+mod mock;
+mod pages;
+mod panics;
+mod pattern_guard;
+mod profiles;
+#[cfg(feature = "profiling")]
+mod profiling;
+mod proxy;
+mod purge;
+mod resolver;
+mod schedule;
+#[cfg(feature = "scripting")]
+mod script_resolver;
+#[cfg(feature = "admin")]
+mod search;
+mod shadow;
+mod signing;
+#[cfg(feature = "admin")]
+mod slo;
+mod static_assets;
+mod subdomains;
+mod swaps;
+mod targets;
+mod theme;
+mod upstream;
 mod uri_mappings;
+#[cfg(feature = "admin")]
+mod validate;
+mod warm_up;
+#[cfg(feature = "wasm")]
+mod wasm_resolver;
+mod well_known;
+#[cfg(feature = "metrics")]
+use crate::metrics::{parse_buckets, Metrics, Outcome, DEFAULT_BUCKETS};
 use crate::{
-	environment::{extract_pattern_uris, extract_port_number, extract_standard_uris},
-	uri_mappings::UriMappings,
+	bouncer::Bouncer,
+	bundles::Bundle,
+	canary::CanaryResolver,
+	chaos::{inject, Chaos},
+	client_ip::client_ip,
+	enumeration_guard::{EnumerationGuard, GuardSettings, Penalty},
+	environment::{
+		expand_variables, extract_pattern_uris, extract_pattern_values, extract_port_number,
+		extract_standard_uris, extract_standard_values, extract_value, find_file,
+		find_included_files, merge_env_files, read_env_file,
+	},
+	events::EventLog,
+	flatten::Flattener,
+	forms::{Form, FormResolver},
+	ip_ranges::IpRanges,
+	jobs::{Cron, JobSchedule, Jobs},
+	keys::normalize_key,
+	languages::{LanguageResolver, Translations},
+	limits::{Limits, MIN_HEADER_SIZE},
+	lint::{Linter, Rule, Severity},
+	listeners::{load_tls_config, parse_listeners, Address, Bound, Listener, Scheme},
+	locales::Locales,
+	log_limit::LogLimit,
+	maintenance::Maintenance,
+	mirrors::{Balance, MirrorResolver, Mirrors},
+	pages::{Page, Pages, Strings},
+	profiles::{Profile, PROFILE_PREFIX},
+	proxy::Proxy,
+	purge::{Cdn, Purger},
+	resolver::{FallbackResolver, PageResolver, Resolution, ResolveRequest, ResolverChain, Target},
+	schedule::{Schedule, ScheduleResolver},
+	shadow::Shadow,
+	signing::Signer,
+	static_assets::StaticAssets,
+	subdomains::{parse_host, subdomain_key},
+	swaps::{Swap, SwapResolver},
+	targets::TargetPolicy,
+	theme::{parse_color, Theme},
+	upstream::UpstreamResolver,
+	uri_mappings::{LinkOptions, UriMappings},
+	warm_up::{popular_hosts, warm_up},
+	well_known::WellKnown,
 };
+#[cfg(feature = "admin")]
+use crate::{client_ip::ClientIpHeader, memory::Caches, slo::Slo};
 
+const ENVIRONMENT_ENV_NAME: &str = "URSHORT_ENV";
+const INCLUDE_ENV_NAME: &str = "URSHORT_INCLUDE";
 const STANDARD_URI_ENV_NAME: &str = "URSHORT_STANDARD_URI_";
+const STANDARD_DELAY_ENV_NAME: &str = "URSHORT_STANDARD_DELAY_";
+const STANDARD_ALLOW_ENV_NAME: &str = "URSHORT_STANDARD_ALLOW_";
+const STANDARD_PASSTHROUGH_ENV_NAME: &str = "URSHORT_STANDARD_PASSTHROUGH_";
+const STANDARD_STATUS_ENV_NAME: &str = "URSHORT_STANDARD_STATUS_";
+const STANDARD_PROXY_ENV_NAME: &str = "URSHORT_STANDARD_PROXY_";
+const STANDARD_REFERRER_ENV_NAME: &str = "URSHORT_STANDARD_REFERRER_";
+const STANDARD_NOTE_ENV_NAME: &str = "URSHORT_STANDARD_NOTE_";
 const PATTERN_URI_ENV_NAME: &str = "URSHORT_PATTERN_URI_";
 const PATTERN_REGEX_ENV_NAME: &str = "URSHORT_PATTERN_REGEX_";
+const PATTERN_DELAY_ENV_NAME: &str = "URSHORT_PATTERN_DELAY_";
+const PATTERN_ALLOW_ENV_NAME: &str = "URSHORT_PATTERN_ALLOW_";
+const PATTERN_PASSTHROUGH_ENV_NAME: &str = "URSHORT_PATTERN_PASSTHROUGH_";
+const PATTERN_STATUS_ENV_NAME: &str = "URSHORT_PATTERN_STATUS_";
+const PATTERN_PROXY_ENV_NAME: &str = "URSHORT_PATTERN_PROXY_";
+const PATTERN_REFERRER_ENV_NAME: &str = "URSHORT_PATTERN_REFERRER_";
+const PATTERN_NOTE_ENV_NAME: &str = "URSHORT_PATTERN_NOTE_";
+const PATTERN_BUDGET_ENV_NAME: &str = "URSHORT_PATTERN_BUDGET";
+const PATTERN_STRIKES_ENV_NAME: &str = "URSHORT_PATTERN_STRIKES";
+const DEFAULT_PATTERN_STRIKES: usize = 5;
+const CANARY_URI_ENV_NAME: &str = "URSHORT_CANARY_URI_";
+const LANGUAGE_URI_ENV_NAME: &str = "URSHORT_LANGUAGE_URI_";
+const SWAP_URI_ENV_NAME: &str = "URSHORT_SWAP_URI_";
+const SWAP_AT_ENV_NAME: &str = "URSHORT_SWAP_AT_";
+const SCHEDULE_URI_ENV_NAME: &str = "URSHORT_SCHEDULE_URI_";
+const MIRROR_URI_ENV_NAME: &str = "URSHORT_MIRROR_URI_";
+const MIRROR_BALANCE_ENV_NAME: &str = "URSHORT_MIRROR_BALANCE_";
+const MIRROR_CHECK_INTERVAL_ENV_NAME: &str = "URSHORT_MIRROR_CHECK_INTERVAL";
+const DEFAULT_MIRROR_CHECK_INTERVAL: u64 = 30;
+const BUNDLE_URI_ENV_NAME: &str = "URSHORT_BUNDLE_URI_";
+const BUNDLE_TITLE_ENV_NAME: &str = "URSHORT_BUNDLE_TITLE_";
+const MARKDOWN_PAGE_ENV_NAME: &str = "URSHORT_MARKDOWN_PAGE_";
+const PROFILE_LINKS_ENV_NAME: &str = "URSHORT_PROFILE_LINKS_";
+const PROFILE_NAME_ENV_NAME: &str = "URSHORT_PROFILE_NAME_";
+const PROFILE_BIO_ENV_NAME: &str = "URSHORT_PROFILE_BIO_";
+const PROFILE_AVATAR_ENV_NAME: &str = "URSHORT_PROFILE_AVATAR_";
+const FORM_URI_ENV_NAME: &str = "URSHORT_FORM_URI_";
+const FALLBACK_URI_ENV_NAME: &str = "URSHORT_FALLBACK_URI";
+const UPSTREAM_URI_ENV_NAME: &str = "URSHORT_UPSTREAM_URI";
+const UPSTREAM_CACHE_DURATION_ENV_NAME: &str = "URSHORT_UPSTREAM_CACHE_DURATION";
+const DEFAULT_UPSTREAM_CACHE_DURATION: u64 = 300;
+#[cfg(feature = "scripting")]
+const SCRIPT_REGEX_ENV_NAME: &str = "URSHORT_SCRIPT_REGEX_";
+#[cfg(feature = "scripting")]
+const SCRIPT_ENV_NAME: &str = "URSHORT_SCRIPT_";
+#[cfg(feature = "wasm")]
+const WASM_RESOLVER_ENV_NAME: &str = "URSHORT_WASM_RESOLVER";
+#[cfg(feature = "wasm")]
+const WASM_FUEL_ENV_NAME: &str = "URSHORT_WASM_FUEL";
+#[cfg(feature = "wasm")]
+const DEFAULT_WASM_FUEL: u64 = 1_000_000;
+const ALLOWED_SCHEMES_ENV_NAME: &str = "URSHORT_ALLOWED_SCHEMES";
+const BASE_URL_ENV_NAME: &str = "URSHORT_BASE_URL";
+const ROOT_REDIRECT_ENV_NAME: &str = "URSHORT_ROOT_REDIRECT";
+const WELL_KNOWN_DIR_ENV_NAME: &str = "URSHORT_WELL_KNOWN_DIR";
+const WELL_KNOWN_URI_ENV_NAME: &str = "URSHORT_WELL_KNOWN_URI";
+const FLATTEN_REDIRECTS_ENV_NAME: &str = "URSHORT_FLATTEN_REDIRECTS";
+const FLATTEN_INTERVAL_ENV_NAME: &str = "URSHORT_FLATTEN_INTERVAL";
+/// Every hour, in seconds
+const DEFAULT_FLATTEN_INTERVAL: u64 = 3600;
+const SHADOW_FILE_ENV_NAME: &str = "URSHORT_SHADOW_FILE";
+const PRIVATE_URI_ENV_NAME: &str = "URSHORT_PRIVATE_URI_";
+const SIGNING_SECRET_ENV_NAME: &str = "URSHORT_SIGNING_SECRET";
+const CANONICAL_URL_ENV_NAME: &str = "URSHORT_CANONICAL_URL";
+const PURGE_CDN_ENV_NAME: &str = "URSHORT_PURGE_CDN";
+const PURGE_TOKEN_ENV_NAME: &str = "URSHORT_PURGE_TOKEN";
+const EVENT_LOG_DIR_ENV_NAME: &str = "URSHORT_EVENT_LOG_DIR";
+const EVENT_LOG_SEGMENT_SIZE_ENV_NAME: &str = "URSHORT_EVENT_LOG_SEGMENT_SIZE";
+/// 16 MiB, in bytes
+const DEFAULT_EVENT_LOG_SEGMENT_SIZE: u64 = 16 * 1024 * 1024;
+const EVENT_LOG_SEGMENTS_ENV_NAME: &str = "URSHORT_EVENT_LOG_SEGMENTS";
+const DEFAULT_EVENT_LOG_SEGMENTS: usize = 10;
+const CHAOS_LATENCY_ENV_NAME: &str = "URSHORT_CHAOS_LATENCY";
+const CHAOS_ERROR_RATE_ENV_NAME: &str = "URSHORT_CHAOS_ERROR_RATE";
+const CHAOS_ERROR_STATUS_ENV_NAME: &str = "URSHORT_CHAOS_ERROR_STATUS";
+const EVENT_LOG_SAMPLE_ENV_NAME: &str = "URSHORT_EVENT_LOG_SAMPLE";
+const SUBDOMAIN_HOST_ENV_NAME: &str = "URSHORT_SUBDOMAIN_HOST";
 const PORT_ENV_NAME: &str = "URSHORT_PORT";
+const LISTENERS_ENV_NAME: &str = "URSHORT_LISTENERS";
+const TLS_CERT_ENV_NAME: &str = "URSHORT_TLS_CERT";
+const TLS_KEY_ENV_NAME: &str = "URSHORT_TLS_KEY";
+const CROWDSEC_URL_ENV_NAME: &str = "URSHORT_CROWDSEC_URL";
+const CROWDSEC_API_KEY_ENV_NAME: &str = "URSHORT_CROWDSEC_API_KEY";
+const CROWDSEC_TARPIT_ENV_NAME: &str = "URSHORT_CROWDSEC_TARPIT";
+const CROWDSEC_CACHE_ENV_NAME: &str = "URSHORT_CROWDSEC_CACHE";
+const DEFAULT_CROWDSEC_CACHE: u64 = 60;
+const MAX_HEADER_SIZE_ENV_NAME: &str = "URSHORT_MAX_HEADER_SIZE";
+const HEADER_READ_TIMEOUT_ENV_NAME: &str = "URSHORT_HEADER_READ_TIMEOUT";
+const BODY_READ_TIMEOUT_ENV_NAME: &str = "URSHORT_BODY_READ_TIMEOUT";
+const DRAIN_TIMEOUT_ENV_NAME: &str = "URSHORT_DRAIN_TIMEOUT";
+const DEFAULT_DRAIN_TIMEOUT: u64 = 30;
 const DEFAULT_PORT: u16 = 54027;
+const CLIENT_IP_HEADER_ENV_NAME: &str = "URSHORT_CLIENT_IP_HEADER";
+const MISS_LIMIT_ENV_NAME: &str = "URSHORT_MISS_LIMIT";
+const MISS_WINDOW_ENV_NAME: &str = "URSHORT_MISS_WINDOW";
+const DEFAULT_MISS_WINDOW: u64 = 60;
+const MISS_DELAY_ENV_NAME: &str = "URSHORT_MISS_DELAY";
+const DEFAULT_MISS_DELAY: u64 = 1000;
+const MISS_BAN_LIMIT_ENV_NAME: &str = "URSHORT_MISS_BAN_LIMIT";
+const MISS_BAN_DURATION_ENV_NAME: &str = "URSHORT_MISS_BAN_DURATION";
+const DEFAULT_MISS_BAN_DURATION: u64 = 600;
+const LOCALE_DIR_ENV_NAME: &str = "URSHORT_LOCALE_DIR";
+const THEME_TEXT_ENV_NAME: &str = "URSHORT_THEME_TEXT";
+const THEME_BACKGROUND_ENV_NAME: &str = "URSHORT_THEME_BACKGROUND";
+const THEME_ACCENT_ENV_NAME: &str = "URSHORT_THEME_ACCENT";
+const THEME_DARK_TEXT_ENV_NAME: &str = "URSHORT_THEME_DARK_TEXT";
+const THEME_DARK_BACKGROUND_ENV_NAME: &str = "URSHORT_THEME_DARK_BACKGROUND";
+const THEME_DARK_ACCENT_ENV_NAME: &str = "URSHORT_THEME_DARK_ACCENT";
+const THEME_LOGO_ENV_NAME: &str = "URSHORT_THEME_LOGO";
+const THEME_FOOTER_ENV_NAME: &str = "URSHORT_THEME_FOOTER";
+const LANGUAGE_ENV_NAME: &str = "URSHORT_LANGUAGE";
+const INDEX_TITLE_ENV_NAME: &str = "URSHORT_INDEX_TITLE";
+const INDEX_HEADING_ENV_NAME: &str = "URSHORT_INDEX_HEADING";
+const INDEX_MESSAGE_ENV_NAME: &str = "URSHORT_INDEX_MESSAGE";
+const ERROR_TITLE_ENV_NAME: &str = "URSHORT_ERROR_TITLE";
+const ERROR_HEADING_ENV_NAME: &str = "URSHORT_ERROR_HEADING";
+const ERROR_MESSAGE_ENV_NAME: &str = "URSHORT_ERROR_MESSAGE";
+const WORKER_THREADS_ENV_NAME: &str = "URSHORT_WORKER_THREADS";
+const MAX_BLOCKING_THREADS_ENV_NAME: &str = "URSHORT_MAX_BLOCKING_THREADS";
+const WARM_UP_HOSTS_ENV_NAME: &str = "URSHORT_WARM_UP_HOSTS";
+const STARTUP_OUTPUT_ENV_NAME: &str = "URSHORT_STARTUP_OUTPUT";
+#[cfg(feature = "metrics")]
+const METRICS_ENV_NAME: &str = "URSHORT_METRICS";
+#[cfg(feature = "metrics")]
+const METRICS_BUCKETS_ENV_NAME: &str = "URSHORT_METRICS_BUCKETS";
+const JOB_CRON_ENV_NAME: &str = "URSHORT_JOB_CRON_";
+const LINT_ENV_NAME: &str = "URSHORT_LINT_";
+#[cfg(feature = "admin")]
+const ADMIN_TOKEN_ENV_NAME: &str = "URSHORT_ADMIN_TOKEN";
+const MAINTENANCE_NOTICE_ENV_NAME: &str = "URSHORT_MAINTENANCE_NOTICE";
+const DEFAULT_MAINTENANCE_NOTICE: &str =
+	"URShort is undergoing maintenance. Existing links will keep working.";
+
+fn main() {
+	let loaded_env_files = load_env_files();
+
+	// Run a one off command instead of the server if one was given
+	let args: Vec<String> = env::args().skip(1).collect();
+	if let Some(command) = args.first() {
+		print_warnings();
+		let result = match command.as_str() {
+			"sign" => commands::sign(
+				&args[1..],
+				load_signer(),
+				load_canonical_url(&load_target_policy()).as_deref(),
+			),
+			"diff" => commands::diff(&args[1..]),
+			"check" => commands::check(&lint_targets(), &load_linter()),
+			"replay" => commands::replay(&args[1..], &load_uri_mappings(&load_target_policy())),
+			#[cfg(feature = "admin")]
+			"open" => load_runtime()
+				.map_err(|error| format!("Could not start the runtime, because {error}"))
+				.and_then(|runtime| {
+					runtime.block_on(commands::open(
+						&args[1..],
+						&load_instance_url(),
+						extract_value(env::vars_os(), ADMIN_TOKEN_ENV_NAME),
+					))
+				}),
+			"purge" => load_runtime()
+				.map_err(|error| format!("Could not start the runtime, because {error}"))
+				.and_then(|runtime| {
+					runtime.block_on(commands::purge(
+						&args[1..],
+						load_purger(),
+						load_canonical_url(&load_target_policy()).as_deref(),
+					))
+				}),
+			"events" => commands::events(&args[1..]),
+			"mock" => commands::mock(&args[1..], DEFAULT_PORT),
+			"shell-helper" => {
+				commands::shell_helper(&load_instance_url());
+				Ok(())
+			}
+			"dashboards" => {
+				commands::dashboards();
+				Ok(())
+			}
+			_ => Err(format!("Unknown command '{command}'")),
+		};
+		if let Err(error) = result {
+			eprintln!("{error}");
+			std::process::exit(1);
+		}
+		return;
+	}
+
+	load_startup_output();
+	std::panic::set_hook(Box::new(panics::log_panic));
 
-#[tokio::main]
-async fn main() {
 	// Notify user if the .env file was used, but don't if one was not found
 	// as it may be confusing if one was used by docker, but not passed locally
-	if dotenv().is_ok() {
-		println!("Loaded local '.env' file");
+	for name in &loaded_env_files {
+		banner!("Loaded local '{name}' file");
 	}
-	println!();
+	banner!();
 
+	match load_runtime() {
+		Ok(runtime) => runtime.block_on(serve()),
+		Err(error) => {
+			print_warnings();
+			eprintln!("Could not start the runtime, because {error}");
+			std::process::exit(1);
+		}
+	}
+}
+
+/// Load the configuration and serve the links
+async fn serve() {
 	// Load the envirmental variables
-	let standard_uris = extract_standard_uris(env::vars_os(), STANDARD_URI_ENV_NAME);
+	let targets = load_target_policy();
+	let uri_mappings = Arc::new(load_pattern_guard(load_uri_mappings(&targets)));
+	#[cfg(feature = "admin")]
+	let canonical_url = load_canonical_url(&targets);
+	#[cfg(feature = "admin")]
+	if let Some(url) = &canonical_url {
+		banner!("Links are shared as {url}/...");
+		banner!();
+	}
+
+	let client_ip_header: Option<HeaderName> =
+		extract_value(env::vars_os(), CLIENT_IP_HEADER_ENV_NAME);
+	let jobs = Arc::new(Jobs::default());
+	let guard = load_guard(&jobs);
+
+	print_uri_mappings(&uri_mappings);
+	start_warm_up(&uri_mappings);
+	let resolver = Arc::new(load_resolver(uri_mappings.clone(), &targets, &jobs));
+
+	let maintenance = Arc::new(Maintenance::new(
+		extract_value(env::vars_os(), MAINTENANCE_NOTICE_ENV_NAME)
+			.unwrap_or_else(|| DEFAULT_MAINTENANCE_NOTICE.to_string()),
+	));
+
+	let pages = Arc::new(load_pages());
+	let well_known = load_well_known(&targets);
+	#[cfg(feature = "metrics")]
+	let metrics = load_metrics();
+	#[cfg(feature = "admin")]
+	let admin_token = extract_value::<_, String>(env::vars_os(), ADMIN_TOKEN_ENV_NAME)
+		.filter(|token| !token.is_empty());
+	#[cfg(feature = "admin")]
+	let slo = admin_token.as_ref().map(|_| Arc::new(Slo::default()));
+	let links = Arc::new(Links {
+		resolver,
+		guard,
+		pages,
+		#[cfg(feature = "metrics")]
+		metrics: metrics.clone(),
+		subdomain_host: load_subdomain_host(),
+		root_redirect: load_root_redirect(&targets),
+		locations: render_locations(&uri_mappings),
+		shadow: load_shadow(&targets),
+		proxy: load_proxy(&uri_mappings, &targets),
+		targets,
+		flattener: load_flattener(&uri_mappings, &jobs),
+		events: load_event_log(),
+		bouncer: load_bouncer(),
+		#[cfg(feature = "admin")]
+		slo: slo.clone(),
+	});
+
+	#[cfg(feature = "admin")]
+	let caches = slo.map(|slo| links.caches(slo));
+
+	let chaos = load_chaos();
+	let mut startup = Startup::new(&uri_mappings, &links, chaos.is_some());
+
+	// Setup REST API
+	let catch_panics = links.catch_panics();
+	let mut app = router(links, maintenance.clone(), client_ip_header.clone(), chaos);
+
+	// `GET /metrics` for Prometheus, only if it was asked for
+	#[cfg(feature = "metrics")]
+	if let Some(metrics) = metrics {
+		app = app.merge(metrics::router(metrics));
+		startup.subsystems.push("metrics");
+		banner!("Metrics enabled at /metrics");
+		banner!();
+	}
+
+	// `GET /.well-known/*` for discovery files, only if they were set up
+	if let Some(well_known) = well_known {
+		app = app.merge(well_known::router(well_known));
+		startup.subsystems.push("well_known");
+	}
+
+	// `/api/*` for managing the instance, only if there is a token to protect it. It's served on
+	// the admin listeners instead of with the links, if there are any
+	let (listeners, bound) = bind_listeners();
+	#[cfg(feature = "admin")]
+	let admin_app = match (admin_token, caches) {
+		(Some(token), Some(caches)) => {
+			let routes = admin_routes(
+				token,
+				client_ip_header,
+				maintenance,
+				uri_mappings,
+				jobs,
+				canonical_url,
+				caches,
+			);
+			startup.subsystems.push("admin");
+			if bound.iter().any(Bound::is_admin) {
+				Some(routes)
+			} else {
+				app = app.merge(routes);
+				None
+			}
+		}
+		_ => None,
+	};
+	#[cfg(not(feature = "admin"))]
+	let admin_app: Option<Router> = None;
+
+	startup.announce(&listeners);
+	drain::serve(
+		app.layer(catch_panics.clone()),
+		admin_app.map(|admin_app| admin_app.layer(catch_panics)),
+		bound,
+		load_drain_timeout(),
+	)
+	.await;
+}
+
+/// The `/api/*` routes for managing the instance
+#[cfg(feature = "admin")]
+fn admin_routes(
+	token: String,
+	client_ip_header: Option<HeaderName>,
+	maintenance: Arc<Maintenance>,
+	uri_mappings: Arc<UriMappings>,
+	jobs: Arc<Jobs>,
+	canonical_url: Option<String>,
+	caches: Caches,
+) -> Router {
+	banner!("Admin API enabled");
+	banner!();
+	Router::new()
+		.nest(
+			"/api",
+			admin::router(
+				token,
+				maintenance,
+				uri_mappings.clone(),
+				jobs,
+				validate::Validator::new(
+					uri_mappings,
+					STANDARD_URI_ENV_NAME,
+					PATTERN_REGEX_ENV_NAME,
+				),
+				canonical_url,
+				caches,
+			),
+		)
+		// Outside the routes, so failing the token check can still log the client
+		.layer(axum::Extension(ClientIpHeader(client_ip_header)))
+}
+
+/// What started, for when startup is described with JSON
+#[derive(Serialize)]
+struct Startup {
+	listeners: Vec<String>,
+	mappings: StartupMappings,
+	/// The optional parts that are on
+	subsystems: Vec<&'static str>,
+	/// Warnings from loading the configuration, like settings that were ignored
+	warnings: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct StartupMappings {
+	standard: usize,
+	pattern: usize,
+	private: usize,
+}
+
+impl Startup {
+	/// Describe the mappings and the subsystems the links use
+	fn new(uri_mappings: &UriMappings, links: &Links, chaos: bool) -> Startup {
+		let subsystems = [
+			("enumeration_guard", links.guard.is_some()),
+			("subdomains", links.subdomain_host.is_some()),
+			("root_redirect", links.root_redirect.is_some()),
+			("shadow", links.shadow.is_some()),
+			("flatten_redirects", links.flattener.is_some()),
+			("proxy", links.proxy.is_some()),
+			("event_log", links.events.is_some()),
+			("crowdsec", links.bouncer.is_some()),
+			("chaos", chaos),
+		];
+		Startup {
+			listeners: Vec::new(),
+			mappings: StartupMappings {
+				standard: uri_mappings.standard.len(),
+				pattern: uri_mappings.pattern.len(),
+				private: uri_mappings.private.len(),
+			},
+			subsystems: subsystems
+				.into_iter()
+				.filter_map(|(name, enabled)| enabled.then_some(name))
+				.collect(),
+			warnings: Vec::new(),
+		}
+	}
+
+	/// Say where the links are being served, as JSON if it was asked for
+	fn announce(self, listeners: &[Listener]) {
+		if JSON_STARTUP.load(Ordering::Relaxed) {
+			println!("{}", self.describe(listeners));
+		} else {
+			for listener in listeners {
+				match (listener.scheme, &listener.address) {
+					(Scheme::Redirect, _) => {
+						println!("Redirecting http://{} to HTTPS", listener.address);
+					}
+					(Scheme::Admin, Address::Tcp(address)) => {
+						println!("Admin API on http://{address}");
+					}
+					(Scheme::Admin, Address::Unix(_)) => println!("Admin API on {listener}"),
+					_ => println!("Listening on {listener}"),
+				}
+			}
+		}
+	}
+
+	/// Describe startup as one line of JSON, with the warnings that were held back for it
+	fn describe(mut self, listeners: &[Listener]) -> String {
+		self.listeners = listeners.iter().map(Listener::to_string).collect();
+		self.warnings = take_warnings()
+			.into_iter()
+			.filter(|line| !line.is_empty())
+			.collect();
+		serde_json::to_string(&self)
+			.unwrap_or_else(|error| format!("Could not describe startup, because {error}"))
+	}
+}
+
+/// The routes for the pages and links, slowed down or failed on purpose if there's chaos. Only
+/// these routes get the chaos, so metrics and the admin API keep answering
+fn router(
+	links: Arc<Links>,
+	maintenance: Arc<Maintenance>,
+	client_ip_header: Option<HeaderName>,
+	chaos: Option<Arc<Chaos>>,
+) -> Router {
+	let index_links = links.clone();
+	let index_client_ip_header = client_ip_header.clone();
+	let static_links = links.clone();
+	let routes = Router::new()
+		// `GET /` for homepage, or the link for the subdomain
+		.route(
+			"/",
+			get(
+				move |Query(query): Query<HashMap<String, String>>,
+				      ConnectInfo(peer): ConnectInfo<SocketAddr>,
+				      headers: HeaderMap| {
+					let client = client_ip(&headers, peer, index_client_ip_header.as_ref());
+					index_page(
+						query,
+						headers,
+						client,
+						maintenance.clone(),
+						index_links.clone(),
+					)
+				},
+			),
+		)
+		// `GET /static/:file` for the files pages load
+		.route(
+			"/static/:file",
+			get(move |Path(file): Path<String>| async move {
+				static_links.pages.assets.response(&file)
+			}),
+		)
+		// `GET /:parameter` for vanity URL or error page if it fails, and any other method for
+		// links that pass it through
+		.route(
+			"/:parameter",
+			any(
+				move |method: Method,
+				      Path(parameter): Path<String>,
+				      Query(query): Query<HashMap<String, String>>,
+				      ConnectInfo(peer): ConnectInfo<SocketAddr>,
+				      headers: HeaderMap,
+				      RawBody(body): RawBody| {
+					let client = client_ip(&headers, peer, client_ip_header.as_ref());
+					get_match_and_redirect(
+						method,
+						parameter,
+						query,
+						headers,
+						body,
+						client,
+						links.clone(),
+					)
+				},
+			),
+		);
+
+	match chaos {
+		Some(chaos) => routes.layer(middleware::from_fn(move |request, next| {
+			inject(request, next, chaos.clone())
+		})),
+		None => routes,
+	}
+}
+
+/// Print the warning, or hold it back if they still are
+fn warn(line: String) {
+	match WARNINGS.lock().unwrap().as_mut() {
+		Some(warnings) => warnings.push(line),
+		None => println!("{line}"),
+	}
+}
+
+/// Stop holding warnings back, getting the ones that were
+fn take_warnings() -> Vec<String> {
+	WARNINGS.lock().unwrap().take().unwrap_or_default()
+}
+
+/// Print the warnings that were held back, and any after as they happen
+fn print_warnings() {
+	for line in take_warnings() {
+		println!("{line}");
+	}
+}
+
+/// Load whether startup is described with the banner or one JSON object. With the banner, the
+/// warnings so far are printed, since they're only held back for the JSON
+fn load_startup_output() {
+	match extract_value::<_, String>(env::vars_os(), STARTUP_OUTPUT_ENV_NAME).as_deref() {
+		Some("json") => JSON_STARTUP.store(true, Ordering::Relaxed),
+		Some("text") | None => {}
+		Some(output) => {
+			warning!(
+				"{STARTUP_OUTPUT_ENV_NAME} was ignored, because '{output}' isn't text or json"
+			);
+			warning!();
+		}
+	}
+	if !JSON_STARTUP.load(Ordering::Relaxed) {
+		print_warnings();
+	}
+}
+
+/// Build the runtime with the number of threads that were asked for. A single worker thread runs
+/// everything on the main thread instead
+fn load_runtime() -> std::io::Result<Runtime> {
+	let workers = extract_value::<_, usize>(env::vars_os(), WORKER_THREADS_ENV_NAME)
+		.filter(|workers| *workers > 0);
+	let blocking = extract_value::<_, usize>(env::vars_os(), MAX_BLOCKING_THREADS_ENV_NAME)
+		.filter(|blocking| *blocking > 0);
+
+	let mut builder = match workers {
+		Some(1) => {
+			banner!("Running on a single thread");
+			banner!();
+			runtime::Builder::new_current_thread()
+		}
+		Some(workers) => {
+			banner!("Running on {workers} worker threads");
+			banner!();
+			let mut builder = runtime::Builder::new_multi_thread();
+			builder.worker_threads(workers);
+			builder
+		}
+		None => runtime::Builder::new_multi_thread(),
+	};
+	if let Some(blocking) = blocking {
+		builder.max_blocking_threads(blocking);
+	}
+	builder.enable_all().build()
+}
+
+/// Load the local `.env` file, then the one for the environment set with `URSHORT_ENV` on top of
+/// it, like `.env.staging`. Each can include more files with `URSHORT_INCLUDE`, which are loaded
+/// right after it. Variables that were already set aren't changed. Returns the names of the files
+/// that were loaded
+fn load_env_files() -> Vec<String> {
+	let mut files = read_env_files(".env");
+	let environment = env::var(ENVIRONMENT_ENV_NAME).ok().or_else(|| {
+		let (_, file) = files.first()?;
+		file.get(ENVIRONMENT_ENV_NAME).cloned()
+	});
+	if let Some(environment) = environment {
+		files.extend(read_env_files(&format!(".env.{environment}")));
+	}
+
+	let (loaded, files): (Vec<String>, Vec<_>) = files.into_iter().unzip();
+	for (name, value) in merge_env_files(files, |name| env::var_os(name).is_some()) {
+		env::set_var(name, value);
+	}
+	loaded
+}
+
+/// Read a `.env` file and the files it includes, keyed by their names
+fn read_env_files(name: &str) -> Vec<(String, BTreeMap<String, String>)> {
+	let Some(path) = find_file(name) else {
+		// Not having a `.env` file is normal, but not having the one asked for is a mistake
+		if name != ".env" {
+			warning!("Could not find a '{name}' file for {ENVIRONMENT_ENV_NAME}");
+		}
+		return Vec::new();
+	};
+	let file = match read_env_file(&path) {
+		Ok(file) => file,
+		Err(error) => {
+			warning!("{error}");
+			return Vec::new();
+		}
+	};
+
+	let dir = path.parent().unwrap_or_else(|| std::path::Path::new("."));
+	let includes = file.get(INCLUDE_ENV_NAME).cloned().unwrap_or_default();
+	let mut files = vec![(name.to_string(), file)];
+	for pattern in includes.split(',').map(str::trim).filter(|x| !x.is_empty()) {
+		let paths = match find_included_files(pattern, dir) {
+			Ok(paths) => paths,
+			Err(error) => {
+				warning!("{INCLUDE_ENV_NAME} '{pattern}' was ignored, because {error}");
+				warning!();
+				continue;
+			}
+		};
+		for path in paths {
+			match read_env_file(&path) {
+				Ok(file) => {
+					let name = path.strip_prefix(dir).unwrap_or(&path).display();
+					files.push((name.to_string(), file));
+				}
+				Err(error) => warning!("{error}"),
+			}
+		}
+	}
+	files
+}
+
+/// Every target set in the environmental variables, keyed by the variable's name
+fn lint_targets() -> Vec<(String, String)> {
+	let mut targets = Vec::new();
+	for env_var_prefix in [
+		STANDARD_URI_ENV_NAME,
+		PRIVATE_URI_ENV_NAME,
+		PATTERN_URI_ENV_NAME,
+		CANARY_URI_ENV_NAME,
+		SWAP_URI_ENV_NAME,
+	] {
+		for (key, target) in extract_standard_values::<_, String>(env::vars_os(), env_var_prefix) {
+			targets.push((format!("{env_var_prefix}{key}"), target));
+		}
+	}
+	for (key, mirrors) in extract_standard_values::<_, String>(env::vars_os(), MIRROR_URI_ENV_NAME)
+	{
+		for target in mirrors
+			.split(',')
+			.filter(|target| !target.trim().is_empty())
+		{
+			targets.push((
+				format!("{MIRROR_URI_ENV_NAME}{key}"),
+				target.trim().to_string(),
+			));
+		}
+	}
+	for env_var_name in [FALLBACK_URI_ENV_NAME, ROOT_REDIRECT_ENV_NAME] {
+		if let Some(target) = extract_value::<_, String>(env::vars_os(), env_var_name) {
+			targets.push((env_var_name.to_string(), target));
+		}
+	}
+
+	targets.sort();
+	targets
+}
+
+/// Load how much each lint rule matters
+fn load_linter() -> Linter {
+	let mut linter = Linter::default();
+	if extract_value::<_, String>(env::vars_os(), BASE_URL_ENV_NAME).is_some() {
+		linter = linter.with_relative();
+	}
+	for rule in Rule::ALL {
+		let env_var_name = format!("{LINT_ENV_NAME}{}", rule.name());
+		let Some(severity) = extract_value::<_, String>(env::vars_os(), &env_var_name) else {
+			continue;
+		};
+		match severity.parse::<Severity>() {
+			Ok(severity) => linter = linter.with_severity(rule, severity),
+			Err(error) => warning!("{env_var_name} was ignored, because {error}"),
+		}
+	}
+	linter
+}
+
+/// Guard the patterns against ones that are too slow to match, if there's a budget for them
+fn load_pattern_guard(uri_mappings: UriMappings) -> UriMappings {
+	let Some(budget) = extract_value::<_, u64>(env::vars_os(), PATTERN_BUDGET_ENV_NAME) else {
+		return uri_mappings;
+	};
+	let strikes =
+		extract_value(env::vars_os(), PATTERN_STRIKES_ENV_NAME).unwrap_or(DEFAULT_PATTERN_STRIKES);
+	uri_mappings.with_pattern_guard(Duration::from_micros(budget), strikes)
+}
+
+/// Load all of the mappings from the environmental variables
+fn load_uri_mappings(targets: &TargetPolicy) -> UriMappings {
+	let standard_uris = extract_standard_uris(env::vars_os(), STANDARD_URI_ENV_NAME, targets);
 	let pattern_uris =
 		extract_pattern_uris(env::vars_os(), PATTERN_URI_ENV_NAME, PATTERN_REGEX_ENV_NAME);
-	let uri_mappings = Arc::new(UriMappings::new(standard_uris, pattern_uris));
+	let (standard_options, pattern_options) = load_link_options();
+	let uri_mappings = UriMappings::new(standard_uris, pattern_uris)
+		.with_options(standard_options, pattern_options)
+		.with_notes(
+			extract_standard_values(env::vars_os(), STANDARD_NOTE_ENV_NAME),
+			extract_pattern_values(env::vars_os(), PATTERN_NOTE_ENV_NAME),
+		);
 
-	let port: u16 = extract_port_number(env::vars_os(), PORT_ENV_NAME).unwrap_or(DEFAULT_PORT);
+	let private_uris = extract_standard_uris(env::vars_os(), PRIVATE_URI_ENV_NAME, targets);
+	if let Some(signer) = load_signer() {
+		return uri_mappings.with_private(private_uris, signer);
+	}
+
+	if !private_uris.is_empty() {
+		warning!("Private URIs were ignored, because no signing secret was set");
+		warning!();
+	}
+	uri_mappings
+}
+
+/// Build the chain of resolvers used to find the URI for a request
+fn load_resolver(
+	uri_mappings: Arc<UriMappings>,
+	targets: &TargetPolicy,
+	jobs: &Arc<Jobs>,
+) -> ResolverChain {
+	let mut resolver = ResolverChain::new();
+
+	// Canaries and swaps come first, so they can stand in for the mappings they're changing
+	if let Some(canary_resolver) = load_canary_resolver(&uri_mappings, targets) {
+		resolver = resolver.then(canary_resolver);
+	}
+
+	if let Some(swap_resolver) = load_swap_resolver(&uri_mappings, targets, jobs) {
+		resolver = resolver.then(swap_resolver);
+	}
+
+	// Translations come before the mappings they're for, which are used when there isn't one
+	if let Some(language_resolver) = load_language_resolver(&uri_mappings, targets) {
+		resolver = resolver.then(language_resolver);
+	}
+
+	resolver = resolver.then(uri_mappings);
+
+	if let Some(schedule_resolver) = load_schedule_resolver(targets) {
+		resolver = resolver.then(schedule_resolver);
+	}
+
+	if let Some(mirror_resolver) = load_mirror_resolver(targets, jobs) {
+		resolver = resolver.then(mirror_resolver);
+	}
+
+	if let Some(page_resolver) = load_page_resolver(targets) {
+		resolver = resolver.then(page_resolver);
+	}
+
+	if let Some(form_resolver) = load_form_resolver(targets) {
+		resolver = resolver.then(form_resolver);
+	}
+
+	#[cfg(feature = "scripting")]
+	if let Some(script_resolver) = load_script_resolver() {
+		resolver = resolver.then(script_resolver);
+	}
+
+	#[cfg(feature = "wasm")]
+	if let Some(path) =
+		extract_value::<_, std::path::PathBuf>(env::vars_os(), WASM_RESOLVER_ENV_NAME)
+	{
+		let fuel = extract_value(env::vars_os(), WASM_FUEL_ENV_NAME).unwrap_or(DEFAULT_WASM_FUEL);
+		match wasm_resolver::WasmResolver::new(&path, fuel) {
+			Ok(wasm_resolver) => {
+				banner!("Loaded WASM resolver {}", path.display());
+				banner!();
+				resolver = resolver.then(wasm_resolver);
+			}
+			Err(error) => {
+				warning!("WASM resolver was ignored, because {error}");
+				warning!();
+			}
+		}
+	}
+
+	if let Some(upstream) = extract_value::<_, reqwest::Url>(env::vars_os(), UPSTREAM_URI_ENV_NAME)
+	{
+		let cache_duration = Duration::from_secs(
+			extract_value(env::vars_os(), UPSTREAM_CACHE_DURATION_ENV_NAME)
+				.unwrap_or(DEFAULT_UPSTREAM_CACHE_DURATION),
+		);
+		match UpstreamResolver::new(upstream.clone(), cache_duration) {
+			Ok(upstream_resolver) => {
+				banner!("Unmatched paths will be looked up at {upstream}");
+				banner!();
+				resolver = resolver.then(upstream_resolver);
+			}
+			Err(error) => {
+				warning!("Upstream was ignored, because {error}");
+				warning!();
+			}
+		}
+	}
+
+	let Some(fallback) = extract_value::<_, String>(env::vars_os(), FALLBACK_URI_ENV_NAME) else {
+		return resolver;
+	};
+	match parse_target(targets, &fallback) {
+		Ok(fallback) => {
+			banner!("Unmatched paths will redirect to {fallback}");
+			banner!();
+			resolver.then(FallbackResolver::new(fallback))
+		}
+		Err(error) => {
+			warning!("{FALLBACK_URI_ENV_NAME} was ignored, because {error}");
+			warning!();
+			resolver
+		}
+	}
+}
+
+/// Load the staged targets for standard mappings, if there are any
+fn load_canary_resolver(
+	uri_mappings: &UriMappings,
+	targets: &TargetPolicy,
+) -> Option<CanaryResolver> {
+	let mut canaries = extract_standard_uris(env::vars_os(), CANARY_URI_ENV_NAME, targets);
+	canaries.retain(|key, _| {
+		let exists = uri_mappings.standard.contains_key(key);
+		if !exists {
+			warning!("{CANARY_URI_ENV_NAME}{key} was ignored, because there is no standard mapping for it");
+			warning!();
+		}
+		exists
+	});
+	if canaries.is_empty() {
+		return None;
+	}
+
+	banner!("Loaded Canary URIs:");
+	for (key, uri) in &canaries {
+		banner!("{key} {uri}");
+	}
+	banner!();
+	Some(CanaryResolver::new(
+		canaries,
+		uri_mappings.standard_options.clone(),
+	))
+}
+
+/// Load the targets of standard mappings in other languages, if there are any
+fn load_language_resolver(
+	uri_mappings: &UriMappings,
+	targets: &TargetPolicy,
+) -> Option<LanguageResolver> {
+	let mut translations = HashMap::new();
+	for (key, translated) in
+		extract_standard_values::<_, String>(env::vars_os(), LANGUAGE_URI_ENV_NAME)
+	{
+		if !uri_mappings.standard.contains_key(&key) {
+			warning!("{LANGUAGE_URI_ENV_NAME}{key} was ignored, because there is no standard mapping for it");
+			warning!();
+			continue;
+		}
+		match Translations::parse(&translated, |target| parse_target(targets, target)) {
+			Ok(translated) => {
+				translations.insert(key, translated);
+			}
+			Err(error) => {
+				warning!("{LANGUAGE_URI_ENV_NAME}{key} was ignored, because {error}");
+				warning!();
+			}
+		}
+	}
+	if translations.is_empty() {
+		return None;
+	}
+
+	banner!("Loaded Language URIs:");
+	for key in translations.keys() {
+		banner!("{key}");
+	}
+	banner!();
+	Some(LanguageResolver::new(
+		translations,
+		uri_mappings.standard_options.clone(),
+	))
+}
+
+/// Load the targets paths change to at a set time, and log each change when it happens
+fn load_swap_resolver(
+	uri_mappings: &UriMappings,
+	targets: &TargetPolicy,
+	jobs: &Arc<Jobs>,
+) -> Option<SwapResolver> {
+	let mut times = extract_standard_values::<_, String>(env::vars_os(), SWAP_AT_ENV_NAME);
+	let mut swaps = HashMap::new();
+	for (key, uri) in extract_standard_uris(env::vars_os(), SWAP_URI_ENV_NAME, targets) {
+		let at = times
+			.remove(&key)
+			.ok_or_else(|| format!("{SWAP_AT_ENV_NAME}{key} isn't set"))
+			.and_then(|at| clock::parse_timestamp(&at));
+		match at {
+			// Swapping a private mapping would make it public
+			Ok(_) if uri_mappings.private.contains_key(&key) => {
+				warning!("{SWAP_URI_ENV_NAME}{key} was ignored, because it's a private mapping");
+				warning!();
+			}
+			Ok(at) => {
+				swaps.insert(key, Swap { uri, at });
+			}
+			Err(error) => {
+				warning!("{SWAP_URI_ENV_NAME}{key} was ignored, because {error}");
+				warning!();
+			}
+		}
+	}
+	if swaps.is_empty() {
+		return None;
+	}
+
+	// Cached copies of the old redirect are purged once it's swapped, if there's a CDN
+	let purge = load_purger()
+		.zip(load_canonical_url(targets))
+		.map(|(purger, canonical)| (Arc::new(purger), canonical));
+
+	let now = clock::unix_now();
+	banner!("Loaded Swap URIs:");
+	for (key, swap) in &swaps {
+		let Swap { uri, at } = swap.clone();
+		if at <= now {
+			banner!("{key} {uri} (already swapped)");
+			continue;
+		}
+		banner!("{key} {uri} (in {} seconds)", at - now);
+
+		let key = key.clone();
+		let purge = purge.clone();
+		jobs.spawn(&format!("swap_{key}"), JobSchedule::Once(at), move || {
+			let (key, uri, purge) = (key.clone(), uri.clone(), purge.clone());
+			async move {
+				println!("Swapped {key} to {uri}");
+				let Some((purger, canonical)) = purge else {
+					return Ok(());
+				};
+				purger
+					.purge(&[format!("{canonical}/{key}")])
+					.await
+					.inspect_err(|error| println!("{error}"))
+			}
+		});
+	}
+	banner!();
+	Some(SwapResolver::new(
+		swaps,
+		uri_mappings.standard_options.clone(),
+	))
+}
+
+/// Load the schedule mappings, if there are any
+fn load_schedule_resolver(targets: &TargetPolicy) -> Option<ScheduleResolver> {
+	let mut schedules = HashMap::new();
+	for (key, schedule) in
+		extract_standard_values::<_, String>(env::vars_os(), SCHEDULE_URI_ENV_NAME)
+	{
+		match Schedule::parse(&schedule, |target| parse_target(targets, target)) {
+			Ok(schedule) => {
+				schedules.insert(key, schedule);
+			}
+			Err(error) => {
+				warning!("{SCHEDULE_URI_ENV_NAME}{key} was ignored, because {error}");
+				warning!();
+			}
+		}
+	}
+	if schedules.is_empty() {
+		return None;
+	}
+
+	banner!("Loaded Schedule URIs:");
+	for key in schedules.keys() {
+		banner!("{key}");
+	}
+	banner!();
+	Some(ScheduleResolver::new(schedules))
+}
+
+/// Load the mirror mappings and start checking their health, if there are any
+fn load_mirror_resolver(policy: &TargetPolicy, jobs: &Arc<Jobs>) -> Option<Arc<MirrorResolver>> {
+	let mut balances =
+		extract_standard_values::<_, String>(env::vars_os(), MIRROR_BALANCE_ENV_NAME);
+	let mut mirrors = HashMap::new();
+	for (key, targets) in extract_standard_values::<_, String>(env::vars_os(), MIRROR_URI_ENV_NAME)
+	{
+		let targets = targets
+			.split(',')
+			.filter(|target| !target.trim().is_empty())
+			.map(|target| parse_target(policy, target))
+			.collect::<Result<Vec<_>, _>>();
+		let balance = balances
+			.remove(&key)
+			.map_or(Ok(Balance::default()), |balance| balance.parse());
+
+		match (targets, balance) {
+			(Ok(targets), Ok(balance)) if !targets.is_empty() => {
+				mirrors.insert(key, Mirrors::new(targets, balance));
+			}
+			(Ok(_), Ok(_)) => {
+				warning!("{MIRROR_URI_ENV_NAME}{key} was ignored, because it has no targets");
+				warning!();
+			}
+			(Err(error), _) => {
+				warning!("{MIRROR_URI_ENV_NAME}{key} was ignored, because {error}");
+				warning!();
+			}
+			(_, Err(error)) => {
+				warning!("{MIRROR_BALANCE_ENV_NAME}{key} was ignored, because {error}");
+				warning!();
+			}
+		}
+	}
+	if mirrors.is_empty() {
+		return None;
+	}
+
+	banner!("Loaded Mirror URIs:");
+	for key in mirrors.keys() {
+		banner!("{key}");
+	}
+	banner!();
 
-	println!("Loaded Standard URIs:");
+	let mirror_resolver = match MirrorResolver::new(mirrors) {
+		Ok(mirror_resolver) => Arc::new(mirror_resolver),
+		Err(error) => {
+			warning!("Mirrors were ignored, because {error}");
+			warning!();
+			return None;
+		}
+	};
+
+	// Keep checking the targets, so requests only go to the ones that are up
+	let interval = Duration::from_secs(
+		extract_value(env::vars_os(), MIRROR_CHECK_INTERVAL_ENV_NAME)
+			.unwrap_or(DEFAULT_MIRROR_CHECK_INTERVAL),
+	);
+	let checked_resolver = mirror_resolver.clone();
+	jobs.spawn(
+		"mirror_health",
+		load_job_schedule("mirror_health", interval),
+		move || {
+			let checked_resolver = checked_resolver.clone();
+			async move {
+				checked_resolver.check_health().await;
+				Ok(())
+			}
+		},
+	);
+	Some(mirror_resolver)
+}
+
+/// Load the proxy for links that fetch their target, if any links do
+fn load_proxy(uri_mappings: &UriMappings, targets: &TargetPolicy) -> Option<Proxy> {
+	let proxied = uri_mappings
+		.standard_options
+		.values()
+		.chain(uri_mappings.pattern_options.values())
+		.any(|options| options.proxy);
+	if !proxied {
+		return None;
+	}
+
+	match Proxy::new(targets.clone()) {
+		Ok(proxy) => Some(proxy),
+		Err(error) => {
+			warning!("Proxied links will redirect instead, because {error}");
+			warning!();
+			None
+		}
+	}
+}
+
+/// Load the checks for where standard targets end up, if they should be flattened
+fn load_flattener(uri_mappings: &UriMappings, jobs: &Arc<Jobs>) -> Option<Arc<Flattener>> {
+	if !extract_value(env::vars_os(), FLATTEN_REDIRECTS_ENV_NAME).unwrap_or(false) {
+		return None;
+	}
+
+	let mut targets: Vec<Uri> = uri_mappings.standard.values().cloned().collect();
+	targets.sort_by_key(ToString::to_string);
+	targets.dedup();
+	let flattener = match Flattener::new(targets) {
+		Ok(flattener) => Arc::new(flattener),
+		Err(error) => {
+			warning!("{FLATTEN_REDIRECTS_ENV_NAME} was ignored, because {error}");
+			warning!();
+			return None;
+		}
+	};
+	banner!("Standard targets that redirect will be sent to where they end up");
+	banner!();
+
+	// Keep following the targets, as where they end up can change
+	let interval = Duration::from_secs(
+		extract_value(env::vars_os(), FLATTEN_INTERVAL_ENV_NAME)
+			.unwrap_or(DEFAULT_FLATTEN_INTERVAL),
+	);
+	let checked_flattener = flattener.clone();
+	jobs.spawn(
+		"flatten_redirects",
+		load_job_schedule("flatten_redirects", interval),
+		move || {
+			let checked_flattener = checked_flattener.clone();
+			async move {
+				checked_flattener.check().await;
+				Ok(())
+			}
+		},
+	);
+	Some(flattener)
+}
+
+/// Load the bundle, Markdown, and profile pages, if there are any
+fn load_page_resolver(targets: &TargetPolicy) -> Option<PageResolver> {
+	let mut pages = HashMap::new();
+
+	let mut titles = extract_standard_values::<_, String>(env::vars_os(), BUNDLE_TITLE_ENV_NAME);
+	for (key, bundle) in extract_standard_values::<_, String>(env::vars_os(), BUNDLE_URI_ENV_NAME) {
+		match Bundle::parse(&bundle, |target| parse_target(targets, target)) {
+			Ok(bundle) => {
+				let title = titles.remove(&key).unwrap_or_else(|| key.clone());
+				pages.insert(key, bundle.page(&title));
+			}
+			Err(error) => {
+				warning!("{BUNDLE_URI_ENV_NAME}{key} was ignored, because {error}");
+				warning!();
+			}
+		}
+	}
+
+	for (key, path) in
+		extract_standard_values::<_, std::path::PathBuf>(env::vars_os(), MARKDOWN_PAGE_ENV_NAME)
+	{
+		match std::fs::read_to_string(&path) {
+			Ok(markdown) => {
+				let page = markdown::render(&markdown, &key);
+				pages.insert(key, page);
+			}
+			Err(error) => {
+				warning!("{MARKDOWN_PAGE_ENV_NAME}{key} was ignored, because {error}");
+				warning!();
+			}
+		}
+	}
+
+	load_profiles(&mut pages, targets);
+
+	if pages.is_empty() {
+		return None;
+	}
+
+	banner!("Loaded Pages:");
+	for key in pages.keys() {
+		banner!("{key}");
+	}
+	banner!();
+	Some(PageResolver::new(pages))
+}
+
+/// Load the profile pages into the pages, each under its username
+fn load_profiles(pages: &mut HashMap<String, Page>, targets: &TargetPolicy) {
+	let mut names = extract_standard_values::<_, String>(env::vars_os(), PROFILE_NAME_ENV_NAME);
+	let mut bios = extract_standard_values::<_, String>(env::vars_os(), PROFILE_BIO_ENV_NAME);
+	let mut avatars = HashMap::new();
+	for (username, avatar) in
+		extract_standard_values::<_, String>(env::vars_os(), PROFILE_AVATAR_ENV_NAME)
+	{
+		match parse_target(targets, &avatar) {
+			Ok(avatar) => {
+				avatars.insert(username, avatar);
+			}
+			Err(error) => {
+				warning!("{PROFILE_AVATAR_ENV_NAME}{username} was ignored, because {error}");
+				warning!();
+			}
+		}
+	}
+	for (username, links) in
+		extract_standard_values::<_, String>(env::vars_os(), PROFILE_LINKS_ENV_NAME)
+	{
+		let links = match Bundle::parse(&links, |target| parse_target(targets, target)) {
+			Ok(links) => links,
+			Err(error) => {
+				warning!("{PROFILE_LINKS_ENV_NAME}{username} was ignored, because {error}");
+				warning!();
+				continue;
+			}
+		};
+		let profile = Profile {
+			name: names.remove(&username).unwrap_or_else(|| username.clone()),
+			bio: bios.remove(&username),
+			avatar: avatars.remove(&username),
+			links,
+		};
+		pages.insert(format!("{PROFILE_PREFIX}{username}"), profile.page());
+	}
+}
+
+/// Load the form mappings, if there are any
+fn load_form_resolver(targets: &TargetPolicy) -> Option<FormResolver> {
+	let mut forms = HashMap::new();
+	for (key, form) in extract_standard_values::<_, String>(env::vars_os(), FORM_URI_ENV_NAME) {
+		match form.parse::<Form>() {
+			Ok(form) => {
+				forms.insert(key, form);
+			}
+			Err(error) => {
+				warning!("{FORM_URI_ENV_NAME}{key} was ignored, because {error}");
+				warning!();
+			}
+		}
+	}
+	if forms.is_empty() {
+		return None;
+	}
+
+	banner!("Loaded Form URIs:");
+	for key in forms.keys() {
+		banner!("{key}");
+	}
+	banner!();
+	Some(FormResolver::new(forms, targets.clone()))
+}
+
+/// Load the script mappings, if there are any
+#[cfg(feature = "scripting")]
+fn load_script_resolver() -> Option<script_resolver::ScriptResolver> {
+	let mut regexes =
+		extract_pattern_values::<_, regex::Regex>(env::vars_os(), SCRIPT_REGEX_ENV_NAME);
+	let mut scripts = extract_pattern_values::<_, String>(env::vars_os(), SCRIPT_ENV_NAME);
+	let mut places: Vec<usize> = regexes.keys().copied().collect();
+	places.sort_unstable();
+
+	let mut loaded = Vec::new();
+	for place in places {
+		let regex = regexes.remove(&place)?;
+		match scripts.remove(&place) {
+			Some(script) => loaded.push((regex, script)),
+			None => {
+				warning!("{SCRIPT_REGEX_ENV_NAME}{place} was ignored, because it has no script");
+			}
+		}
+	}
+	if loaded.is_empty() {
+		return None;
+	}
+
+	let regexes: Vec<String> = loaded.iter().map(|(regex, _)| regex.to_string()).collect();
+	match script_resolver::ScriptResolver::new(loaded) {
+		Ok(script_resolver) => {
+			banner!("Loaded Script URIs:");
+			for regex in regexes {
+				banner!("{regex}");
+			}
+			banner!();
+			Some(script_resolver)
+		}
+		Err(error) => {
+			warning!("Scripts were ignored, because {error}");
+			warning!();
+			None
+		}
+	}
+}
+
+/// Look up the hosts of the most common targets in the background, if asked to
+fn start_warm_up(uri_mappings: &UriMappings) {
+	let Some(limit) = extract_value::<_, usize>(env::vars_os(), WARM_UP_HOSTS_ENV_NAME) else {
+		return;
+	};
+	let targets = uri_mappings
+		.standard
+		.values()
+		.chain(uri_mappings.private.values());
+	tokio::spawn(warm_up(popular_hosts(targets, limit)));
+}
+
+/// Print out all of the loaded mappings
+fn print_uri_mappings(uri_mappings: &UriMappings) {
+	banner!("Loaded Standard URIs:");
 	for (key, uri) in &uri_mappings.standard {
-		println!("{} {}", key, uri);
+		banner!("{key} {uri}");
 	}
-	println!();
+	banner!();
 
-	println!("Loaded Pattern URIs:");
+	banner!("Loaded Pattern URIs:");
 	for (key, uri) in &uri_mappings.pattern {
-		println!("{} {}", key, uri);
+		banner!("{key} {uri}");
 	}
-	println!();
+	banner!();
 
-	// Setup REST API
-	let app = Router::new()
-		// `GET /` for homepage
-		.route("/", get(index_page))
-		// `POST /:parameter` for vanity URL or error page if it fails
-		.route(
-			"/:parameter",
-			get(move |Path(parameter): Path<String>| {
-				get_match_and_redirect(parameter, uri_mappings.clone(), error_page)
-			}),
+	if !uri_mappings.private.is_empty() {
+		banner!("Loaded Private URIs:");
+		for key in uri_mappings.private.keys() {
+			banner!("{key}");
+		}
+		banner!();
+	}
+}
+
+/// Load the per-link options for both standard and pattern mappings from the environmental variables
+fn load_link_options() -> (HashMap<String, LinkOptions>, HashMap<usize, LinkOptions>) {
+	let mut standard_options: HashMap<String, LinkOptions> = HashMap::new();
+	for (key, delay) in extract_standard_values::<_, u64>(env::vars_os(), STANDARD_DELAY_ENV_NAME) {
+		standard_options.entry(key).or_default().delay = Some(Duration::from_millis(delay));
+	}
+	for (key, allowed) in
+		extract_standard_values::<_, String>(env::vars_os(), STANDARD_ALLOW_ENV_NAME)
+	{
+		let allowed = parse_allowed_ranges(&format!("{STANDARD_ALLOW_ENV_NAME}{key}"), &allowed);
+		standard_options.entry(key).or_default().allowed = Some(allowed);
+	}
+	for (key, passthrough) in
+		extract_standard_values::<_, bool>(env::vars_os(), STANDARD_PASSTHROUGH_ENV_NAME)
+	{
+		standard_options.entry(key).or_default().passthrough = passthrough;
+	}
+	for (key, proxy) in extract_standard_values::<_, bool>(env::vars_os(), STANDARD_PROXY_ENV_NAME)
+	{
+		standard_options.entry(key).or_default().proxy = proxy;
+	}
+	for (key, policy) in
+		extract_standard_values::<_, String>(env::vars_os(), STANDARD_REFERRER_ENV_NAME)
+	{
+		let policy = parse_referrer_policy(&format!("{STANDARD_REFERRER_ENV_NAME}{key}"), &policy);
+		standard_options.entry(key).or_default().referrer_policy = policy;
+	}
+	for (key, status) in extract_standard_values::<_, u16>(env::vars_os(), STANDARD_STATUS_ENV_NAME)
+	{
+		if let Some(status) =
+			parse_redirect_status(&format!("{STANDARD_STATUS_ENV_NAME}{key}"), status)
+		{
+			standard_options.entry(key).or_default().status = Some(status);
+		}
+	}
+
+	let mut pattern_options: HashMap<usize, LinkOptions> = HashMap::new();
+	for (place, delay) in extract_pattern_values::<_, u64>(env::vars_os(), PATTERN_DELAY_ENV_NAME) {
+		pattern_options.entry(place).or_default().delay = Some(Duration::from_millis(delay));
+	}
+	for (place, allowed) in
+		extract_pattern_values::<_, String>(env::vars_os(), PATTERN_ALLOW_ENV_NAME)
+	{
+		let allowed = parse_allowed_ranges(&format!("{PATTERN_ALLOW_ENV_NAME}{place}"), &allowed);
+		pattern_options.entry(place).or_default().allowed = Some(allowed);
+	}
+	for (place, passthrough) in
+		extract_pattern_values::<_, bool>(env::vars_os(), PATTERN_PASSTHROUGH_ENV_NAME)
+	{
+		pattern_options.entry(place).or_default().passthrough = passthrough;
+	}
+	for (place, proxy) in extract_pattern_values::<_, bool>(env::vars_os(), PATTERN_PROXY_ENV_NAME)
+	{
+		pattern_options.entry(place).or_default().proxy = proxy;
+	}
+	for (place, policy) in
+		extract_pattern_values::<_, String>(env::vars_os(), PATTERN_REFERRER_ENV_NAME)
+	{
+		let policy = parse_referrer_policy(&format!("{PATTERN_REFERRER_ENV_NAME}{place}"), &policy);
+		pattern_options.entry(place).or_default().referrer_policy = policy;
+	}
+	for (place, status) in extract_pattern_values::<_, u16>(env::vars_os(), PATTERN_STATUS_ENV_NAME)
+	{
+		if let Some(status) =
+			parse_redirect_status(&format!("{PATTERN_STATUS_ENV_NAME}{place}"), status)
+		{
+			pattern_options.entry(place).or_default().status = Some(status);
+		}
+	}
+
+	(standard_options, pattern_options)
+}
+
+/// Check the status a mapping redirects with is one that redirects
+fn parse_redirect_status(env_var_name: &str, status: u16) -> Option<StatusCode> {
+	match StatusCode::from_u16(status) {
+		Ok(status) if [301, 302, 303, 307, 308].contains(&status.as_u16()) => Some(status),
+		_ => {
+			warning!("{env_var_name} was ignored, because {status} is not a redirect status");
+			warning!();
+			None
+		}
+	}
+}
+
+/// Check the referrer policy of a mapping is one browsers know
+fn parse_referrer_policy(env_var_name: &str, policy: &str) -> Option<HeaderValue> {
+	const POLICIES: [&str; 8] = [
+		"no-referrer",
+		"no-referrer-when-downgrade",
+		"origin",
+		"origin-when-cross-origin",
+		"same-origin",
+		"strict-origin",
+		"strict-origin-when-cross-origin",
+		"unsafe-url",
+	];
+	let policy = policy.trim().to_ascii_lowercase();
+	if let Some(policy) = POLICIES.iter().find(|known| **known == policy) {
+		return Some(HeaderValue::from_static(policy));
+	}
+	warning!("{env_var_name} was ignored, because '{policy}' is not a referrer policy");
+	warning!();
+	None
+}
+
+/// Parse the IP ranges allowed to use a mapping. If they can't be parsed, nobody is allowed, so a
+/// typo can't make a restricted mapping public
+fn parse_allowed_ranges(env_var_name: &str, ranges: &str) -> IpRanges {
+	ranges.parse().unwrap_or_else(|error| {
+		warning!("{env_var_name} blocks everyone, because {error}");
+		warning!();
+		IpRanges::default()
+	})
+}
+
+/// Load the signer for private URIs, if a secret is set
+fn load_signer() -> Option<Signer> {
+	extract_value::<_, String>(env::vars_os(), SIGNING_SECRET_ENV_NAME)
+		.filter(|secret| !secret.is_empty())
+		.map(|secret| Signer::new(secret.as_bytes()))
+}
+
+/// Start enumeration protection, if it's enabled
+fn load_guard(jobs: &Arc<Jobs>) -> Option<Arc<EnumerationGuard>> {
+	let settings = load_guard_settings()?;
+	banner!(
+		"Enumeration protection enabled after {} misses",
+		settings.miss_limit
+	);
+	banner!();
+
+	// Periodically forget clients so the tracking doesn't grow forever
+	let window = settings.window;
+	let guard = Arc::new(EnumerationGuard::new(settings));
+	let pruned_guard = guard.clone();
+	jobs.spawn(
+		"guard_prune",
+		load_job_schedule("guard_prune", window),
+		move || {
+			pruned_guard.prune(Instant::now());
+			std::future::ready(Ok(()))
+		},
+	);
+	Some(guard)
+}
+
+/// Load which schemes links can redirect to, and what relative ones are resolved against
+fn load_target_policy() -> TargetPolicy {
+	let policy = match extract_value::<_, String>(env::vars_os(), ALLOWED_SCHEMES_ENV_NAME) {
+		Some(schemes) => TargetPolicy::new(schemes.split(',').map(ToString::to_string).collect()),
+		None => TargetPolicy::default(),
+	};
+
+	let Some(base) = extract_value::<_, String>(env::vars_os(), BASE_URL_ENV_NAME) else {
+		return policy;
+	};
+	match policy.clone().with_base(&base) {
+		Ok(policy) => {
+			banner!("Relative URIs will be resolved against {base}");
+			banner!();
+			policy
+		}
+		Err(error) => {
+			warning!("{BASE_URL_ENV_NAME} was ignored, because {error}");
+			warning!();
+			policy
+		}
+	}
+}
+
+/// Parse a configured target, after expanding the variables in it
+fn parse_target(targets: &TargetPolicy, target: &str) -> Result<Uri, String> {
+	targets.parse(&expand_variables(target, |name| env::var(name).ok())?)
+}
+
+/// Load how to answer requests under `/.well-known/`, if at all
+fn load_well_known(targets: &TargetPolicy) -> Option<WellKnown> {
+	let dir: Option<std::path::PathBuf> = extract_value(env::vars_os(), WELL_KNOWN_DIR_ENV_NAME);
+	if let Some(dir) = &dir {
+		banner!(
+			"Serving the files in '{}' under /.well-known/",
+			dir.display()
+		);
+		banner!();
+	}
+
+	let redirect =
+		extract_value::<_, String>(env::vars_os(), WELL_KNOWN_URI_ENV_NAME).and_then(|uri| {
+			match parse_target(targets, &uri) {
+				Ok(uri) => {
+					banner!("Requests under /.well-known/ will redirect to {uri}");
+					banner!();
+					Some(uri)
+				}
+				Err(error) => {
+					warning!("{WELL_KNOWN_URI_ENV_NAME} was ignored, because {error}");
+					warning!();
+					None
+				}
+			}
+		});
+
+	if dir.is_none() && redirect.is_none() {
+		return None;
+	}
+	Some(WellKnown { dir, redirect })
+}
+
+/// Load where to send requests for `/`, if anywhere
+fn load_root_redirect(targets: &TargetPolicy) -> Option<Uri> {
+	let uri: String = extract_value(env::vars_os(), ROOT_REDIRECT_ENV_NAME)?;
+	match parse_target(targets, &uri) {
+		Ok(uri) => {
+			banner!("The index page will redirect to {uri}");
+			banner!();
+			Some(uri)
+		}
+		Err(error) => {
+			warning!("{ROOT_REDIRECT_ENV_NAME} was ignored, because {error}");
+			warning!();
+			None
+		}
+	}
+}
+
+/// Load the host to use subdomains of as keys, if there is one
+fn load_subdomain_host() -> Option<String> {
+	let host: String = extract_value(env::vars_os(), SUBDOMAIN_HOST_ENV_NAME)?;
+	match parse_host(&host) {
+		Ok(host) => {
+			banner!("Subdomains of {host} are used as keys");
+			banner!();
+			Some(host)
+		}
+		Err(error) => {
+			warning!("{SUBDOMAIN_HOST_ENV_NAME} was ignored, because {error}");
+			warning!();
+			None
+		}
+	}
+}
+
+/// Load what purges cached redirects from the CDN, if there is one
+fn load_purger() -> Option<Purger> {
+	let cdn: String = extract_value(env::vars_os(), PURGE_CDN_ENV_NAME)?;
+	let purger = cdn.parse::<Cdn>().and_then(|cdn| {
+		let token: String = extract_value(env::vars_os(), PURGE_TOKEN_ENV_NAME)
+			.ok_or_else(|| format!("{PURGE_TOKEN_ENV_NAME} isn't set"))?;
+		Purger::new(cdn, token)
+	});
+	match purger {
+		Ok(purger) => Some(purger),
+		Err(error) => {
+			warning!("{PURGE_CDN_ENV_NAME} was ignored, because {error}");
+			warning!();
+			None
+		}
+	}
+}
+
+/// Load where to listen and bind to them, stopping if one can't be listened on
+fn bind_listeners() -> (Vec<Listener>, Vec<Bound>) {
+	let port: u16 = extract_port_number(env::vars_os(), PORT_ENV_NAME).unwrap_or(DEFAULT_PORT);
+	let default = || {
+		vec![Listener {
+			scheme: Scheme::Http,
+			address: Address::Tcp(SocketAddr::from(([0, 0, 0, 0], port))),
+		}]
+	};
+
+	let mut tls = None;
+	let listeners = match extract_value::<_, String>(env::vars_os(), LISTENERS_ENV_NAME) {
+		Some(value) => match parse_listeners(&value).and_then(|listeners| {
+			if listeners
+				.iter()
+				.any(|listener| listener.scheme == Scheme::Https)
+			{
+				tls = Some(load_tls()?);
+			}
+			Ok(listeners)
+		}) {
+			Ok(listeners) => listeners,
+			Err(error) => {
+				warning!("{LISTENERS_ENV_NAME} was ignored, because {error}");
+				warning!();
+				default()
+			}
+		},
+		None => default(),
+	};
+
+	match listeners::bind(&listeners, tls.as_ref(), load_limits()) {
+		Ok(bound) => (listeners, bound),
+		Err(error) => {
+			print_warnings();
+			eprintln!("{error}");
+			std::process::exit(1);
+		}
+	}
+}
+
+/// Load how much and how slowly clients can send requests
+fn load_limits() -> Limits {
+	let max_header_size = extract_value(env::vars_os(), MAX_HEADER_SIZE_ENV_NAME).filter(|size| {
+		let enough = *size >= MIN_HEADER_SIZE;
+		if !enough {
+			warning!(
+				"{MAX_HEADER_SIZE_ENV_NAME} was ignored, because it's less than {MIN_HEADER_SIZE} bytes"
+			);
+			warning!();
+		}
+		enough
+	});
+	let seconds = |name| extract_value(env::vars_os(), name).map(Duration::from_secs);
+	Limits {
+		max_header_size,
+		header_read_timeout: seconds(HEADER_READ_TIMEOUT_ENV_NAME),
+		body_read_timeout: seconds(BODY_READ_TIMEOUT_ENV_NAME),
+	}
+}
+
+/// Load the certificate and key HTTPS listeners are served with
+fn load_tls() -> Result<Arc<ServerConfig>, String> {
+	let certificate: Option<String> = extract_value(env::vars_os(), TLS_CERT_ENV_NAME);
+	let key: Option<String> = extract_value(env::vars_os(), TLS_KEY_ENV_NAME);
+	match (certificate, key) {
+		(Some(certificate), Some(key)) => load_tls_config(&certificate, &key),
+		_ => Err(format!(
+			"HTTPS needs {TLS_CERT_ENV_NAME} and {TLS_KEY_ENV_NAME}"
+		)),
+	}
+}
+
+/// Load how long to wait for requests in flight to finish when shutting down
+fn load_drain_timeout() -> Duration {
+	Duration::from_secs(
+		extract_value(env::vars_os(), DRAIN_TIMEOUT_ENV_NAME).unwrap_or(DEFAULT_DRAIN_TIMEOUT),
+	)
+}
+
+/// Load the latency and errors to inject into requests, if any were asked for
+fn load_chaos() -> Option<Arc<Chaos>> {
+	let latency: Option<u64> = extract_value(env::vars_os(), CHAOS_LATENCY_ENV_NAME);
+	let error_percent: Option<u64> = extract_value(env::vars_os(), CHAOS_ERROR_RATE_ENV_NAME);
+	if latency.is_none() && error_percent.is_none() {
+		return None;
+	}
+
+	let error_status = extract_value::<_, u16>(env::vars_os(), CHAOS_ERROR_STATUS_ENV_NAME)
+		.and_then(|status| StatusCode::from_u16(status).ok())
+		.unwrap_or(StatusCode::SERVICE_UNAVAILABLE);
+	match Chaos::new(
+		latency.map(Duration::from_millis),
+		error_percent.unwrap_or(0),
+		error_status,
+	) {
+		Ok(chaos) => {
+			banner!(
+				"Chaos is on: links are delayed {}ms and {}% of them fail with {error_status}",
+				latency.unwrap_or(0),
+				error_percent.unwrap_or(0)
+			);
+			banner!();
+			Some(Arc::new(chaos))
+		}
+		Err(error) => {
+			warning!("{CHAOS_ERROR_RATE_ENV_NAME} was ignored, because {error}");
+			warning!();
+			None
+		}
+	}
+}
+
+/// Load the bouncer that asks `CrowdSec` about clients, if there's an API for it
+fn load_bouncer() -> Option<Bouncer> {
+	let url: String = extract_value(env::vars_os(), CROWDSEC_URL_ENV_NAME)?;
+	let Some(api_key) = extract_value::<_, String>(env::vars_os(), CROWDSEC_API_KEY_ENV_NAME)
+	else {
+		warning!(
+			"{CROWDSEC_URL_ENV_NAME} was ignored, because {CROWDSEC_API_KEY_ENV_NAME} isn't set"
 		);
+		warning!();
+		return None;
+	};
+	let tarpit = extract_value(env::vars_os(), CROWDSEC_TARPIT_ENV_NAME).map(Duration::from_millis);
+	let cache_for = Duration::from_secs(
+		extract_value(env::vars_os(), CROWDSEC_CACHE_ENV_NAME).unwrap_or(DEFAULT_CROWDSEC_CACHE),
+	);
+	match Bouncer::new(&url, api_key, tarpit, cache_for) {
+		Ok(bouncer) => {
+			banner!("Asking CrowdSec at {url} which clients to turn away");
+			banner!();
+			Some(bouncer)
+		}
+		Err(error) => {
+			warning!("{CROWDSEC_URL_ENV_NAME} was ignored, because {error}");
+			warning!();
+			None
+		}
+	}
+}
+
+/// Load the log redirects are recorded to, if there's a directory for it
+fn load_event_log() -> Option<EventLog> {
+	let dir: String = extract_value(env::vars_os(), EVENT_LOG_DIR_ENV_NAME)?;
+	let segment_size = extract_value(env::vars_os(), EVENT_LOG_SEGMENT_SIZE_ENV_NAME)
+		.unwrap_or(DEFAULT_EVENT_LOG_SEGMENT_SIZE);
+	let keep = extract_value(env::vars_os(), EVENT_LOG_SEGMENTS_ENV_NAME)
+		.unwrap_or(DEFAULT_EVENT_LOG_SEGMENTS);
+	let sample_rate = extract_value(env::vars_os(), EVENT_LOG_SAMPLE_ENV_NAME).unwrap_or(1);
+	match EventLog::start(dir.clone().into(), segment_size, keep, sample_rate) {
+		Ok(events) => {
+			banner!("Logging redirects to {dir}");
+			banner!();
+			Some(events)
+		}
+		Err(error) => {
+			warning!("{EVENT_LOG_DIR_ENV_NAME} was ignored, because {error}");
+			warning!();
+			None
+		}
+	}
+}
+
+/// Load the URL links are shared with, when the instance answers on more than one host
+fn load_canonical_url(targets: &TargetPolicy) -> Option<String> {
+	let url: String = extract_value(env::vars_os(), CANONICAL_URL_ENV_NAME)?;
+	match parse_target(targets, &url) {
+		Ok(url) => Some(url.to_string().trim_end_matches('/').to_string()),
+		Err(error) => {
+			warning!("{CANONICAL_URL_ENV_NAME} was ignored, because {error}");
+			warning!();
+			None
+		}
+	}
+}
+
+/// Load the URL of the instance for commands to talk to, which is the canonical URL or otherwise
+/// the port on this machine
+fn load_instance_url() -> String {
+	load_canonical_url(&load_target_policy()).unwrap_or_else(|| {
+		let port: u16 = extract_port_number(env::vars_os(), PORT_ENV_NAME).unwrap_or(DEFAULT_PORT);
+		format!("http://localhost:{port}")
+	})
+}
+
+/// Load the candidate mappings to shadow the live ones with, if there are any
+fn load_shadow(targets: &TargetPolicy) -> Option<Shadow> {
+	let path = extract_value::<_, std::path::PathBuf>(env::vars_os(), SHADOW_FILE_ENV_NAME)?;
+	let file: Vec<(OsString, OsString)> = match read_env_file(&path) {
+		Ok(file) => file
+			.into_iter()
+			.map(|(name, value)| (name.into(), value.into()))
+			.collect(),
+		Err(error) => {
+			warning!("{error}, so there are no shadow mappings");
+			warning!();
+			return None;
+		}
+	};
+
+	let candidate = UriMappings::new(
+		extract_standard_uris(file.clone(), STANDARD_URI_ENV_NAME, targets),
+		extract_pattern_uris(file, PATTERN_URI_ENV_NAME, PATTERN_REGEX_ENV_NAME),
+	);
+	banner!(
+		"Shadowing with {} standard and {} pattern URIs from {}",
+		candidate.standard.len(),
+		candidate.pattern.len(),
+		path.display()
+	);
+	banner!();
+	Some(Shadow::new(candidate))
+}
+
+/// Render the `Location` headers for the standard targets
+fn render_locations(uri_mappings: &UriMappings) -> HashMap<Uri, HeaderValue> {
+	uri_mappings
+		.standard
+		.values()
+		.filter_map(|uri| Some((uri.clone(), HeaderValue::try_from(uri.to_string()).ok()?)))
+		.collect()
+}
+
+/// Load the metrics, which are only enabled if asked for
+#[cfg(feature = "metrics")]
+fn load_metrics() -> Option<Arc<Metrics>> {
+	if !extract_value(env::vars_os(), METRICS_ENV_NAME).unwrap_or(false) {
+		return None;
+	}
+
+	let buckets = match extract_value::<_, String>(env::vars_os(), METRICS_BUCKETS_ENV_NAME) {
+		Some(buckets) => parse_buckets(&buckets).unwrap_or_else(|error| {
+			warning!("{METRICS_BUCKETS_ENV_NAME} was ignored, because {error}");
+			warning!();
+			DEFAULT_BUCKETS.to_vec()
+		}),
+		None => DEFAULT_BUCKETS.to_vec(),
+	};
+	Some(Arc::new(Metrics::new(buckets)))
+}
+
+/// Load when a background job runs, using the interval unless a cron expression is set for it
+fn load_job_schedule(name: &str, interval: Duration) -> JobSchedule {
+	let env_var_name = format!("{JOB_CRON_ENV_NAME}{name}");
+	let Some(cron) = extract_value::<_, String>(env::vars_os(), &env_var_name) else {
+		return JobSchedule::Every(interval);
+	};
+	match cron.parse::<Cron>() {
+		Ok(cron) => JobSchedule::Cron(cron),
+		Err(error) => {
+			warning!("{env_var_name} was ignored, because {error}");
+			warning!();
+			JobSchedule::Every(interval)
+		}
+	}
+}
+
+/// Load the enumeration protection settings, which are only enabled if a miss limit is set
+fn load_guard_settings() -> Option<GuardSettings> {
+	let miss_limit: u32 = extract_value(env::vars_os(), MISS_LIMIT_ENV_NAME)?;
+
+	// The ban has to come after at least one delayed miss, or the delay would never be used
+	let least_ban_limit = miss_limit.saturating_add(2);
+	let default_ban_limit = miss_limit.saturating_mul(2).max(least_ban_limit);
+	let ban_limit = match extract_value(env::vars_os(), MISS_BAN_LIMIT_ENV_NAME) {
+		Some(ban_limit) if ban_limit >= least_ban_limit => ban_limit,
+		Some(_) => {
+			warning!("{MISS_BAN_LIMIT_ENV_NAME} was ignored, because it must be at least {least_ban_limit}, two more than {MISS_LIMIT_ENV_NAME}");
+			warning!();
+			default_ban_limit
+		}
+		None => default_ban_limit,
+	};
+
+	Some(GuardSettings {
+		miss_limit,
+		window: Duration::from_secs(
+			extract_value(env::vars_os(), MISS_WINDOW_ENV_NAME).unwrap_or(DEFAULT_MISS_WINDOW),
+		),
+		delay: Duration::from_millis(
+			extract_value(env::vars_os(), MISS_DELAY_ENV_NAME).unwrap_or(DEFAULT_MISS_DELAY),
+		),
+		ban_limit,
+		ban: Duration::from_secs(
+			extract_value(env::vars_os(), MISS_BAN_DURATION_ENV_NAME)
+				.unwrap_or(DEFAULT_MISS_BAN_DURATION),
+		),
+	})
+}
+
+/// Load how the built in pages look and read
+fn load_pages() -> Pages {
+	Pages {
+		theme: load_theme(),
+		strings: load_strings(),
+		locales: load_locales(),
+		assets: StaticAssets::default(),
+	}
+}
+
+/// Load the theme for the built in pages, using the default look for anything not set
+fn load_theme() -> Theme {
+	let mut theme = Theme::default();
+	for (env_var_name, color) in [
+		(THEME_TEXT_ENV_NAME, &mut theme.text),
+		(THEME_BACKGROUND_ENV_NAME, &mut theme.background),
+		(THEME_ACCENT_ENV_NAME, &mut theme.accent),
+		(THEME_DARK_TEXT_ENV_NAME, &mut theme.dark_text),
+		(THEME_DARK_BACKGROUND_ENV_NAME, &mut theme.dark_background),
+		(THEME_DARK_ACCENT_ENV_NAME, &mut theme.dark_accent),
+	] {
+		if let Some(value) = extract_value::<_, String>(env::vars_os(), env_var_name) {
+			match parse_color(&value) {
+				Ok(value) => *color = value,
+				Err(error) => {
+					warning!("{env_var_name} was ignored, because {error}");
+					warning!();
+				}
+			}
+		}
+	}
+	theme.logo = extract_value(env::vars_os(), THEME_LOGO_ENV_NAME);
+	theme.footer = extract_value(env::vars_os(), THEME_FOOTER_ENV_NAME);
+	theme
+}
+
+/// Load the words used on the built in pages, using the default for anything not set
+fn load_strings() -> Strings {
+	let mut strings = Strings::default();
+	for (env_var_name, string) in [
+		(LANGUAGE_ENV_NAME, &mut strings.language),
+		(INDEX_TITLE_ENV_NAME, &mut strings.index_title),
+		(INDEX_HEADING_ENV_NAME, &mut strings.index_heading),
+		(INDEX_MESSAGE_ENV_NAME, &mut strings.index_message),
+		(ERROR_TITLE_ENV_NAME, &mut strings.error_title),
+		(ERROR_HEADING_ENV_NAME, &mut strings.error_heading),
+		(ERROR_MESSAGE_ENV_NAME, &mut strings.error_message),
+	] {
+		if let Some(value) = extract_value(env::vars_os(), env_var_name) {
+			*string = value;
+		}
+	}
+	strings
+}
 
-	let address = SocketAddr::from(([0, 0, 0, 0], port));
-	println!("Listening on http://{}", address);
+/// Load the translated pages, if there is a directory for them
+fn load_locales() -> Locales {
+	let Some(dir) = extract_value::<_, std::path::PathBuf>(env::vars_os(), LOCALE_DIR_ENV_NAME)
+	else {
+		return Locales::default();
+	};
 
-	axum::Server::bind(&address)
-		.serve(app.into_make_service())
-		.await
-		.unwrap();
+	match Locales::load(&dir) {
+		Ok(locales) => {
+			let mut languages: Vec<&String> = locales.languages().collect();
+			languages.sort();
+			banner!("Loaded Locales:");
+			for language in languages {
+				banner!("{language}");
+			}
+			banner!();
+			locales
+		}
+		Err(error) => {
+			warning!("{LOCALE_DIR_ENV_NAME} was ignored, because {error}");
+			warning!();
+			Locales::default()
+		}
+	}
 }
 
-/// Load the index.html page at compile time
-async fn index_page() -> Html<&'static str> {
-	Html(std::include_str!("../assets/index.html"))
+/// Show the index page, with the maintenance notice if there is one. Unless the request was
+/// made to a subdomain, then it's the link for it, or the root redirect is set
+async fn index_page(
+	query: HashMap<String, String>,
+	headers: HeaderMap,
+	client: IpAddr,
+	maintenance: Arc<Maintenance>,
+	links: Arc<Links>,
+) -> Response {
+	let key = links
+		.subdomain_host
+		.as_deref()
+		.and_then(|host| subdomain_key(&headers, host));
+	if let Some(key) = key {
+		return get_match_and_redirect(
+			Method::GET,
+			key,
+			query,
+			headers,
+			Body::empty(),
+			client,
+			links,
+		)
+		.await;
+	}
+
+	if let Some(uri) = &links.root_redirect {
+		return (StatusCode::FOUND, [(header::LOCATION, uri.to_string())]).into_response();
+	}
+
+	links
+		.pages
+		.index(&headers, maintenance.notice().as_deref())
+		.into_response()
+}
+
+/// Everything needed to handle a request for a link
+struct Links {
+	resolver: Arc<ResolverChain>,
+	guard: Option<Arc<EnumerationGuard>>,
+	pages: Arc<Pages>,
+	#[cfg(feature = "metrics")]
+	metrics: Option<Arc<Metrics>>,
+	/// The host that subdomains are used as keys under
+	subdomain_host: Option<String>,
+	/// Where to send requests for `/` instead of showing the index page
+	root_redirect: Option<Uri>,
+	/// The rendered `Location` headers for the standard targets, so they don't need to be
+	/// rendered for every request. Mappings can't change while running, so they never go stale
+	locations: HashMap<Uri, HeaderValue>,
+	/// Candidate mappings that requests are also resolved against, to compare with the live ones
+	shadow: Option<Shadow>,
+	/// Which targets can be redirected to
+	targets: TargetPolicy,
+	/// Where standard targets that redirect end up, if they're being flattened
+	flattener: Option<Arc<Flattener>>,
+	/// Fetches the targets of links that are proxied, if there are any
+	proxy: Option<Proxy>,
+	/// Where redirects are logged for analytics, if they are
+	events: Option<EventLog>,
+	/// Asks `CrowdSec` which clients to turn away, if it's set up
+	bouncer: Option<Bouncer>,
+	/// How long each link takes and how often it errors, if the admin API can report it
+	#[cfg(feature = "admin")]
+	slo: Option<Arc<Slo>>,
+}
+
+impl Links {
+	/// The caches that fill up while running, for the admin API to report on
+	#[cfg(feature = "admin")]
+	fn caches(&self, slo: Arc<Slo>) -> Caches {
+		Caches {
+			locations: self.locations.len(),
+			guard: self.guard.clone(),
+			flattener: self.flattener.clone(),
+			slo,
+		}
+	}
+
+	/// Answer requests whose handler panicked with the error page, instead of dropping the
+	/// connection. The panic itself is logged by the panic hook
+	fn catch_panics(
+		&self,
+	) -> CatchPanicLayer<impl Fn(Box<dyn Any + Send>) -> Response + Clone + Send + Sync> {
+		let pages = self.pages.clone();
+		#[cfg(feature = "metrics")]
+		let metrics = self.metrics.clone();
+		CatchPanicLayer::custom(move |error: Box<dyn Any + Send>| {
+			println!(
+				"Answered a panicked request with the error page, because {}",
+				panics::panic_message(error.as_ref())
+			);
+			println!();
+			#[cfg(feature = "metrics")]
+			if let Some(metrics) = &metrics {
+				metrics.record_panic();
+			}
+			(
+				StatusCode::INTERNAL_SERVER_ERROR,
+				pages.error(&HeaderMap::new()),
+			)
+				.into_response()
+		})
+	}
+
+	/// Turn the client away before resolving anything, if it's banned for looking for links or
+	/// `CrowdSec` flagged it
+	async fn turn_away(
+		&self,
+		client: IpAddr,
+		headers: &HeaderMap,
+		start: Instant,
+	) -> Option<Response> {
+		let status = if self
+			.guard
+			.as_ref()
+			.is_some_and(|guard| guard.is_banned(client, start))
+		{
+			StatusCode::TOO_MANY_REQUESTS
+		} else {
+			match &self.bouncer {
+				Some(bouncer) if bouncer.is_flagged(client).await => StatusCode::FORBIDDEN,
+				_ => return None,
+			}
+		};
+		#[cfg(feature = "metrics")]
+		self.record(Outcome::Banned, None, start.elapsed());
+		Some((status, self.pages.error(headers)).into_response())
+	}
+
+	/// Count the request in the metrics, if they're enabled
+	#[cfg(feature = "metrics")]
+	fn record(&self, outcome: Outcome, link: Option<&str>, duration: Duration) {
+		if let Some(metrics) = &self.metrics {
+			metrics.record(outcome, link, duration);
+		}
+	}
+
+	/// Track how the link did, if the admin API can report it. Links that were found but
+	/// couldn't be answered, like a proxied target that's down, count as errors
+	#[cfg(feature = "admin")]
+	fn record_slo(&self, path: &str, latency: Duration, response: &Response) {
+		if let Some(slo) = &self.slo {
+			let status = response.status();
+			slo.record(
+				path,
+				latency,
+				status.is_client_error() || status.is_server_error(),
+			);
+		}
+	}
+
+	/// Send the request on to where the link goes, logging it if there's an event log
+	async fn send(
+		&self,
+		path: &str,
+		location: HeaderValue,
+		options: &LinkOptions,
+		method: Method,
+		headers: &HeaderMap,
+		body: Body,
+	) -> Response {
+		if let Some(events) = &self.events {
+			events.record(path, location.to_str().unwrap_or_default());
+		}
+		match (&self.proxy, location.to_str()) {
+			(Some(proxy), Ok(target)) if options.proxy => {
+				proxy.forward(target, method, headers, body).await
+			}
+			_ => redirect(options, location),
+		}
+	}
+
+	/// Render the `Location` header for the target, if it can be redirected to
+	fn location(&self, uri: &Uri) -> Result<HeaderValue, String> {
+		// Targets known to redirect are sent to where they end up, saving a hop
+		if let Some(destination) = self
+			.flattener
+			.as_ref()
+			.and_then(|flattener| flattener.get(uri))
+		{
+			if let Ok(location) = self.targets.resolve(&destination).and_then(|destination| {
+				HeaderValue::try_from(destination.to_string()).map_err(|error| error.to_string())
+			}) {
+				return Ok(location);
+			}
+		}
+
+		// Standard targets were checked and rendered when they were loaded, but ones built while
+		// resolving, like with a pattern, haven't been yet
+		match self.locations.get(uri) {
+			Some(location) => Ok(location.clone()),
+			None => self.targets.resolve(uri).and_then(|uri| {
+				HeaderValue::try_from(uri.to_string()).map_err(|error| error.to_string())
+			}),
+		}
+	}
 }
 
-/// Load the error.html page at compile time
-async fn error_page() -> Html<&'static str> {
-	Html(std::include_str!("../assets/error.html"))
+/// Redirect to the location, with the status and referrer policy of the mapping
+fn redirect(options: &LinkOptions, location: HeaderValue) -> Response {
+	let mut response = (options.redirect_status(), [(header::LOCATION, location)]).into_response();
+	if let Some(policy) = &options.referrer_policy {
+		response
+			.headers_mut()
+			.insert(header::REFERRER_POLICY, policy.clone());
+	}
+	response
 }
 
 /// Attempts to get a match and redirect if one is found
-async fn get_match_and_redirect<F, Fut>(
-	path: String,
-	uri_mappings: Arc<UriMappings>,
-	error_page: F,
-) -> Result<axum::response::Redirect, Html<&'static str>>
-where
-	F: Fn() -> Fut,
-	Fut: Future<Output = Html<&'static str>>,
-{
-	match uri_mappings.match_anything(&path) {
-		Ok(x) => Ok(Redirect::temporary(x.to_string().as_str())),
-		Err(_) => Err(error_page().await),
+async fn get_match_and_redirect(
+	method: Method,
+	mut path: String,
+	query: HashMap<String, String>,
+	headers: HeaderMap,
+	body: Body,
+	client: IpAddr,
+	links: Arc<Links>,
+) -> Response {
+	if let Some(normalized) = normalize_key(&path) {
+		path = normalized;
+	}
+	let Links {
+		resolver,
+		guard,
+		pages,
+		shadow,
+		..
+	} = links.as_ref();
+
+	let start = Instant::now();
+	if let Some(response) = links.turn_away(client, &headers, start).await {
+		return response;
+	}
+
+	let request = ResolveRequest {
+		path: Cow::Borrowed(&path),
+		query: &query,
+		headers: &headers,
+		client,
+	};
+	let resolution = resolver.resolve(&request).await;
+	let live = resolution.as_ref().map(|resolution| &resolution.target);
+	if let Some(difference) = shadow
+		.as_ref()
+		.and_then(|shadow| shadow.compare(&path, live))
+	{
+		SHADOW_LOG.print(&path, || format!("Shadow mappings differ: {difference}"));
+		#[cfg(feature = "metrics")]
+		if let Some(metrics) = &links.metrics {
+			metrics.record_shadow_difference();
+		}
+	}
+
+	// Only links that pass the method through answer anything but reading them
+	let reading = method == Method::GET || method == Method::HEAD;
+	if !reading
+		&& !matches!(&resolution, Some(Resolution { target: Target::Redirect(_), options }) if options.passthrough)
+	{
+		return StatusCode::METHOD_NOT_ALLOWED.into_response();
+	}
+
+	if let Some(Resolution { target, options }) = resolution {
+		#[cfg(feature = "metrics")]
+		let duration = start.elapsed();
+		// Slow down links that have been marked to make enumerating them tedious
+		if let Some(delay) = options.delay {
+			tokio::time::sleep(delay).await;
+		}
+		let response = match target {
+			Target::Redirect(uri) => match links.location(&uri) {
+				Ok(location) => {
+					#[cfg(feature = "metrics")]
+					links.record(Outcome::Redirect, Some(&path), duration);
+					links
+						.send(&path, location, &options, method, &headers, body)
+						.await
+				}
+				Err(error) => {
+					BLOCKED_LOG.print(&path, || {
+						format!("Redirect from /{path} to {uri} was blocked, because {error}")
+					});
+					#[cfg(feature = "metrics")]
+					links.record(Outcome::Miss, None, duration);
+					pages.error(&headers).into_response()
+				}
+			},
+			Target::Page(page) => {
+				#[cfg(feature = "metrics")]
+				links.record(Outcome::Page, Some(&path), duration);
+				pages.render(&page).into_response()
+			}
+		};
+		#[cfg(feature = "admin")]
+		links.record_slo(
+			&path,
+			start
+				.elapsed()
+				.saturating_sub(options.delay.unwrap_or_default()),
+			&response,
+		);
+		return response;
+	}
+	#[cfg(feature = "metrics")]
+	links.record(Outcome::Miss, None, start.elapsed());
+
+	let penalty = guard.as_ref().map_or(Penalty::None, |guard| {
+		guard.record_miss(client, Instant::now())
+	});
+	match penalty {
+		Penalty::None => pages.error(&headers).into_response(),
+		Penalty::Delay(delay) => {
+			tokio::time::sleep(delay).await;
+			pages.error(&headers).into_response()
+		}
+		Penalty::Banned => (StatusCode::TOO_MANY_REQUESTS, pages.error(&headers)).into_response(),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn json_startup_holds_warnings() {
+		env::set_var(STARTUP_OUTPUT_ENV_NAME, "json");
+		env::set_var(MISS_LIMIT_ENV_NAME, "3");
+		env::set_var(MISS_BAN_LIMIT_ENV_NAME, "1");
+		load_startup_output();
+		let settings = load_guard_settings();
+		env::remove_var(MISS_BAN_LIMIT_ENV_NAME);
+		env::remove_var(MISS_LIMIT_ENV_NAME);
+		env::remove_var(STARTUP_OUTPUT_ENV_NAME);
+		assert_eq!(settings.map(|settings| settings.ban_limit), Some(6));
+
+		let startup = Startup {
+			listeners: Vec::new(),
+			mappings: StartupMappings {
+				standard: 0,
+				pattern: 0,
+				private: 0,
+			},
+			subsystems: vec!["enumeration_guard"],
+			warnings: Vec::new(),
+		};
+		let json = startup.describe(&[]);
+		assert!(!json.contains('\n'));
+		let described: serde_json::Value = serde_json::from_str(&json).unwrap();
+		assert_eq!(
+			described["warnings"],
+			serde_json::json!([format!(
+				"{MISS_BAN_LIMIT_ENV_NAME} was ignored, because it must be at least 5, two more than {MISS_LIMIT_ENV_NAME}"
+			)])
+		);
+		// Once described, warnings are printed as they happen
+		assert!(WARNINGS.lock().unwrap().is_none());
 	}
 }
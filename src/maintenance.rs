@@ -0,0 +1,57 @@
+use std::sync::RwLock;
+
+/// Tracks if the instance is in maintenance mode and the notice to show while it is. It can only
+/// be turned on through the admin API
+#[cfg_attr(not(feature = "admin"), allow(dead_code))]
+pub struct Maintenance {
+	notice: RwLock<Option<String>>,
+	default_notice: String,
+}
+
+impl Maintenance {
+	/// Create a new `Maintenance` that is turned off
+	pub fn new(default_notice: String) -> Maintenance {
+		Maintenance {
+			notice: RwLock::new(None),
+			default_notice,
+		}
+	}
+
+	/// Get the notice to show, if maintenance mode is on
+	pub fn notice(&self) -> Option<String> {
+		self.notice.read().unwrap().clone()
+	}
+
+	/// Turn maintenance mode on with the notice, or the default notice if none is given
+	#[cfg_attr(not(feature = "admin"), allow(dead_code))]
+	pub fn enable(&self, notice: Option<String>) {
+		let notice = notice.unwrap_or_else(|| self.default_notice.clone());
+		*self.notice.write().unwrap() = Some(notice);
+	}
+
+	/// Turn maintenance mode off
+	#[cfg_attr(not(feature = "admin"), allow(dead_code))]
+	pub fn disable(&self) {
+		*self.notice.write().unwrap() = None;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn toggle_maintenance() {
+		let maintenance = Maintenance::new("Back soon".to_string());
+		assert_eq!(maintenance.notice(), None);
+
+		maintenance.enable(None);
+		assert_eq!(maintenance.notice(), Some("Back soon".to_string()));
+
+		maintenance.enable(Some("Upgrading".to_string()));
+		assert_eq!(maintenance.notice(), Some("Upgrading".to_string()));
+
+		maintenance.disable();
+		assert_eq!(maintenance.notice(), None);
+	}
+}
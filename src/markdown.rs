@@ -0,0 +1,52 @@
+use pulldown_cmark::{html, Event, Options, Parser, Tag, TagEnd};
+
+use crate::pages::Page;
+
+/// Render a Markdown document as a page, titled by its first heading or the fallback title
+pub fn render(markdown: &str, fallback_title: &str) -> Page {
+	let options = Options::ENABLE_TABLES
+		| Options::ENABLE_STRIKETHROUGH
+		| Options::ENABLE_TASKLISTS
+		| Options::ENABLE_FOOTNOTES;
+
+	let mut content = String::new();
+	html::push_html(&mut content, Parser::new_ext(markdown, options));
+
+	let title = first_heading(Parser::new_ext(markdown, options))
+		.unwrap_or_else(|| fallback_title.to_string());
+	Page::new(&title, content)
+}
+
+/// Get the text of the first heading
+fn first_heading<'a>(mut events: impl Iterator<Item = Event<'a>>) -> Option<String> {
+	events.find(|event| matches!(event, Event::Start(Tag::Heading { .. })))?;
+
+	let mut title = String::new();
+	for event in events {
+		match event {
+			Event::Text(text) | Event::Code(text) => title.push_str(&text),
+			Event::End(TagEnd::Heading(_)) => break,
+			_ => {}
+		}
+	}
+	Some(title)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn render_markdown() {
+		let rendered = render(
+			"Intro\n\n# My `Rust` Talk\n\n- [Slides](https://example.com/slides)\n",
+			"talk",
+		);
+		assert_eq!(rendered.title, "My Rust Talk");
+		let rendered = rendered.content;
+		assert!(rendered.contains("<h1>My <code>Rust</code> Talk</h1>"));
+		assert!(rendered.contains("<li><a href=\"https://example.com/slides\">Slides</a></li>"));
+
+		assert_eq!(render("No headings <here>", "notes").title, "notes");
+	}
+}
@@ -0,0 +1,154 @@
+use std::{collections::HashMap, fs, hash::Hash, mem::size_of, sync::Arc};
+
+use axum::http::Uri;
+use regex::Regex;
+use serde::Serialize;
+
+use crate::{
+	enumeration_guard::EnumerationGuard, flatten::Flattener, slo::Slo, uri_mappings::UriMappings,
+};
+
+/// The caches that fill up while running, to report how much is in them
+pub struct Caches {
+	/// How many `Location` headers were rendered ahead of time
+	pub locations: usize,
+	pub guard: Option<Arc<EnumerationGuard>>,
+	pub flattener: Option<Arc<Flattener>>,
+	pub slo: Arc<Slo>,
+}
+
+/// What's taking up memory, to help decide if the mappings have outgrown being held in memory
+#[derive(Debug, Serialize)]
+pub struct MemoryReport {
+	mappings: MappingCounts,
+	/// Rough sizes of the mapping collections, in bytes. Compiled regexes aren't counted
+	estimated_bytes: MappingSizes,
+	caches: CacheOccupancy,
+	/// What the operating system reports for the process, where it's known
+	process: Option<ProcessMemory>,
+}
+
+#[derive(Debug, Serialize)]
+struct MappingCounts {
+	standard: usize,
+	pattern: usize,
+	private: usize,
+	notes: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct MappingSizes {
+	standard: usize,
+	pattern: usize,
+	private: usize,
+	notes: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct CacheOccupancy {
+	locations: usize,
+	flattened_targets: Option<usize>,
+	guarded_clients: Option<usize>,
+	slo_keys: usize,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+struct ProcessMemory {
+	resident_bytes: u64,
+	peak_resident_bytes: u64,
+}
+
+/// Report on the mappings and caches, and on the process if the operating system says
+pub fn report(uri_mappings: &UriMappings, caches: &Caches) -> MemoryReport {
+	MemoryReport {
+		mappings: MappingCounts {
+			standard: uri_mappings.standard.len(),
+			pattern: uri_mappings.pattern.len(),
+			private: uri_mappings.private.len(),
+			notes: uri_mappings.standard_notes.len() + uri_mappings.pattern_notes.len(),
+		},
+		estimated_bytes: MappingSizes {
+			standard: estimate_uris(&uri_mappings.standard),
+			pattern: estimate_patterns(&uri_mappings.pattern),
+			private: estimate_uris(&uri_mappings.private),
+			notes: estimate_notes(&uri_mappings.standard_notes)
+				+ estimate_notes(&uri_mappings.pattern_notes),
+		},
+		caches: CacheOccupancy {
+			locations: caches.locations,
+			flattened_targets: caches
+				.flattener
+				.as_ref()
+				.map(|flattener| flattener.flattened()),
+			guarded_clients: caches.guard.as_ref().map(|guard| guard.tracked_clients()),
+			slo_keys: caches.slo.tracked_keys(),
+		},
+		process: fs::read_to_string("/proc/self/status")
+			.ok()
+			.and_then(|status| parse_status(&status)),
+	}
+}
+
+/// The slots a map has room for, plus the text in its keys and values
+fn estimate_map<K, V>(map: &HashMap<K, V>, text: impl Fn(&K, &V) -> usize) -> usize
+where
+	K: Eq + Hash,
+{
+	map.capacity() * (size_of::<K>() + size_of::<V>() + 1)
+		+ map
+			.iter()
+			.map(|(key, value)| text(key, value))
+			.sum::<usize>()
+}
+
+fn estimate_uris(uris: &HashMap<String, Uri>) -> usize {
+	estimate_map(uris, |key, uri| key.len() + uri.to_string().len())
+}
+
+fn estimate_notes<K: Eq + Hash>(notes: &HashMap<K, String>) -> usize {
+	estimate_map(notes, |_, note| note.len())
+}
+
+fn estimate_patterns(patterns: &Vec<(Regex, String)>) -> usize {
+	patterns.capacity() * size_of::<(Regex, String)>()
+		+ patterns
+			.iter()
+			.map(|(regex, target)| regex.as_str().len() + target.len())
+			.sum::<usize>()
+}
+
+/// Read the resident memory out of a Linux `/proc/<pid>/status` file
+fn parse_status(status: &str) -> Option<ProcessMemory> {
+	let kilobytes = |name: &str| -> Option<u64> {
+		status
+			.lines()
+			.find_map(|line| line.strip_prefix(name)?.strip_prefix(':'))?
+			.trim()
+			.strip_suffix("kB")?
+			.trim()
+			.parse::<u64>()
+			.ok()
+	};
+	Some(ProcessMemory {
+		resident_bytes: kilobytes("VmRSS")? * 1024,
+		peak_resident_bytes: kilobytes("VmHWM")? * 1024,
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn read_process_memory() {
+		let status = "Name:\turshort\nVmHWM:\t    9000 kB\nVmRSS:\t    8000 kB\nThreads:\t4\n";
+		assert_eq!(
+			parse_status(status),
+			Some(ProcessMemory {
+				resident_bytes: 8000 * 1024,
+				peak_resident_bytes: 9000 * 1024,
+			})
+		);
+		assert_eq!(parse_status("Name:\turshort\n"), None);
+	}
+}
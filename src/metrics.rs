@@ -0,0 +1,250 @@
+use axum::{http::header, routing::get, Router};
+
+use std::{
+	collections::HashMap,
+	fmt::Write,
+	sync::{Arc, Mutex},
+	time::Duration,
+};
+
+/// Upper bounds, in seconds, of the latency histogram buckets when none are set
+pub const DEFAULT_BUCKETS: [f64; 8] = [0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1];
+
+/// How many links get their own hit counter, so paths made up by clients can't grow it forever
+const MAX_LINKS: usize = 1000;
+/// The link label used for hits past the limit
+const OTHER_LINK: &str = "_other";
+
+/// What happened to a request
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Outcome {
+	Redirect,
+	Page,
+	Miss,
+	Banned,
+}
+
+impl Outcome {
+	fn as_str(self) -> &'static str {
+		match self {
+			Outcome::Redirect => "redirect",
+			Outcome::Page => "page",
+			Outcome::Miss => "miss",
+			Outcome::Banned => "banned",
+		}
+	}
+}
+
+/// Parse comma separated bucket bounds, in seconds
+pub fn parse_buckets(buckets: &str) -> Result<Vec<f64>, String> {
+	let mut bounds = buckets
+		.split(',')
+		.map(|bound| {
+			let bound = bound.trim();
+			match bound.parse::<f64>() {
+				Ok(value) if value.is_finite() && value > 0.0 => Ok(value),
+				_ => Err(format!("'{bound}' is not a positive number of seconds")),
+			}
+		})
+		.collect::<Result<Vec<f64>, String>>()?;
+	bounds.sort_by(f64::total_cmp);
+	bounds.dedup();
+	Ok(bounds)
+}
+
+#[derive(Default)]
+struct Counts {
+	requests: HashMap<Outcome, u64>,
+	links: HashMap<String, u64>,
+	buckets: Vec<u64>,
+	sum: f64,
+	count: u64,
+	shadow_differences: u64,
+	panics: u64,
+}
+
+/// Counts requests for Prometheus to scrape
+pub struct Metrics {
+	buckets: Vec<f64>,
+	counts: Mutex<Counts>,
+}
+
+impl Metrics {
+	/// Create a new `Metrics` with the upper bounds of the latency buckets, in seconds
+	pub fn new(buckets: Vec<f64>) -> Metrics {
+		Metrics {
+			counts: Mutex::new(Counts {
+				buckets: vec![0; buckets.len()],
+				..Counts::default()
+			}),
+			buckets,
+		}
+	}
+
+	/// Count a request, with the path of the link if it matched one and how long it took to
+	/// resolve
+	pub fn record(&self, outcome: Outcome, link: Option<&str>, duration: Duration) {
+		let seconds = duration.as_secs_f64();
+		let mut counts = self.counts.lock().unwrap();
+		*counts.requests.entry(outcome).or_default() += 1;
+		if let Some(link) = link {
+			// Only new links need their own copy of the name
+			if let Some(hits) = counts.links.get_mut(link) {
+				*hits += 1;
+			} else {
+				let link = if counts.links.len() < MAX_LINKS {
+					link
+				} else {
+					OTHER_LINK
+				};
+				*counts.links.entry(link.to_string()).or_default() += 1;
+			}
+		}
+
+		// Buckets are only counted once here and added up when rendered
+		if let Some(index) = self.buckets.iter().position(|bound| seconds <= *bound) {
+			counts.buckets[index] += 1;
+		}
+		counts.sum += seconds;
+		counts.count += 1;
+	}
+
+	/// Count a request the shadow mappings would have resolved differently
+	pub fn record_shadow_difference(&self) {
+		self.counts.lock().unwrap().shadow_differences += 1;
+	}
+
+	/// Count a request whose handler panicked
+	pub fn record_panic(&self) {
+		self.counts.lock().unwrap().panics += 1;
+	}
+
+	/// Render the metrics in the Prometheus text format
+	pub fn render(&self) -> String {
+		let counts = self.counts.lock().unwrap();
+		let mut text = String::new();
+
+		text.push_str(
+			"# HELP urshort_requests_total Requests handled, by what happened to them.\n",
+		);
+		text.push_str("# TYPE urshort_requests_total counter\n");
+		for outcome in [
+			Outcome::Redirect,
+			Outcome::Page,
+			Outcome::Miss,
+			Outcome::Banned,
+		] {
+			let count = counts.requests.get(&outcome).copied().unwrap_or(0);
+			let _ = writeln!(
+				text,
+				"urshort_requests_total{{outcome=\"{}\"}} {count}",
+				outcome.as_str()
+			);
+		}
+
+		text.push_str(
+			"# HELP urshort_link_hits_total Requests that matched a link, by its path.\n",
+		);
+		text.push_str("# TYPE urshort_link_hits_total counter\n");
+		let mut links: Vec<(&String, &u64)> = counts.links.iter().collect();
+		links.sort();
+		for (link, count) in links {
+			let _ = writeln!(
+				text,
+				"urshort_link_hits_total{{link=\"{}\"}} {count}",
+				escape_label(link)
+			);
+		}
+
+		text.push_str(
+			"# HELP urshort_resolve_duration_seconds Time taken to find where a request goes.\n",
+		);
+		text.push_str("# TYPE urshort_resolve_duration_seconds histogram\n");
+		let mut cumulative = 0;
+		for (bound, count) in self.buckets.iter().zip(&counts.buckets) {
+			cumulative += count;
+			let _ = writeln!(
+				text,
+				"urshort_resolve_duration_seconds_bucket{{le=\"{bound}\"}} {cumulative}"
+			);
+		}
+		let _ = writeln!(
+			text,
+			"urshort_resolve_duration_seconds_bucket{{le=\"+Inf\"}} {}",
+			counts.count
+		);
+		let _ = writeln!(text, "urshort_resolve_duration_seconds_sum {}", counts.sum);
+		let _ = writeln!(
+			text,
+			"urshort_resolve_duration_seconds_count {}",
+			counts.count
+		);
+
+		text.push_str(
+			"# HELP urshort_shadow_differences_total Requests the shadow mappings would have resolved differently.\n",
+		);
+		text.push_str("# TYPE urshort_shadow_differences_total counter\n");
+		let _ = writeln!(
+			text,
+			"urshort_shadow_differences_total {}",
+			counts.shadow_differences
+		);
+		text.push_str(
+			"# HELP urshort_panics_total Requests answered with the error page because their handler panicked.\n",
+		);
+		text.push_str("# TYPE urshort_panics_total counter\n");
+		let _ = writeln!(text, "urshort_panics_total {}", counts.panics);
+		text
+	}
+}
+
+/// The route for Prometheus to scrape the metrics from
+pub fn router(metrics: Arc<Metrics>) -> Router {
+	Router::new().route(
+		"/metrics",
+		get(move || async move {
+			(
+				[(
+					header::CONTENT_TYPE,
+					"text/plain; version=0.0.4; charset=utf-8",
+				)],
+				metrics.render(),
+			)
+		}),
+	)
+}
+
+/// Escape a label value for the Prometheus text format
+fn escape_label(value: &str) -> String {
+	value
+		.replace('\\', "\\\\")
+		.replace('"', "\\\"")
+		.replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn render_metrics() {
+		let metrics = Metrics::new(parse_buckets("0.01, 0.001").unwrap());
+		metrics.record(Outcome::Redirect, Some("test"), Duration::from_micros(500));
+		metrics.record(Outcome::Redirect, Some("test"), Duration::from_millis(5));
+		metrics.record(Outcome::Page, Some("a\"b"), Duration::from_millis(50));
+		metrics.record(Outcome::Miss, None, Duration::from_millis(1));
+
+		let text = metrics.render();
+		assert!(text.contains("urshort_requests_total{outcome=\"redirect\"} 2\n"));
+		assert!(text.contains("urshort_requests_total{outcome=\"banned\"} 0\n"));
+		assert!(text.contains("urshort_link_hits_total{link=\"test\"} 2\n"));
+		assert!(text.contains("urshort_link_hits_total{link=\"a\\\"b\"} 1\n"));
+		assert!(text.contains("urshort_resolve_duration_seconds_bucket{le=\"0.001\"} 2\n"));
+		assert!(text.contains("urshort_resolve_duration_seconds_bucket{le=\"0.01\"} 3\n"));
+		assert!(text.contains("urshort_resolve_duration_seconds_bucket{le=\"+Inf\"} 4\n"));
+		assert!(text.contains("urshort_resolve_duration_seconds_count 4\n"));
+
+		assert!(parse_buckets("0.1,fast").is_err());
+		assert!(parse_buckets("-1").is_err());
+	}
+}
@@ -0,0 +1,183 @@
+use std::{
+	collections::HashMap,
+	str::FromStr,
+	sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+	time::Duration,
+};
+
+use async_trait::async_trait;
+use axum::http::Uri;
+use reqwest::{redirect, Client};
+
+use crate::resolver::{Resolution, ResolveRequest, Resolver};
+
+/// How long to wait on a target before counting it as down
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How to pick between healthy targets
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Balance {
+	/// Always use the first healthy target
+	#[default]
+	Failover,
+	/// Take turns between the healthy targets
+	RoundRobin,
+}
+
+impl FromStr for Balance {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.trim().to_ascii_lowercase().as_str() {
+			"failover" => Ok(Balance::Failover),
+			"round-robin" => Ok(Balance::RoundRobin),
+			_ => Err(format!("'{s}' is not 'failover' or 'round-robin'")),
+		}
+	}
+}
+
+/// Several targets for the same link, that requests are only sent to while they're healthy
+pub struct Mirrors {
+	targets: Vec<(Uri, AtomicBool)>,
+	balance: Balance,
+	turn: AtomicUsize,
+}
+
+impl Mirrors {
+	/// Create new `Mirrors`, that are all healthy until checked
+	pub fn new(targets: Vec<Uri>, balance: Balance) -> Mirrors {
+		Mirrors {
+			targets: targets
+				.into_iter()
+				.map(|uri| (uri, AtomicBool::new(true)))
+				.collect(),
+			balance,
+			turn: AtomicUsize::new(0),
+		}
+	}
+
+	/// Get the target to redirect to. If none are healthy, the first is used anyway
+	pub fn pick(&self) -> Option<&Uri> {
+		let healthy: Vec<&Uri> = self
+			.targets
+			.iter()
+			.filter(|(_, healthy)| healthy.load(Ordering::Relaxed))
+			.map(|(uri, _)| uri)
+			.collect();
+
+		match (self.balance, healthy.as_slice()) {
+			(_, []) => self.targets.first().map(|(uri, _)| uri),
+			(Balance::Failover, [first, ..]) => Some(*first),
+			(Balance::RoundRobin, healthy) => {
+				let turn = self.turn.fetch_add(1, Ordering::Relaxed);
+				Some(healthy[turn % healthy.len()])
+			}
+		}
+	}
+
+	/// Check every target, counting anything other than a server error as healthy
+	async fn check_health(&self, client: &Client) {
+		for (uri, healthy) in &self.targets {
+			let is_healthy = match client.head(uri.to_string()).send().await {
+				Ok(response) => !response.status().is_server_error(),
+				Err(_) => false,
+			};
+			if healthy.swap(is_healthy, Ordering::Relaxed) != is_healthy {
+				let status = if is_healthy { "back up" } else { "down" };
+				println!("Mirror {uri} is {status}");
+			}
+		}
+	}
+}
+
+/// Resolves paths with several targets to one that is healthy
+pub struct MirrorResolver {
+	mirrors: HashMap<String, Mirrors>,
+	client: Client,
+}
+
+impl MirrorResolver {
+	/// Create a new `MirrorResolver` from mirrors keyed by path
+	pub fn new(mirrors: HashMap<String, Mirrors>) -> Result<MirrorResolver, String> {
+		let client = Client::builder()
+			.redirect(redirect::Policy::none())
+			.timeout(HEALTH_CHECK_TIMEOUT)
+			.build()
+			.map_err(|error| error.to_string())?;
+
+		Ok(MirrorResolver { mirrors, client })
+	}
+
+	/// Check the health of every target
+	pub async fn check_health(&self) {
+		for mirrors in self.mirrors.values() {
+			mirrors.check_health(&self.client).await;
+		}
+	}
+}
+
+#[async_trait]
+impl Resolver for MirrorResolver {
+	async fn resolve(&self, request: &ResolveRequest<'_>) -> Option<Resolution> {
+		let mirrors = self.mirrors.get(request.path.as_ref())?;
+		Some(Resolution::redirect(mirrors.pick()?.clone()))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::net::{SocketAddr, TcpListener};
+
+	use axum::{http::StatusCode, routing::get, Router};
+
+	use super::*;
+
+	#[tokio::test]
+	async fn pick_healthy_mirrors() {
+		// One target that is up, one that errors, and one that isn't listening at all
+		let app = Router::new()
+			.route("/up", get(|| async { "Up" }))
+			.route("/broken", get(|| async { StatusCode::BAD_GATEWAY }));
+		let server = axum::Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0)))
+			.serve(app.into_make_service());
+		let address = server.local_addr();
+		tokio::spawn(server);
+		let closed = TcpListener::bind("127.0.0.1:0")
+			.unwrap()
+			.local_addr()
+			.unwrap();
+
+		let up = Uri::from_str(&format!("http://{address}/up")).unwrap();
+		let broken = Uri::from_str(&format!("http://{address}/broken")).unwrap();
+		let down = Uri::from_str(&format!("http://{closed}/")).unwrap();
+
+		let failover = Mirrors::new(
+			vec![broken.clone(), down.clone(), up.clone()],
+			Balance::Failover,
+		);
+		let round_robin = Mirrors::new(vec![up.clone(), down.clone()], Balance::RoundRobin);
+
+		// Everything is healthy until checked
+		assert_eq!(failover.pick(), Some(&broken));
+		assert_eq!(round_robin.pick(), Some(&up));
+		assert_eq!(round_robin.pick(), Some(&down));
+
+		let resolver = MirrorResolver::new(HashMap::from([
+			("failover".to_string(), failover),
+			("round-robin".to_string(), round_robin),
+		]))
+		.unwrap();
+		resolver.check_health().await;
+
+		let failover = &resolver.mirrors["failover"];
+		assert_eq!(failover.pick(), Some(&up));
+		let round_robin = &resolver.mirrors["round-robin"];
+		assert_eq!(round_robin.pick(), Some(&up));
+		assert_eq!(round_robin.pick(), Some(&up));
+
+		// With nothing healthy, the first target is still better than nothing
+		let all_down = Mirrors::new(vec![down.clone()], Balance::Failover);
+		all_down.check_health(&resolver.client).await;
+		assert_eq!(all_down.pick(), Some(&down));
+	}
+}
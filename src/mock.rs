@@ -0,0 +1,98 @@
+use std::{collections::HashMap, net::SocketAddr, sync::Arc};
+
+use axum::{
+	extract::Path,
+	http::{header, HeaderValue, StatusCode},
+	response::{IntoResponse, Response},
+	routing::get,
+	Router,
+};
+use serde::Deserialize;
+
+/// A redirect in a fixture file, either just the target or the target with its status
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Fixture {
+	Target(String),
+	WithStatus { target: String, status: u16 },
+}
+
+/// The redirects a mock serves, by key
+pub type Fixtures = HashMap<String, (StatusCode, HeaderValue)>;
+
+/// Parse a fixture file, a JSON object of keys to their targets, like
+/// `{"docs": "https://example.com/docs", "old": {"target": "https://example.com", "status": 301}}`
+pub fn parse_fixtures(json: &str) -> Result<Fixtures, String> {
+	let fixtures: HashMap<String, Fixture> =
+		serde_json::from_str(json).map_err(|error| error.to_string())?;
+	fixtures
+		.into_iter()
+		.map(|(key, fixture)| {
+			let (target, status) = match fixture {
+				Fixture::Target(target) => (target, StatusCode::TEMPORARY_REDIRECT),
+				Fixture::WithStatus { target, status } => {
+					let status = StatusCode::from_u16(status)
+						.ok()
+						.filter(StatusCode::is_redirection)
+						.ok_or_else(|| format!("'{key}' has {status}, which isn't a redirect"))?;
+					(target, status)
+				}
+			};
+			let location = HeaderValue::try_from(target)
+				.map_err(|error| format!("'{key}' has a target that can't be sent, {error}"))?;
+			Ok((key, (status, location)))
+		})
+		.collect()
+}
+
+/// Serve only the fixtures on the port, until stopped
+pub async fn serve(fixtures: Fixtures, port: u16) -> Result<(), String> {
+	let fixtures = Arc::new(fixtures);
+	let app = Router::new().route(
+		"/:key",
+		get(move |Path(key): Path<String>| async move { respond(&fixtures, &key) }),
+	);
+
+	let address = SocketAddr::from(([0, 0, 0, 0], port));
+	println!("Mocking on http://{address}");
+	axum::Server::bind(&address)
+		.serve(app.into_make_service())
+		.await
+		.map_err(|error| error.to_string())
+}
+
+fn respond(fixtures: &Fixtures, key: &str) -> Response {
+	match fixtures.get(key) {
+		Some((status, location)) => {
+			(*status, [(header::LOCATION, location.clone())]).into_response()
+		}
+		None => (StatusCode::NOT_FOUND, format!("No fixture for '{key}'")).into_response(),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn respond_with_fixtures() {
+		let fixtures = parse_fixtures(
+			r#"{"docs": "https://example.com/docs", "old": {"target": "https://example.com", "status": 301}}"#,
+		)
+		.unwrap();
+
+		let docs = respond(&fixtures, "docs");
+		assert_eq!(docs.status(), StatusCode::TEMPORARY_REDIRECT);
+		assert_eq!(docs.headers()[header::LOCATION], "https://example.com/docs");
+		assert_eq!(
+			respond(&fixtures, "old").status(),
+			StatusCode::MOVED_PERMANENTLY
+		);
+		assert_eq!(respond(&fixtures, "new").status(), StatusCode::NOT_FOUND);
+
+		assert!(
+			parse_fixtures(r#"{"bad": {"target": "https://example.com", "status": 200}}"#).is_err()
+		);
+		assert!(parse_fixtures("[]").is_err());
+	}
+}
@@ -0,0 +1,169 @@
+use axum::{http::HeaderMap, response::Html};
+
+use crate::{html, locales::Locales, static_assets::StaticAssets, theme::Theme};
+
+/// The content of a page, which gets placed inside of the site's template when it's sent
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Page {
+	/// Already escaped title
+	pub title: String,
+	/// HTML for the body of the page
+	pub content: String,
+}
+
+impl Page {
+	/// Create a new `Page`, escaping the title
+	pub fn new(title: &str, content: String) -> Page {
+		Page {
+			title: html::escape(title),
+			content,
+		}
+	}
+}
+
+/// The words used on the built in pages
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Strings {
+	/// The language the strings are written in
+	pub language: String,
+	pub index_title: String,
+	pub index_heading: String,
+	pub index_message: String,
+	pub error_title: String,
+	pub error_heading: String,
+	pub error_message: String,
+}
+
+impl Default for Strings {
+	fn default() -> Self {
+		Strings {
+			language: "en".to_string(),
+			index_title: "URShort".to_string(),
+			index_heading: "URShort is live!".to_string(),
+			index_message: "If you can read this, URShort has successfully started.".to_string(),
+			error_title: "URShort Error".to_string(),
+			error_heading: "Whoops...".to_string(),
+			error_message: "It seems the URL you went to either hasn't been set yet or was misspelled. Please contact the website owner to let them know.".to_string(),
+		}
+	}
+}
+
+/// Renders the built in pages and any others with the site's theme, strings, and translations
+#[derive(Default)]
+pub struct Pages {
+	pub theme: Theme,
+	pub strings: Strings,
+	pub locales: Locales,
+	pub assets: StaticAssets,
+}
+
+impl Pages {
+	/// Render a page with the theme
+	pub fn render(&self, page: &Page) -> Html<String> {
+		self.render_in(&self.strings.language, page)
+	}
+
+	fn render_in(&self, language: &str, page: &Page) -> Html<String> {
+		Html(self.theme.render(
+			language,
+			&page.title,
+			&page.content,
+			&self.assets.path("style.css"),
+		))
+	}
+
+	/// Render a built in page, using a translation instead of the strings if there is one
+	fn built_in(
+		&self,
+		name: &str,
+		headers: &HeaderMap,
+		(title, heading, message): (&str, &str, &str),
+		notice: Option<&str>,
+	) -> Html<String> {
+		let (language, content) = match self.locales.page(name, headers) {
+			Some((language, content)) => (language, content.to_string()),
+			None => (
+				self.strings.language.as_str(),
+				format!(
+					"\t<h1>{}</h1>\n\t<p>{}</p>",
+					html::escape(heading),
+					html::escape(message)
+				),
+			),
+		};
+		let content = match notice {
+			Some(notice) => html::with_notice(&content, notice),
+			None => content,
+		};
+		self.render_in(language, &Page::new(title, content))
+	}
+
+	/// The index page, with the notice if there is one
+	pub fn index(&self, headers: &HeaderMap, notice: Option<&str>) -> Html<String> {
+		let strings = &self.strings;
+		self.built_in(
+			"index",
+			headers,
+			(
+				&strings.index_title,
+				&strings.index_heading,
+				&strings.index_message,
+			),
+			notice,
+		)
+	}
+
+	/// The error page
+	pub fn error(&self, headers: &HeaderMap) -> Html<String> {
+		let strings = &self.strings;
+		self.built_in(
+			"error",
+			headers,
+			(
+				&strings.error_title,
+				&strings.error_heading,
+				&strings.error_message,
+			),
+			None,
+		)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use axum::http::HeaderValue;
+
+	use super::*;
+
+	#[test]
+	fn render_built_in_pages() {
+		let pages = Pages {
+			strings: Strings {
+				error_heading: "Not <here>".to_string(),
+				..Strings::default()
+			},
+			..Pages::default()
+		};
+
+		let Html(page) = pages.error(&HeaderMap::new());
+		assert!(page.contains("<html lang=\"en\">"));
+		assert!(page.contains("<title>URShort Error</title>"));
+		assert!(page.contains("<h1>Not &lt;here&gt;</h1>"));
+
+		let Html(page) = pages.index(&HeaderMap::new(), Some("Moving"));
+		assert!(page.contains(
+			"<p class=\"notice\" role=\"status\">Moving</p>\n\t<h1>URShort is live!</h1>"
+		));
+
+		// Translations have their own language
+		let mut headers = HeaderMap::new();
+		headers.insert("Accept-Language", HeaderValue::from_static("de"));
+		let pages = Pages {
+			locales: Locales::from_pages(&[("de", "error", "\t<h1>Fehler</h1>")]),
+			..Pages::default()
+		};
+		let Html(page) = pages.error(&headers);
+		assert!(page.contains("<html lang=\"de\">"));
+		assert!(page.contains("<h1>Fehler</h1>"));
+	}
+}
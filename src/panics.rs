@@ -0,0 +1,33 @@
+use std::{any::Any, backtrace::Backtrace, panic::PanicHookInfo};
+
+/// Log a panic with where it happened and a stack trace. Requests that panic are still answered,
+/// so this is the only sign of them besides the metrics
+pub fn log_panic(info: &PanicHookInfo) {
+	println!("A thread {info}");
+	println!("{}", Backtrace::force_capture());
+	println!();
+}
+
+/// The message a handler panicked with, if it was text
+pub fn panic_message(error: &(dyn Any + Send)) -> &str {
+	error
+		.downcast_ref::<&str>()
+		.copied()
+		.or_else(|| error.downcast_ref::<String>().map(String::as_str))
+		.unwrap_or("unknown panic")
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn read_panic_messages() {
+		let text: Box<dyn Any + Send> = Box::new("went wrong");
+		assert_eq!(panic_message(text.as_ref()), "went wrong");
+		let formatted: Box<dyn Any + Send> = Box::new(format!("went wrong {}", 2));
+		assert_eq!(panic_message(formatted.as_ref()), "went wrong 2");
+		let other: Box<dyn Any + Send> = Box::new(2);
+		assert_eq!(panic_message(other.as_ref()), "unknown panic");
+	}
+}
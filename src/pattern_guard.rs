@@ -0,0 +1,80 @@
+use std::{
+	sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+	time::Duration,
+};
+
+use regex::Regex;
+
+/// Skips patterns that keep taking too long to match, so one slow regex can't hold up every
+/// request that reaches the patterns
+pub struct PatternGuard {
+	budget: Duration,
+	/// How many slow matches in a row quarantine a pattern
+	strikes: usize,
+	/// How many slow matches in a row each pattern has had
+	slow: Vec<AtomicUsize>,
+	quarantined: Vec<AtomicBool>,
+}
+
+impl PatternGuard {
+	/// Create a new `PatternGuard` for the number of patterns, with how long a match can take
+	pub fn new(patterns: usize, budget: Duration, strikes: usize) -> PatternGuard {
+		PatternGuard {
+			budget,
+			strikes: strikes.max(1),
+			slow: (0..patterns).map(|_| AtomicUsize::new(0)).collect(),
+			quarantined: (0..patterns).map(|_| AtomicBool::new(false)).collect(),
+		}
+	}
+
+	/// Check if the pattern in the place has been quarantined, so it should be skipped
+	pub fn is_quarantined(&self, place: usize) -> bool {
+		self.quarantined
+			.get(place)
+			.is_some_and(|quarantined| quarantined.load(Ordering::Relaxed))
+	}
+
+	/// Record how long the pattern in the place took to match, quarantining it if it's been over
+	/// the budget too many times in a row
+	pub fn record(&self, place: usize, regex: &Regex, elapsed: Duration) {
+		let Some(slow) = self.slow.get(place) else {
+			return;
+		};
+		if elapsed <= self.budget {
+			slow.store(0, Ordering::Relaxed);
+			return;
+		}
+
+		let strikes = slow.fetch_add(1, Ordering::Relaxed) + 1;
+		if strikes >= self.strikes && !self.quarantined[place].swap(true, Ordering::Relaxed) {
+			println!(
+				"Pattern {place} ({regex}) was quarantined, because it took longer than {:?} to match {strikes} times in a row",
+				self.budget
+			);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn quarantine_slow_patterns() {
+		let regex = Regex::new("^a+$").unwrap();
+		let guard = PatternGuard::new(2, Duration::from_millis(1), 2);
+		let slow = Duration::from_millis(5);
+		let fast = Duration::from_micros(5);
+
+		// A fast match in between starts the count over
+		guard.record(0, &regex, slow);
+		guard.record(0, &regex, fast);
+		guard.record(0, &regex, slow);
+		assert!(!guard.is_quarantined(0));
+
+		guard.record(0, &regex, slow);
+		assert!(guard.is_quarantined(0));
+		assert!(!guard.is_quarantined(1));
+		assert!(!guard.is_quarantined(2));
+	}
+}
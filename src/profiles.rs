@@ -0,0 +1,77 @@
+use std::fmt::Write;
+
+use axum::http::Uri;
+
+use crate::{bundles::Bundle, html, pages::Page};
+
+/// The key profiles are shown under, before the username
+pub const PROFILE_PREFIX: char = '@';
+
+/// A page about a person, with the links they want to share
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Profile {
+	pub name: String,
+	pub bio: Option<String>,
+	pub avatar: Option<Uri>,
+	pub links: Bundle,
+}
+
+impl Profile {
+	/// Render the profile page
+	pub fn page(&self) -> Page {
+		let mut content = String::new();
+		if let Some(avatar) = &self.avatar {
+			let _ = writeln!(
+				content,
+				"\t<img class=\"avatar\" src=\"{}\" alt=\"\">",
+				html::escape(&avatar.to_string())
+			);
+		}
+		let _ = writeln!(content, "\t<h1>{}</h1>", html::escape(&self.name));
+		if let Some(bio) = &self.bio {
+			let _ = writeln!(content, "\t<p>{}</p>", html::escape(bio));
+		}
+		content.push_str(&self.links.list());
+		Page::new(&self.name, content)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::str::FromStr;
+
+	use super::*;
+
+	#[test]
+	fn render_profiles() {
+		let profile = Profile {
+			name: "Ada <3".to_string(),
+			bio: Some("Writes \"programs\"".to_string()),
+			avatar: Some(Uri::from_str("https://example.com/ada.png?size=64&round").unwrap()),
+			links: Bundle::parse("Blog https://blog.example.com/", |target| {
+				Uri::from_str(target).map_err(|error| error.to_string())
+			})
+			.unwrap(),
+		};
+
+		let page = profile.page();
+		assert_eq!(page.title, "Ada &lt;3");
+		let page = page.content;
+		assert!(page.contains(
+			"<img class=\"avatar\" src=\"https://example.com/ada.png?size=64&amp;round\" alt=\"\">"
+		));
+		assert!(page.contains("<h1>Ada &lt;3</h1>"));
+		assert!(page.contains("<p>Writes &quot;programs&quot;</p>"));
+		assert!(page.contains("<a href=\"https://blog.example.com/\">Blog</a>"));
+
+		let plain = Profile {
+			bio: None,
+			avatar: None,
+			..profile
+		}
+		.page()
+		.content;
+		assert!(!plain.contains("<img"));
+		assert!(!plain.contains("<p>"));
+	}
+}
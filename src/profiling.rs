@@ -0,0 +1,113 @@
+use std::{thread, time::Duration};
+
+use axum::{
+	extract::Query,
+	http::{header, StatusCode},
+	response::{IntoResponse, Response},
+	routing::get,
+	Router,
+};
+use pprof::{protos::Message, ProfilerGuardBuilder};
+use serde::Deserialize;
+
+/// How long a profile runs for when it isn't said
+const DEFAULT_SECONDS: u64 = 10;
+/// The longest a profile can run for, so a request can't leave the profiler running
+const MAX_SECONDS: u64 = 60;
+/// How many times a second the threads are sampled. Not quite 100, so it doesn't line up with
+/// work that runs on a timer
+const FREQUENCY: i32 = 99;
+
+#[derive(Deserialize)]
+struct ProfileQuery {
+	seconds: Option<u64>,
+}
+
+#[derive(Clone, Copy)]
+enum Format {
+	/// The protobuf format `go tool pprof` reads
+	Pprof,
+	/// An SVG flamegraph
+	Flamegraph,
+}
+
+/// Create the routes that profile the running instance. They go under the admin API, so they
+/// need its token
+pub fn router() -> Router {
+	Router::new()
+		// `GET /api/debug/pprof/profile?seconds=<seconds>` for a CPU profile for `go tool pprof`
+		.route(
+			"/debug/pprof/profile",
+			get(|query| profile(query, Format::Pprof)),
+		)
+		// `GET /api/debug/pprof/flamegraph?seconds=<seconds>` for a CPU profile as a flamegraph
+		.route(
+			"/debug/pprof/flamegraph",
+			get(|query| profile(query, Format::Flamegraph)),
+		)
+}
+
+async fn profile(Query(query): Query<ProfileQuery>, format: Format) -> Response {
+	let duration = profile_duration(query.seconds);
+	let profiled = tokio::task::spawn_blocking(move || record(duration, format)).await;
+	match profiled {
+		Ok(Ok(body)) => {
+			let content_type = match format {
+				Format::Pprof => "application/octet-stream",
+				Format::Flamegraph => "image/svg+xml",
+			};
+			([(header::CONTENT_TYPE, content_type)], body).into_response()
+		}
+		Ok(Err(error)) => (StatusCode::CONFLICT, error).into_response(),
+		Err(error) => (StatusCode::INTERNAL_SERVER_ERROR, error.to_string()).into_response(),
+	}
+}
+
+/// How long to profile for, from the seconds asked for
+fn profile_duration(seconds: Option<u64>) -> Duration {
+	Duration::from_secs(seconds.unwrap_or(DEFAULT_SECONDS).clamp(1, MAX_SECONDS))
+}
+
+/// Sample every thread for the duration, then write the profile out. Only one profile can run
+/// at a time
+fn record(duration: Duration, format: Format) -> Result<Vec<u8>, String> {
+	let guard = ProfilerGuardBuilder::default()
+		.frequency(FREQUENCY)
+		.blocklist(&["libc", "libgcc", "pthread", "vdso"])
+		.build()
+		.map_err(|error| format!("Could not start profiling, because {error}"))?;
+	thread::sleep(duration);
+
+	let report = guard
+		.report()
+		.build()
+		.map_err(|error| format!("Could not build the profile, because {error}"))?;
+	let mut body = Vec::new();
+	match format {
+		Format::Pprof => report
+			.pprof()
+			.map_err(|error| error.to_string())?
+			.encode(&mut body)
+			.map_err(|error| error.to_string())?,
+		Format::Flamegraph => report
+			.flamegraph(&mut body)
+			.map_err(|error| error.to_string())?,
+	}
+	Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn limit_profile_duration() {
+		assert_eq!(profile_duration(None), Duration::from_secs(DEFAULT_SECONDS));
+		assert_eq!(profile_duration(Some(0)), Duration::from_secs(1));
+		assert_eq!(profile_duration(Some(5)), Duration::from_secs(5));
+		assert_eq!(
+			profile_duration(Some(3600)),
+			Duration::from_secs(MAX_SECONDS)
+		);
+	}
+}
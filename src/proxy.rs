@@ -0,0 +1,254 @@
+use std::{error::Error, io, str::FromStr, time::Duration};
+
+use axum::{
+	body::{Body, StreamBody},
+	http::{
+		header::{self, HeaderName},
+		HeaderMap, Method, StatusCode, Uri,
+	},
+	response::{IntoResponse, Response},
+};
+use futures_util::stream;
+use hyper::body::HttpBody;
+use reqwest::{redirect, Client};
+
+use crate::{flatten::MAX_HOPS, log_limit::LogLimit, targets::TargetPolicy};
+
+/// How long to wait to connect to a target before giving up
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long a target can take to start answering, or to send the next part of the body, before
+/// giving up on it
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+/// Targets that couldn't be proxied, printed a few at a time
+static FAILURE_LOG: LogLimit = LogLimit::new("proxy failures");
+/// The request headers passed on to the target, so it can answer the same as it would directly
+const REQUEST_HEADERS: [HeaderName; 8] = [
+	header::CONTENT_TYPE,
+	header::CONTENT_LENGTH,
+	header::ACCEPT,
+	header::ACCEPT_LANGUAGE,
+	header::USER_AGENT,
+	header::RANGE,
+	header::IF_NONE_MATCH,
+	header::IF_MODIFIED_SINCE,
+];
+/// The response headers passed back, which describe the body. Anything else, like cookies or
+/// where the target redirected, could give away the target
+const RESPONSE_HEADERS: [HeaderName; 10] = [
+	header::CONTENT_TYPE,
+	header::CONTENT_LENGTH,
+	header::CONTENT_RANGE,
+	header::CONTENT_LANGUAGE,
+	header::CONTENT_DISPOSITION,
+	header::ACCEPT_RANGES,
+	header::CACHE_CONTROL,
+	header::ETAG,
+	header::LAST_MODIFIED,
+	header::EXPIRES,
+];
+
+/// Fetches targets and streams them back, instead of redirecting to them
+pub struct Proxy {
+	client: Client,
+}
+
+impl Proxy {
+	/// Create a new `Proxy`. Each redirect the target sends is checked like any other target
+	/// before it's followed, since the client can't see where it goes
+	pub fn new(targets: TargetPolicy) -> Result<Proxy, String> {
+		let policy = redirect::Policy::custom(move |attempt| {
+			// The first URI is the target itself
+			if attempt.previous().len() > MAX_HOPS {
+				return attempt.error(format!("it redirected more than {MAX_HOPS} times"));
+			}
+			match Uri::from_str(attempt.url().as_str())
+				.map_err(|error| error.to_string())
+				.and_then(|uri| targets.resolve(&uri))
+			{
+				Ok(_) => attempt.follow(),
+				Err(error) => attempt.error(error),
+			}
+		});
+		let client = Client::builder()
+			.connect_timeout(CONNECT_TIMEOUT)
+			.redirect(policy)
+			.build()
+			.map_err(|error| error.to_string())?;
+		Ok(Proxy { client })
+	}
+
+	/// Fetch the target and stream it back as the response, sending the request body on as it
+	/// arrives. Redirects are followed here, so the client never sees where the target is
+	pub async fn forward(
+		&self,
+		target: &str,
+		method: Method,
+		headers: &HeaderMap,
+		body: Body,
+	) -> Response {
+		let mut request = self.client.request(method, target);
+		for name in &REQUEST_HEADERS {
+			if let Some(value) = headers.get(name) {
+				request = request.header(name, value);
+			}
+		}
+		if !body.is_end_stream() {
+			request = request.body(reqwest::Body::wrap_stream(body));
+		}
+
+		let response = match tokio::time::timeout(READ_TIMEOUT, request.send()).await {
+			Ok(Ok(response)) => response,
+			Ok(Err(error)) => {
+				FAILURE_LOG.print(target, || {
+					format!("Could not proxy {target}, because {error}")
+				});
+				return StatusCode::BAD_GATEWAY.into_response();
+			}
+			Err(_) => {
+				FAILURE_LOG.print(target, || {
+					format!("Could not proxy {target}, because it took too long to answer")
+				});
+				return StatusCode::GATEWAY_TIMEOUT.into_response();
+			}
+		};
+
+		let status = response.status();
+		let mut forwarded = HeaderMap::new();
+		for name in &RESPONSE_HEADERS {
+			if let Some(value) = response.headers().get(name) {
+				forwarded.insert(name, value.clone());
+			}
+		}
+		(status, forwarded, StreamBody::new(read_body(response))).into_response()
+	}
+}
+
+/// Stream the target's body, cutting it off if the target stalls part way through
+fn read_body(
+	response: reqwest::Response,
+) -> impl futures_util::Stream<Item = Result<axum::body::Bytes, Box<dyn Error + Send + Sync>>> {
+	stream::unfold(Some(response), |response| async move {
+		let mut response = response?;
+		match tokio::time::timeout(READ_TIMEOUT, response.chunk()).await {
+			Ok(Ok(Some(chunk))) => Some((Ok(chunk), Some(response))),
+			Ok(Ok(None)) => None,
+			Ok(Err(error)) => Some((Err(error.into()), None)),
+			Err(_) => {
+				let error = io::Error::new(io::ErrorKind::TimedOut, "the target stopped sending");
+				Some((Err(error.into()), None))
+			}
+		}
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{
+		net::SocketAddr,
+		sync::{
+			atomic::{AtomicUsize, Ordering},
+			Arc,
+		},
+	};
+
+	use axum::{
+		response::Redirect,
+		routing::{get, post},
+		Router,
+	};
+
+	use super::*;
+
+	#[tokio::test]
+	async fn proxy_targets() {
+		let loops = Arc::new(AtomicUsize::new(0));
+		let counted = loops.clone();
+		let app = Router::new()
+			.route(
+				"/internal",
+				get(|headers: HeaderMap| async move {
+					(
+						[
+							(header::CONTENT_TYPE, "text/plain"),
+							(header::SET_COOKIE, "session=secret"),
+						],
+						format!("Hi {:?}", headers.get(header::ACCEPT_LANGUAGE).unwrap()),
+					)
+				}),
+			)
+			.route("/moved", get(|| async { Redirect::temporary("/internal") }))
+			.route(
+				"/loop",
+				get(move || {
+					counted.fetch_add(1, Ordering::Relaxed);
+					async { Redirect::temporary("/loop") }
+				}),
+			)
+			.route(
+				"/script",
+				get(|| async { Redirect::temporary("javascript:alert(1)") }),
+			)
+			.route(
+				"/echo",
+				post(|headers: HeaderMap, body: String| async move {
+					format!("{:?} {body}", headers.get(header::CONTENT_TYPE).unwrap())
+				}),
+			);
+		let server = axum::Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0)))
+			.serve(app.into_make_service());
+		let address = server.local_addr();
+		tokio::spawn(server);
+
+		let proxy = Proxy::new(TargetPolicy::default()).unwrap();
+		let mut headers = HeaderMap::new();
+		headers.insert(header::ACCEPT_LANGUAGE, "fr".parse().unwrap());
+		headers.insert(header::COOKIE, "mine=private".parse().unwrap());
+
+		let response = proxy
+			.forward(
+				&format!("http://{address}/moved"),
+				Method::GET,
+				&headers,
+				Body::empty(),
+			)
+			.await;
+		assert_eq!(response.status(), StatusCode::OK);
+		assert_eq!(response.headers()[header::CONTENT_TYPE], "text/plain");
+		assert!(response.headers().get(header::SET_COOKIE).is_none());
+		assert!(response.headers().get(header::LOCATION).is_none());
+		let text = hyper::body::to_bytes(response.into_body()).await.unwrap();
+		assert_eq!(text, "Hi \"fr\"");
+
+		headers.insert(header::CONTENT_TYPE, "text/plain".parse().unwrap());
+		let posted = proxy
+			.forward(
+				&format!("http://{address}/echo"),
+				Method::POST,
+				&headers,
+				Body::wrap_stream(stream::iter([Ok::<_, io::Error>("sent "), Ok("in parts")])),
+			)
+			.await;
+		assert_eq!(posted.status(), StatusCode::OK);
+		let text = hyper::body::to_bytes(posted.into_body()).await.unwrap();
+		assert_eq!(text, "\"text/plain\" sent in parts");
+
+		let closed = proxy
+			.forward("http://127.0.0.1:1/", Method::GET, &headers, Body::empty())
+			.await;
+		assert_eq!(closed.status(), StatusCode::BAD_GATEWAY);
+
+		// Redirects are only followed to allowed targets, and not forever
+		for path in ["loop", "script"] {
+			let redirected = proxy
+				.forward(
+					&format!("http://{address}/{path}"),
+					Method::GET,
+					&headers,
+					Body::empty(),
+				)
+				.await;
+			assert_eq!(redirected.status(), StatusCode::BAD_GATEWAY);
+		}
+		assert_eq!(loops.load(Ordering::Relaxed), MAX_HOPS + 1);
+	}
+}
@@ -0,0 +1,151 @@
+use std::{str::FromStr, time::Duration};
+
+use reqwest::Client;
+use serde::Serialize;
+
+/// How long to wait on the CDN before giving up
+const PURGE_TIMEOUT: Duration = Duration::from_secs(10);
+/// The most URLs Cloudflare purges in one request
+const CLOUDFLARE_BATCH: usize = 30;
+
+/// The CDN in front of the links, whose cached redirects are purged
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Cdn {
+	/// Cloudflare, with the zone the short URLs are in
+	Cloudflare {
+		zone: String,
+	},
+	Fastly,
+}
+
+impl FromStr for Cdn {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let (name, zone) = s.trim().split_once(':').unwrap_or((s.trim(), ""));
+		match (name.to_ascii_lowercase().as_str(), zone.trim()) {
+			("fastly", "") => Ok(Cdn::Fastly),
+			("cloudflare", zone) if !zone.is_empty() => Ok(Cdn::Cloudflare {
+				zone: zone.to_string(),
+			}),
+			_ => Err(format!("'{s}' is not 'cloudflare:<zone>' or 'fastly'")),
+		}
+	}
+}
+
+#[derive(Serialize)]
+struct CloudflareFiles<'a> {
+	files: &'a [String],
+}
+
+/// Asks the CDN to forget its cached copies of short URLs, so changed links take effect
+pub struct Purger {
+	cdn: Cdn,
+	token: String,
+	client: Client,
+}
+
+impl Purger {
+	/// Create a new `Purger` for the CDN, using the API token
+	pub fn new(cdn: Cdn, token: String) -> Result<Purger, String> {
+		let client = Client::builder()
+			.timeout(PURGE_TIMEOUT)
+			.build()
+			.map_err(|error| error.to_string())?;
+		Ok(Purger { cdn, token, client })
+	}
+
+	/// Purge each of the URLs from the CDN
+	pub async fn purge(&self, urls: &[String]) -> Result<(), String> {
+		for (endpoint, files) in purge_requests(&self.cdn, urls) {
+			let request = match &self.cdn {
+				Cdn::Cloudflare { .. } => self
+					.client
+					.post(&endpoint)
+					.bearer_auth(&self.token)
+					.json(&CloudflareFiles { files: &files }),
+				Cdn::Fastly => self
+					.client
+					.post(&endpoint)
+					.header("Fastly-Key", &self.token),
+			};
+			request
+				.send()
+				.await
+				.and_then(reqwest::Response::error_for_status)
+				.map_err(|error| {
+					format!("Could not purge {}, because {error}", files.join(", "))
+				})?;
+		}
+		Ok(())
+	}
+}
+
+/// The API endpoints to call to purge the URLs, with the URLs each one purges
+fn purge_requests(cdn: &Cdn, urls: &[String]) -> Vec<(String, Vec<String>)> {
+	match cdn {
+		Cdn::Cloudflare { zone } => urls
+			.chunks(CLOUDFLARE_BATCH)
+			.map(|files| {
+				(
+					format!("https://api.cloudflare.com/client/v4/zones/{zone}/purge_cache"),
+					files.to_vec(),
+				)
+			})
+			.collect(),
+		// Fastly purges one URL at a time, given without its scheme
+		Cdn::Fastly => urls
+			.iter()
+			.map(|url| {
+				let cached = url.split_once("://").map_or(url.as_str(), |(_, rest)| rest);
+				(
+					format!("https://api.fastly.com/purge/{cached}"),
+					vec![url.clone()],
+				)
+			})
+			.collect(),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn build_purge_requests() {
+		assert_eq!(
+			Cdn::from_str("Cloudflare:ABC123"),
+			Ok(Cdn::Cloudflare {
+				zone: "ABC123".to_string()
+			})
+		);
+		assert_eq!(Cdn::from_str("fastly"), Ok(Cdn::Fastly));
+		assert!(Cdn::from_str("cloudflare:").is_err());
+		assert!(Cdn::from_str("akamai").is_err());
+
+		let urls: Vec<String> = (0..31)
+			.map(|index| format!("https://go.example.com/{index}"))
+			.collect();
+		let cloudflare = purge_requests(
+			&Cdn::Cloudflare {
+				zone: "abc123".to_string(),
+			},
+			&urls,
+		);
+		assert_eq!(cloudflare.len(), 2);
+		assert_eq!(
+			cloudflare[0].0,
+			"https://api.cloudflare.com/client/v4/zones/abc123/purge_cache"
+		);
+		assert_eq!(cloudflare[0].1.len(), 30);
+		assert_eq!(cloudflare[1].1, vec!["https://go.example.com/30"]);
+
+		assert_eq!(
+			purge_requests(&Cdn::Fastly, &urls[..1]),
+			vec![(
+				"https://api.fastly.com/purge/go.example.com/0".to_string(),
+				vec!["https://go.example.com/0".to_string()]
+			)]
+		);
+	}
+}
@@ -0,0 +1,186 @@
+use std::{borrow::Cow, collections::HashMap, net::IpAddr, sync::Arc};
+
+use async_trait::async_trait;
+use axum::http::{HeaderMap, Uri};
+
+use crate::{pages::Page, uri_mappings::LinkOptions};
+
+/// Everything known about a request that resolvers can use to find a URI
+pub struct ResolveRequest<'a> {
+	pub path: Cow<'a, str>,
+	pub query: &'a HashMap<String, String>,
+	pub headers: &'a HeaderMap,
+	pub client: IpAddr,
+}
+
+/// What to send back for a resolved request
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Target {
+	/// Redirect to the URI
+	Redirect(Uri),
+	/// Show a page instead of redirecting
+	Page(Page),
+}
+
+/// The target a request resolved to, along with the options of the mapping that matched
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Resolution {
+	pub target: Target,
+	pub options: LinkOptions,
+}
+
+impl Resolution {
+	/// Redirect to the URI, without any options
+	pub fn redirect(uri: Uri) -> Resolution {
+		Resolution {
+			target: Target::Redirect(uri),
+			options: LinkOptions::default(),
+		}
+	}
+}
+
+/// A single way of resolving a request to a URI
+#[async_trait]
+pub trait Resolver: Send + Sync {
+	/// Attempt to resolve the request. `None` passes the request on to the next resolver
+	async fn resolve(&self, request: &ResolveRequest<'_>) -> Option<Resolution>;
+}
+
+#[async_trait]
+impl<R: Resolver + ?Sized> Resolver for Arc<R> {
+	async fn resolve(&self, request: &ResolveRequest<'_>) -> Option<Resolution> {
+		self.as_ref().resolve(request).await
+	}
+}
+
+/// Tries each resolver in order until one finds a URI
+#[derive(Default)]
+pub struct ResolverChain {
+	resolvers: Vec<Box<dyn Resolver>>,
+}
+
+impl ResolverChain {
+	/// Create a new `ResolverChain` that doesn't resolve anything
+	pub fn new() -> ResolverChain {
+		ResolverChain::default()
+	}
+
+	/// Add a resolver to try after the existing ones
+	pub fn then(mut self, resolver: impl Resolver + 'static) -> ResolverChain {
+		self.resolvers.push(Box::new(resolver));
+		self
+	}
+
+	/// Find the first resolver with a URI for the request
+	pub async fn resolve(&self, request: &ResolveRequest<'_>) -> Option<Resolution> {
+		for resolver in &self.resolvers {
+			if let Some(resolution) = resolver.resolve(request).await {
+				return Some(resolution);
+			}
+		}
+		None
+	}
+}
+
+/// Resolves every request to the same URI, for use at the end of a chain
+pub struct FallbackResolver {
+	uri: Uri,
+}
+
+impl FallbackResolver {
+	/// Create a new `FallbackResolver` for the URI
+	pub fn new(uri: Uri) -> FallbackResolver {
+		FallbackResolver { uri }
+	}
+}
+
+#[async_trait]
+impl Resolver for FallbackResolver {
+	async fn resolve(&self, _request: &ResolveRequest<'_>) -> Option<Resolution> {
+		Some(Resolution::redirect(self.uri.clone()))
+	}
+}
+
+/// Resolves paths to pages that never change, such as bundles and Markdown pages
+pub struct PageResolver {
+	pages: HashMap<String, Page>,
+}
+
+impl PageResolver {
+	/// Create a new `PageResolver` from pages keyed by path
+	pub fn new(pages: HashMap<String, Page>) -> PageResolver {
+		PageResolver { pages }
+	}
+}
+
+#[async_trait]
+impl Resolver for PageResolver {
+	async fn resolve(&self, request: &ResolveRequest<'_>) -> Option<Resolution> {
+		let page = self.pages.get(request.path.as_ref())?;
+		Some(Resolution {
+			target: Target::Page(page.clone()),
+			options: LinkOptions::default(),
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{net::Ipv4Addr, str::FromStr};
+
+	use super::*;
+
+	struct Exact(&'static str, &'static str);
+
+	#[async_trait]
+	impl Resolver for Exact {
+		async fn resolve(&self, request: &ResolveRequest<'_>) -> Option<Resolution> {
+			(request.path == self.0).then(|| Resolution::redirect(Uri::from_str(self.1).unwrap()))
+		}
+	}
+
+	fn request<'a>(
+		path: &'a str,
+		query: &'a HashMap<String, String>,
+		headers: &'a HeaderMap,
+	) -> ResolveRequest<'a> {
+		ResolveRequest {
+			path: Cow::Borrowed(path),
+			query,
+			headers,
+			client: IpAddr::V4(Ipv4Addr::LOCALHOST),
+		}
+	}
+
+	#[tokio::test]
+	async fn resolve_in_order() {
+		let query = HashMap::new();
+		let headers = HeaderMap::new();
+		let uri = |uri| Some(Target::Redirect(Uri::from_str(uri).unwrap()));
+
+		let chain = ResolverChain::new()
+			.then(Exact("a", "https://example.com/first"))
+			.then(Exact("a", "https://example.com/second"))
+			.then(Exact("b", "https://example.com/b"));
+
+		// The first resolver with a match wins
+		let resolved = chain.resolve(&request("a", &query, &headers)).await;
+		assert_eq!(resolved.map(|x| x.target), uri("https://example.com/first"));
+		let resolved = chain.resolve(&request("b", &query, &headers)).await;
+		assert_eq!(resolved.map(|x| x.target), uri("https://example.com/b"));
+		assert!(chain
+			.resolve(&request("c", &query, &headers))
+			.await
+			.is_none());
+
+		// A fallback catches everything else
+		let chain = chain.then(FallbackResolver::new(
+			Uri::from_str("https://example.com/fallback").unwrap(),
+		));
+		let resolved = chain.resolve(&request("c", &query, &headers)).await;
+		assert_eq!(
+			resolved.map(|x| x.target),
+			uri("https://example.com/fallback")
+		);
+	}
+}
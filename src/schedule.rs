@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use axum::http::Uri;
+
+use crate::{
+	clock::UtcTime,
+	resolver::{Resolution, ResolveRequest, Resolver},
+};
+
+const MINUTES_PER_DAY: u32 = 24 * 60;
+const WEEKDAYS: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+
+/// A target that changes over the week, such as an on-call rota. Written as comma separated
+/// entries of `[day] HH:MM <uri>`, each starting at that time in UTC. Entries without a day start
+/// at that time every day.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Schedule {
+	/// The minute of the week each URI starts at, from earliest to latest
+	entries: Vec<(u32, Uri)>,
+}
+
+impl Schedule {
+	/// Get the URI for the time. Before the week's first entry starts, the last one is still going
+	pub fn at(&self, time: UtcTime) -> &Uri {
+		let now = minute_of_week(time);
+		let current = self.entries.iter().rev().find(|(start, _)| *start <= now);
+		&current.or_else(|| self.entries.last()).unwrap().1
+	}
+
+	/// Parse entries like `mon 09:00 https://example.com/alice`, with the targets parsed the same as
+	/// any other
+	pub fn parse(
+		s: &str,
+		parse_target: impl Fn(&str) -> Result<Uri, String>,
+	) -> Result<Schedule, String> {
+		let mut entries = Vec::new();
+		for entry in s.split(',').filter(|entry| !entry.trim().is_empty()) {
+			let parts: Vec<&str> = entry.split_whitespace().collect();
+			let (days, time, uri) = match parts.as_slice() {
+				[time, uri] => ((0..7).collect(), *time, *uri),
+				[day, time, uri] => {
+					let day = WEEKDAYS
+						.iter()
+						.position(|weekday| weekday.eq_ignore_ascii_case(day))
+						.ok_or_else(|| format!("'{day}' is not a day, like 'mon'"))?;
+					(vec![day], *time, *uri)
+				}
+				_ => {
+					return Err(format!(
+						"'{}' is not like '[day] HH:MM <uri>'",
+						entry.trim()
+					))
+				}
+			};
+
+			let minute = parse_time(time).ok_or_else(|| format!("'{time}' is not like 'HH:MM'"))?;
+			let uri = parse_target(uri)?;
+			for day in days {
+				// At most 7, so can't be truncated
+				#[allow(clippy::cast_possible_truncation)]
+				entries.push((day as u32 * MINUTES_PER_DAY + minute, uri.clone()));
+			}
+		}
+
+		if entries.is_empty() {
+			return Err("there are no entries".to_string());
+		}
+		entries.sort_by_key(|(start, _)| *start);
+		Ok(Schedule { entries })
+	}
+}
+
+/// Parse a `HH:MM` time into minutes since midnight
+fn parse_time(time: &str) -> Option<u32> {
+	let (hour, minute) = time.split_once(':')?;
+	let hour: u32 = hour.parse().ok().filter(|hour| *hour < 24)?;
+	let minute: u32 = minute.parse().ok().filter(|minute| *minute < 60)?;
+	Some(hour * 60 + minute)
+}
+
+fn minute_of_week(time: UtcTime) -> u32 {
+	u32::from(time.weekday) * MINUTES_PER_DAY + u32::from(time.hour) * 60 + u32::from(time.minute)
+}
+
+/// Resolves paths that have a schedule to the URI for the current time
+pub struct ScheduleResolver {
+	schedules: HashMap<String, Schedule>,
+}
+
+impl ScheduleResolver {
+	/// Create a new `ScheduleResolver` from schedules keyed by path
+	pub fn new(schedules: HashMap<String, Schedule>) -> ScheduleResolver {
+		ScheduleResolver { schedules }
+	}
+}
+
+#[async_trait]
+impl Resolver for ScheduleResolver {
+	async fn resolve(&self, request: &ResolveRequest<'_>) -> Option<Resolution> {
+		let schedule = self.schedules.get(request.path.as_ref())?;
+		Some(Resolution::redirect(schedule.at(UtcTime::now()).clone()))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::str::FromStr;
+
+	use super::*;
+	use crate::targets::TargetPolicy;
+
+	fn time(weekday: u8, hour: u8, minute: u8) -> UtcTime {
+		UtcTime {
+			weekday,
+			hour,
+			minute,
+		}
+	}
+
+	#[test]
+	fn follow_schedules() {
+		let policy = TargetPolicy::default();
+		let parse = |s| Schedule::parse(s, |target| policy.parse(target));
+		let rota = parse(
+			"mon 09:00 https://example.com/alice, wed 09:00 https://example.com/bob, FRI 17:30 https://example.com/carol",
+		)
+		.unwrap();
+		let uri = |uri| Uri::from_str(uri).unwrap();
+
+		assert_eq!(rota.at(time(0, 9, 0)), &uri("https://example.com/alice"));
+		assert_eq!(rota.at(time(2, 8, 59)), &uri("https://example.com/alice"));
+		assert_eq!(rota.at(time(2, 9, 0)), &uri("https://example.com/bob"));
+		assert_eq!(rota.at(time(4, 17, 30)), &uri("https://example.com/carol"));
+		// The end of the week carries over into the start of the next one
+		assert_eq!(rota.at(time(0, 8, 0)), &uri("https://example.com/carol"));
+
+		// Entries without a day happen every day
+		let daily = parse("08:00 https://example.com/day,20:00 https://example.com/night").unwrap();
+		assert_eq!(daily.at(time(3, 12, 0)), &uri("https://example.com/day"));
+		assert_eq!(daily.at(time(3, 7, 0)), &uri("https://example.com/night"));
+		assert_eq!(daily.at(time(6, 23, 0)), &uri("https://example.com/night"));
+
+		assert!(parse("").is_err());
+		assert!(parse("someday 09:00 https://example.com/").is_err());
+		assert!(parse("mon 25:00 https://example.com/").is_err());
+		assert!(parse("mon 09:00").is_err());
+		assert!(parse("mon 09:00 javascript:alert(1)").is_err());
+	}
+}
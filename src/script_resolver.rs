@@ -0,0 +1,237 @@
+use std::{str::FromStr, sync::Arc};
+
+use async_trait::async_trait;
+use axum::http::{HeaderMap, Uri};
+use regex::Regex;
+use rhai::{Dynamic, Engine, EvalAltResult, Map, Scope, AST};
+
+use crate::{
+	clock::UtcTime,
+	log_limit::LogLimit,
+	resolver::{Resolution, ResolveRequest, Resolver},
+};
+
+/// The most operations a script can run for a single request
+const MAX_OPERATIONS: u64 = 100_000;
+/// The longest string a script can build
+const MAX_STRING_SIZE: usize = 8 * 1024;
+/// Scripts failing for requests, printed a few at a time
+static FAILURE_LOG: LogLimit = LogLimit::new("script failures");
+
+/// Resolves requests matching a regex by running a [Rhai](https://rhai.rs) script to build the URI.
+///
+/// Scripts can read `path`, `captures` (by name and index), `headers` (by lowercase name), and the
+/// current UTC `weekday` (0 is Monday), `hour`, and `minute`. They return the URI as a string, or
+/// `()` to pass the request on.
+#[derive(Clone)]
+pub struct ScriptResolver {
+	engine: Arc<Engine>,
+	scripts: Arc<Vec<(Regex, AST)>>,
+}
+
+impl ScriptResolver {
+	/// Compile the scripts, which are tried in order
+	pub fn new(scripts: Vec<(Regex, String)>) -> Result<ScriptResolver, String> {
+		let mut engine = Engine::new();
+		engine
+			.set_max_operations(MAX_OPERATIONS)
+			.set_max_string_size(MAX_STRING_SIZE)
+			.set_max_call_levels(32)
+			.set_max_expr_depths(64, 32);
+
+		let scripts = scripts
+			.into_iter()
+			.map(|(regex, script)| match engine.compile(&script) {
+				Ok(ast) => Ok((regex, ast)),
+				Err(error) => Err(format!("the script for '{regex}' has an error: {error}")),
+			})
+			.collect::<Result<_, _>>()?;
+
+		Ok(ScriptResolver {
+			engine: Arc::new(engine),
+			scripts: Arc::new(scripts),
+		})
+	}
+
+	/// Run the first script whose regex matches the request
+	fn run(
+		&self,
+		path: &str,
+		headers: &HeaderMap,
+		time: UtcTime,
+	) -> Result<Option<String>, Box<EvalAltResult>> {
+		let Some((regex, ast, captures)) = self
+			.scripts
+			.iter()
+			.find_map(|(regex, ast)| regex.captures(path).map(|captures| (regex, ast, captures)))
+		else {
+			return Ok(None);
+		};
+
+		let mut capture_map = Map::new();
+		for (index, capture) in captures.iter().enumerate() {
+			if let Some(capture) = capture {
+				capture_map.insert(index.to_string().into(), capture.as_str().into());
+			}
+		}
+		for name in regex.capture_names().flatten() {
+			if let Some(capture) = captures.name(name) {
+				capture_map.insert(name.into(), capture.as_str().into());
+			}
+		}
+
+		let mut header_map = Map::new();
+		for (name, value) in headers {
+			if let Ok(value) = value.to_str() {
+				header_map.insert(name.as_str().into(), value.into());
+			}
+		}
+
+		let mut scope = Scope::new();
+		scope
+			.push("path", path.to_string())
+			.push("captures", capture_map)
+			.push("headers", header_map)
+			.push("weekday", i64::from(time.weekday))
+			.push("hour", i64::from(time.hour))
+			.push("minute", i64::from(time.minute));
+
+		let result: Dynamic = self.engine.eval_ast_with_scope(&mut scope, ast)?;
+		if result.is_unit() {
+			return Ok(None);
+		}
+		Ok(Some(result.into_string().map_err(|kind| {
+			format!("the script returned a {kind} instead of a string")
+		})?))
+	}
+}
+
+#[async_trait]
+impl Resolver for ScriptResolver {
+	async fn resolve(&self, request: &ResolveRequest<'_>) -> Option<Resolution> {
+		if !self
+			.scripts
+			.iter()
+			.any(|(regex, _)| regex.is_match(&request.path))
+		{
+			return None;
+		}
+
+		// Scripts can run for all their operations, so they're kept off the threads serving
+		// requests
+		let scripts = self.clone();
+		let path = request.path.to_string();
+		let headers = request.headers.clone();
+		let time = UtcTime::now();
+		let ran = tokio::task::spawn_blocking(move || scripts.run(&path, &headers, time))
+			.await
+			.unwrap_or_else(|error| Err(error.to_string().into()));
+		let uri = match ran {
+			Ok(uri) => uri?,
+			Err(error) => {
+				FAILURE_LOG.print(&request.path, || {
+					format!("Script failed for '{}': {error}", request.path)
+				});
+				return None;
+			}
+		};
+
+		if let Ok(uri) = Uri::from_str(&uri) {
+			return Some(Resolution::redirect(uri));
+		}
+		FAILURE_LOG.print(&request.path, || {
+			format!("Script returned '{uri}', which is not a URI")
+		});
+		None
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{
+		borrow::Cow,
+		collections::HashMap,
+		net::{IpAddr, Ipv4Addr},
+	};
+
+	use axum::http::{HeaderMap, HeaderValue};
+
+	use super::*;
+	use crate::resolver::Target;
+
+	#[tokio::test]
+	async fn run_scripts() {
+		let resolver = ScriptResolver::new(vec![
+			(
+				Regex::new(r"^oncall$").unwrap(),
+				r#"if hour < 12 { "https://example.com/morning" } else { "https://example.com/evening" }"#
+					.to_string(),
+			),
+			(
+				Regex::new(r"^docs-(?P<page>\w+)$").unwrap(),
+				r#"let lang = headers["accept-language"] ?? "en"; `https://example.com/${lang}/${captures.page}`"#
+					.to_string(),
+			),
+			(Regex::new(r"^nothing$").unwrap(), "()".to_string()),
+			(Regex::new(r"^forever$").unwrap(), "loop {}".to_string()),
+		])
+		.unwrap();
+
+		let mut headers = HeaderMap::new();
+		let run = |path, headers: &HeaderMap, hour| {
+			let time = UtcTime {
+				weekday: 0,
+				hour,
+				minute: 0,
+			};
+			resolver.run(path, headers, time)
+		};
+
+		assert_eq!(
+			run("oncall", &headers, 9).unwrap().as_deref(),
+			Some("https://example.com/morning")
+		);
+		assert_eq!(
+			run("oncall", &headers, 18).unwrap().as_deref(),
+			Some("https://example.com/evening")
+		);
+		assert_eq!(
+			run("docs-setup", &headers, 0).unwrap().as_deref(),
+			Some("https://example.com/en/setup")
+		);
+		headers.insert("Accept-Language", HeaderValue::from_static("de"));
+		assert_eq!(
+			run("docs-setup", &headers, 0).unwrap().as_deref(),
+			Some("https://example.com/de/setup")
+		);
+
+		// Scripts can pass, and runaway scripts are stopped
+		assert_eq!(run("nothing", &headers, 0).unwrap(), None);
+		assert_eq!(run("unmatched", &headers, 0).unwrap(), None);
+		assert!(run("forever", &headers, 0).is_err());
+
+		let query = HashMap::new();
+		let request = |path| ResolveRequest {
+			path: Cow::Borrowed(path),
+			query: &query,
+			headers: &headers,
+			client: IpAddr::V4(Ipv4Addr::LOCALHOST),
+		};
+		assert_eq!(
+			resolver
+				.resolve(&request("docs-setup"))
+				.await
+				.map(|resolution| resolution.target),
+			Some(Target::Redirect(
+				Uri::from_str("https://example.com/de/setup").unwrap()
+			))
+		);
+		assert!(resolver.resolve(&request("forever")).await.is_none());
+		assert!(resolver.resolve(&request("unmatched")).await.is_none());
+
+		// Scripts that don't compile are caught when loading
+		assert!(
+			ScriptResolver::new(vec![(Regex::new("^a$").unwrap(), "if {".to_string())]).is_err()
+		);
+	}
+}
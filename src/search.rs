@@ -0,0 +1,61 @@
+/// How well the query matches the text, lower being better, if every character of the query is
+/// found in order. Matches that start earlier and are spread out less score better
+pub fn score(query: &str, text: &str) -> Option<usize> {
+	let text: Vec<char> = text.to_lowercase().chars().collect();
+	let mut start = None;
+	let mut position = 0;
+	for character in query.to_lowercase().chars() {
+		let found = text[position..]
+			.iter()
+			.position(|candidate| *candidate == character)?;
+		start.get_or_insert(position + found);
+		position += found + 1;
+	}
+
+	// An empty query matches everything equally
+	let start = start.unwrap_or(0);
+	let spread = position.saturating_sub(start);
+	Some(start + spread)
+}
+
+/// Find the keys that best match the query, best first. Shorter keys win ties, as they're closer
+/// to what was typed, and then they're sorted by name
+pub fn search<'a>(
+	query: &str,
+	keys: impl IntoIterator<Item = &'a str>,
+	limit: usize,
+) -> Vec<&'a str> {
+	let mut found: Vec<(usize, &str)> = keys
+		.into_iter()
+		.filter_map(|key| score(query, key).map(|score| (score, key)))
+		.collect();
+	found.sort_by(|(x_score, x), (y_score, y)| {
+		x_score
+			.cmp(y_score)
+			.then_with(|| x.len().cmp(&y.len()))
+			.then_with(|| x.cmp(y))
+	});
+	found.into_iter().take(limit).map(|(_, key)| key).collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn fuzzy_search() {
+		assert_eq!(score("dc", "docs"), Some(3));
+		assert_eq!(score("DOCS", "docs"), Some(4));
+		assert_eq!(score("sd", "docs"), None);
+		assert_eq!(score("", "docs"), Some(0));
+
+		let keys = ["docs", "team-docs", "dashboard-config", "chat", "do"];
+		assert_eq!(
+			search("doc", keys, 10),
+			vec!["docs", "team-docs", "dashboard-config"]
+		);
+		assert_eq!(search("dc", keys, 2), vec!["docs", "team-docs"]);
+		assert_eq!(search("", keys, 2), vec!["do", "chat"]);
+		assert!(search("zzz", keys, 10).is_empty());
+	}
+}
@@ -0,0 +1,84 @@
+use axum::http::Uri;
+
+use crate::{resolver::Target, uri_mappings::UriMappings};
+
+/// A candidate set of mappings that every request is also resolved against, so changes to them,
+/// like a big regex refactor, can be checked with real traffic before they go live
+pub struct Shadow {
+	candidate: UriMappings,
+}
+
+impl Shadow {
+	/// Create a new `Shadow` for the candidate mappings
+	pub fn new(candidate: UriMappings) -> Shadow {
+		Shadow { candidate }
+	}
+
+	/// Describe how the candidate would have resolved the path differently from the live
+	/// mappings, if it would have. Pages are left out, since the candidate can only redirect
+	pub fn compare(&self, path: &str, live: Option<&Target>) -> Option<String> {
+		let live = match live {
+			Some(Target::Redirect(uri)) => Some(uri),
+			Some(Target::Page(_)) => return None,
+			None => None,
+		};
+		let candidate = self
+			.candidate
+			.match_with_options(path)
+			.ok()
+			.map(|(uri, _)| uri);
+		let describe = |uri: Option<&Uri>| uri.map_or("nowhere".to_string(), ToString::to_string);
+		if candidate.as_ref() == live {
+			return None;
+		}
+		Some(format!(
+			"/{path} went to {}, but the candidate would send it to {}",
+			describe(live),
+			describe(candidate.as_ref())
+		))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{collections::HashMap, str::FromStr};
+
+	use regex::Regex;
+
+	use super::*;
+
+	#[test]
+	fn compare_candidate() {
+		let uri = |uri: &str| Uri::from_str(uri).unwrap();
+		let redirect = |target: &str| Target::Redirect(uri(target));
+		let shadow = Shadow::new(UriMappings::new(
+			HashMap::from([("docs".to_string(), uri("https://example.com/docs"))]),
+			vec![(
+				Regex::new(r"^issue-(\d+)$").unwrap(),
+				"https://example.com/issues/$1".to_string(),
+			)],
+		));
+
+		assert_eq!(
+			shadow.compare("docs", Some(&redirect("https://example.com/docs"))),
+			None
+		);
+		assert_eq!(shadow.compare("missing", None), None);
+		assert_eq!(
+			shadow.compare("issue-12", Some(&redirect("https://example.com/old/12"))),
+			Some(
+				"/issue-12 went to https://example.com/old/12, but the candidate would send it \
+				 to https://example.com/issues/12"
+					.to_string()
+			)
+		);
+		assert_eq!(
+			shadow.compare("blog", Some(&redirect("https://example.com/blog"))),
+			Some(
+				"/blog went to https://example.com/blog, but the candidate would send it to \
+				 nowhere"
+					.to_string()
+			)
+		);
+	}
+}
@@ -0,0 +1,124 @@
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+
+use serde::Serialize;
+
+/// How many keys are tracked, so paths made up by clients can't grow it forever
+const MAX_KEYS: usize = 1000;
+
+#[derive(Default)]
+struct KeyStats {
+	requests: u64,
+	errors: u64,
+	total: Duration,
+	slowest: Duration,
+}
+
+/// How a key has been doing since the instance started
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct KeyReport {
+	pub key: String,
+	pub requests: u64,
+	pub errors: u64,
+	/// The share of requests that were errors, from 0 to 1
+	pub error_rate: f64,
+	pub mean_ms: f64,
+	pub max_ms: f64,
+}
+
+/// The keys that are slowest on average, and the ones that error the most
+#[derive(Debug, Serialize)]
+pub struct SloSummary {
+	pub slowest: Vec<KeyReport>,
+	pub flakiest: Vec<KeyReport>,
+}
+
+/// Tracks how long each key takes to answer and how often it errors
+#[derive(Default)]
+pub struct Slo {
+	keys: Mutex<HashMap<String, KeyStats>>,
+}
+
+impl Slo {
+	/// Record a request for the key, with how long it took, not counting any delay
+	pub fn record(&self, key: &str, latency: Duration, error: bool) {
+		let mut keys = self.keys.lock().unwrap();
+		let stats = if let Some(stats) = keys.get_mut(key) {
+			stats
+		} else if keys.len() < MAX_KEYS {
+			keys.entry(key.to_string()).or_default()
+		} else {
+			return;
+		};
+		stats.requests += 1;
+		stats.errors += u64::from(error);
+		stats.total += latency;
+		stats.slowest = stats.slowest.max(latency);
+	}
+
+	/// How many keys are being tracked
+	pub fn tracked_keys(&self) -> usize {
+		self.keys.lock().unwrap().len()
+	}
+
+	/// Summarize the keys, with at most `limit` of each
+	pub fn summary(&self, limit: usize) -> SloSummary {
+		#[allow(clippy::cast_precision_loss)]
+		let reports: Vec<KeyReport> = self
+			.keys
+			.lock()
+			.unwrap()
+			.iter()
+			.map(|(key, stats)| KeyReport {
+				key: key.clone(),
+				requests: stats.requests,
+				errors: stats.errors,
+				error_rate: stats.errors as f64 / stats.requests as f64,
+				mean_ms: stats.total.as_secs_f64() * 1000.0 / stats.requests as f64,
+				max_ms: stats.slowest.as_secs_f64() * 1000.0,
+			})
+			.collect();
+
+		let mut flakiest: Vec<KeyReport> = reports
+			.iter()
+			.filter(|report| report.errors > 0)
+			.cloned()
+			.collect();
+		let mut slowest = reports;
+		slowest.sort_by(|a, b| b.mean_ms.total_cmp(&a.mean_ms).then(a.key.cmp(&b.key)));
+		slowest.truncate(limit);
+
+		flakiest.sort_by(|a, b| {
+			b.error_rate
+				.total_cmp(&a.error_rate)
+				.then(b.errors.cmp(&a.errors))
+				.then(a.key.cmp(&b.key))
+		});
+		flakiest.truncate(limit);
+
+		SloSummary { slowest, flakiest }
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn summarize_keys() {
+		let slo = Slo::default();
+		slo.record("fast", Duration::from_millis(1), false);
+		slo.record("slow", Duration::from_millis(30), false);
+		slo.record("slow", Duration::from_millis(10), true);
+		slo.record("flaky", Duration::from_millis(2), true);
+
+		let summary = slo.summary(2);
+		let keys = |reports: &[KeyReport]| -> Vec<String> {
+			reports.iter().map(|report| report.key.clone()).collect()
+		};
+		assert_eq!(keys(&summary.slowest), vec!["slow", "flaky"]);
+		assert_eq!(keys(&summary.flakiest), vec!["flaky", "slow"]);
+		assert!((summary.slowest[0].mean_ms - 20.0).abs() < f64::EPSILON);
+		assert!((summary.slowest[0].max_ms - 30.0).abs() < f64::EPSILON);
+		assert!((summary.flakiest[1].error_rate - 0.5).abs() < f64::EPSILON);
+	}
+}
@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+
+use axum::{
+	http::{header, StatusCode},
+	response::{IntoResponse, Response},
+};
+use rust_embed::RustEmbed;
+
+/// How long clients can cache files for. They're never changed, since their name changes instead
+const CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+
+#[derive(RustEmbed)]
+#[folder = "assets/static/"]
+struct Embedded;
+
+/// The files built in for pages to load, like style sheets. Each is served with a hash of
+/// its content in the name, so it can be cached forever
+pub struct StaticAssets {
+	/// Hashed names by the file's real name
+	hashed: HashMap<String, String>,
+	/// Real names by the file's hashed name
+	files: HashMap<String, String>,
+}
+
+impl Default for StaticAssets {
+	fn default() -> Self {
+		let mut hashed = HashMap::new();
+		let mut files = HashMap::new();
+		for name in Embedded::iter() {
+			let Some(file) = Embedded::get(&name) else {
+				continue;
+			};
+			let hash = hex::encode(&file.metadata.sha256_hash()[..8]);
+			let hashed_name = match name.rsplit_once('.') {
+				Some((stem, extension)) => format!("{stem}.{hash}.{extension}"),
+				None => format!("{name}.{hash}"),
+			};
+
+			hashed.insert(name.to_string(), hashed_name.clone());
+			files.insert(hashed_name, name.to_string());
+		}
+		StaticAssets { hashed, files }
+	}
+}
+
+impl StaticAssets {
+	/// Get the path to load the file from
+	pub fn path(&self, name: &str) -> String {
+		match self.hashed.get(name) {
+			Some(hashed_name) => format!("/static/{hashed_name}"),
+			None => format!("/static/{name}"),
+		}
+	}
+
+	/// Serve the file with the hashed name
+	pub fn response(&self, hashed_name: &str) -> Response {
+		let Some(file) = self
+			.files
+			.get(hashed_name)
+			.and_then(|name| Embedded::get(name))
+		else {
+			return StatusCode::NOT_FOUND.into_response();
+		};
+
+		let content_type = match hashed_name.rsplit_once('.').map(|(_, extension)| extension) {
+			Some("css") => "text/css; charset=utf-8",
+			Some("js") => "text/javascript; charset=utf-8",
+			Some("svg") => "image/svg+xml",
+			Some("png") => "image/png",
+			Some("ico") => "image/x-icon",
+			_ => "application/octet-stream",
+		};
+		(
+			[
+				(header::CONTENT_TYPE, content_type),
+				(header::CACHE_CONTROL, CACHE_CONTROL),
+			],
+			file.data,
+		)
+			.into_response()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn serve_hashed_assets() {
+		let assets = StaticAssets::default();
+		let path = assets.path("style.css");
+		let hashed_name = path.strip_prefix("/static/").unwrap();
+		let parts: Vec<&str> = hashed_name.split('.').collect();
+		assert_eq!(parts.len(), 3);
+		assert_eq!((parts[0], parts[1].len(), parts[2]), ("style", 16, "css"));
+
+		let response = assets.response(hashed_name);
+		assert_eq!(response.status(), StatusCode::OK);
+		assert_eq!(
+			response.headers()[header::CONTENT_TYPE],
+			"text/css; charset=utf-8"
+		);
+		assert_eq!(response.headers()[header::CACHE_CONTROL], CACHE_CONTROL);
+
+		// Only the hashed names are served, so old versions can't be cached under the new name
+		assert_eq!(assets.response("style.css").status(), StatusCode::NOT_FOUND);
+	}
+}
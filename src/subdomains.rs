@@ -0,0 +1,60 @@
+use axum::http::{header, HeaderMap};
+
+/// Find the key for a request made to a subdomain of the host, like `docs` for
+/// `docs.go.example.com` when the host is `go.example.com`
+pub fn subdomain_key(headers: &HeaderMap, host: &str) -> Option<String> {
+	let requested = headers.get(header::HOST)?.to_str().ok()?;
+	// Ports aren't part of the name
+	let requested = match requested.rsplit_once(':') {
+		Some((name, port)) if port.chars().all(|x| x.is_ascii_digit()) => name,
+		_ => requested,
+	};
+	let requested = requested.trim_end_matches('.').to_ascii_lowercase();
+
+	let key = requested.strip_suffix(host)?.strip_suffix('.')?;
+	// Only the label right below the host is a key
+	if key.is_empty() || key.contains('.') {
+		return None;
+	}
+	Some(key.to_string())
+}
+
+/// Normalize the host subdomains are under, so it can be compared to requested ones
+pub fn parse_host(host: &str) -> Result<String, String> {
+	let host = host.trim().trim_matches('.').to_ascii_lowercase();
+	let allowed = |x: char| x.is_ascii_alphanumeric() || x == '-' || x == '.';
+	if host.is_empty() || !host.chars().all(allowed) {
+		return Err(format!(
+			"'{host}' is not a host name, like 'go.example.com'"
+		));
+	}
+	Ok(host)
+}
+
+#[cfg(test)]
+mod tests {
+	use axum::http::HeaderValue;
+
+	use super::*;
+
+	#[test]
+	fn find_subdomain_key() {
+		let host = parse_host("Go.Example.com.").unwrap();
+		assert_eq!(host, "go.example.com");
+
+		let key = |requested: &'static str| {
+			let mut headers = HeaderMap::new();
+			headers.insert(header::HOST, HeaderValue::from_static(requested));
+			subdomain_key(&headers, &host)
+		};
+		assert_eq!(key("docs.go.example.com"), Some("docs".to_string()));
+		assert_eq!(key("Docs.go.example.com:8080"), Some("docs".to_string()));
+		assert_eq!(key("go.example.com"), None);
+		assert_eq!(key("a.docs.go.example.com"), None);
+		assert_eq!(key("docsgo.example.com"), None);
+		assert_eq!(key("docs.example.com"), None);
+		assert_eq!(subdomain_key(&HeaderMap::new(), &host), None);
+
+		assert!(parse_host("go.example.com/path").is_err());
+	}
+}
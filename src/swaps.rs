@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use axum::http::Uri;
+
+use crate::{
+	clock::unix_now,
+	resolver::{Resolution, ResolveRequest, Resolver, Target},
+	uri_mappings::LinkOptions,
+};
+
+/// A target that a path changes to at a set time
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Swap {
+	pub uri: Uri,
+	/// When to change to the target, in seconds since the Unix epoch
+	pub at: u64,
+}
+
+/// Resolves paths to their swapped target once it's time, and leaves them to the other
+/// resolvers until then
+pub struct SwapResolver {
+	swaps: HashMap<String, Swap>,
+	/// The options of the standard mappings, which still apply after they're swapped
+	options: HashMap<String, LinkOptions>,
+}
+
+impl SwapResolver {
+	/// Create a new `SwapResolver` from swaps keyed by path, with the options of the standard
+	/// mappings they replace
+	pub fn new(swaps: HashMap<String, Swap>, options: HashMap<String, LinkOptions>) -> Self {
+		SwapResolver { swaps, options }
+	}
+
+	/// Resolve the request as if the time was the number of seconds since the Unix epoch
+	fn resolve_at(&self, request: &ResolveRequest<'_>, now: u64) -> Option<Resolution> {
+		let swap = self.swaps.get(request.path.as_ref())?;
+		if now < swap.at {
+			return None;
+		}
+
+		let options = self
+			.options
+			.get(request.path.as_ref())
+			.cloned()
+			.unwrap_or_default();
+		if !options.allows(request.client) {
+			return None;
+		}
+		Some(Resolution {
+			target: Target::Redirect(swap.uri.clone()),
+			options,
+		})
+	}
+}
+
+#[async_trait]
+impl Resolver for SwapResolver {
+	async fn resolve(&self, request: &ResolveRequest<'_>) -> Option<Resolution> {
+		self.resolve_at(request, unix_now())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{
+		borrow::Cow,
+		net::{IpAddr, Ipv4Addr},
+		str::FromStr,
+	};
+
+	use axum::http::HeaderMap;
+
+	use super::*;
+
+	#[test]
+	fn swap_at_time() {
+		let live = Uri::from_str("https://example.com/live").unwrap();
+		let resolver = SwapResolver::new(
+			HashMap::from([(
+				"launch".to_string(),
+				Swap {
+					uri: live.clone(),
+					at: 1000,
+				},
+			)]),
+			HashMap::new(),
+		);
+		let query = HashMap::new();
+		let headers = HeaderMap::new();
+		let request = |path| ResolveRequest {
+			path: Cow::Borrowed(path),
+			query: &query,
+			headers: &headers,
+			client: IpAddr::V4(Ipv4Addr::LOCALHOST),
+		};
+
+		assert_eq!(resolver.resolve_at(&request("launch"), 999), None);
+		assert_eq!(
+			resolver
+				.resolve_at(&request("launch"), 1000)
+				.map(|x| x.target),
+			Some(Target::Redirect(live))
+		);
+		assert_eq!(resolver.resolve_at(&request("other"), 1000), None);
+	}
+}
@@ -0,0 +1,124 @@
+use std::str::FromStr;
+
+use axum::http::Uri;
+use reqwest::Url;
+
+/// The schemes links can redirect to when none are set
+pub const DEFAULT_SCHEMES: [&str; 2] = ["http", "https"];
+
+/// Decides which targets links are allowed to redirect to, so a mapping can't send clients
+/// somewhere dangerous like a `javascript:` URI
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TargetPolicy {
+	schemes: Vec<String>,
+	/// What relative targets are resolved against
+	base: Option<Url>,
+}
+
+impl Default for TargetPolicy {
+	fn default() -> Self {
+		TargetPolicy::new(DEFAULT_SCHEMES.iter().map(ToString::to_string).collect())
+	}
+}
+
+impl TargetPolicy {
+	/// Create a new `TargetPolicy` allowing the schemes
+	pub fn new(schemes: Vec<String>) -> TargetPolicy {
+		TargetPolicy {
+			schemes: schemes
+				.into_iter()
+				.map(|scheme| scheme.trim().to_ascii_lowercase())
+				.collect(),
+			base: None,
+		}
+	}
+
+	/// Resolve relative targets, like `/docs/setup`, against the base
+	pub fn with_base(mut self, base: &str) -> Result<TargetPolicy, String> {
+		let uri = self.parse(base)?;
+		self.base = Some(Url::parse(&uri.to_string()).map_err(|error| error.to_string())?);
+		Ok(self)
+	}
+
+	/// Parse a target, converting international host names to punycode
+	pub fn parse(&self, target: &str) -> Result<Uri, String> {
+		let url = Url::options()
+			.base_url(self.base.as_ref())
+			.parse(target.trim())
+			.map_err(|error| format!("'{target}' is not a valid URI ({error})"))?;
+		if url.cannot_be_a_base() {
+			return Err(format!("'{target}' is not a URI that can be redirected to"));
+		}
+		let uri =
+			Uri::from_str(url.as_str()).map_err(|_| format!("'{target}' is not a valid URI"))?;
+		match uri.scheme_str() {
+			Some(scheme) if self.schemes.iter().any(|allowed| allowed == scheme) => Ok(uri),
+			Some(scheme) => Err(format!("the '{scheme}' scheme is not allowed")),
+			None => Err(format!("'{uri}' has no scheme")),
+		}
+	}
+
+	/// Check that a target built while resolving can be redirected to, resolving it against the
+	/// base if it's relative
+	pub fn resolve(&self, uri: &Uri) -> Result<Uri, String> {
+		match (uri.scheme_str(), &self.base) {
+			// Most targets are already absolute, and only need their scheme checked
+			(Some(scheme), _) if self.schemes.iter().any(|allowed| allowed == scheme) => {
+				Ok(uri.clone())
+			}
+			(None, None) => Err(format!("'{uri}' has no scheme")),
+			_ => self.parse(&uri.to_string()),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn check_targets() {
+		let policy = TargetPolicy::default();
+		assert_eq!(
+			policy.parse("https://Bücher.example/straße").unwrap(),
+			Uri::from_str("https://xn--bcher-kva.example/stra%C3%9Fe").unwrap()
+		);
+		assert!(policy.parse("javascript:alert(1)").is_err());
+		assert!(policy.parse("javascript://%0aalert(1)").is_err());
+		assert!(policy.parse("data:text/html,<script>").is_err());
+		assert!(policy.parse("ftp://example.com/file").is_err());
+		assert!(policy.parse("/relative").is_err());
+
+		assert!(policy
+			.resolve(&Uri::from_str("javascript://example.com/").unwrap())
+			.is_err());
+		assert!(policy
+			.resolve(&Uri::from_str("/relative").unwrap())
+			.is_err());
+
+		let policy = TargetPolicy::new(vec!["https".to_string(), " FTP".to_string()]);
+		assert!(policy.parse("ftp://example.com/file").is_ok());
+		assert!(policy.parse("http://example.com/").is_err());
+
+		// Relative targets use the base
+		let policy = TargetPolicy::default()
+			.with_base("https://example.com/site/")
+			.unwrap();
+		assert_eq!(
+			policy.parse("/docs/setup").unwrap(),
+			Uri::from_str("https://example.com/docs/setup").unwrap()
+		);
+		assert_eq!(
+			policy.parse("docs").unwrap(),
+			Uri::from_str("https://example.com/site/docs").unwrap()
+		);
+		assert_eq!(
+			policy.resolve(&Uri::from_str("/docs/1").unwrap()).unwrap(),
+			Uri::from_str("https://example.com/docs/1").unwrap()
+		);
+		assert!(policy.parse("javascript://example.com/").is_err());
+		assert!(TargetPolicy::default()
+			.with_base("javascript://x/")
+			.is_err());
+	}
+}
@@ -0,0 +1,108 @@
+use crate::html::{escape, fill};
+
+/// How the built in pages look, so they can be branded without replacing them
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Theme {
+	pub text: String,
+	pub background: String,
+	pub accent: String,
+	/// Colors used instead when the client prefers a dark color scheme
+	pub dark_text: String,
+	pub dark_background: String,
+	pub dark_accent: String,
+	/// Where to load a logo from, shown at the top of every page
+	pub logo: Option<String>,
+	/// Text shown at the bottom of every page
+	pub footer: Option<String>,
+}
+
+impl Default for Theme {
+	fn default() -> Self {
+		Theme {
+			text: "#444".to_string(),
+			background: "#fff".to_string(),
+			accent: "#0645ad".to_string(),
+			dark_text: "#ddd".to_string(),
+			dark_background: "#1e1e1e".to_string(),
+			dark_accent: "#8ab4f8".to_string(),
+			logo: None,
+			footer: None,
+		}
+	}
+}
+
+impl Theme {
+	/// Place the content inside of the site's page template. The title and content must already
+	/// be escaped
+	pub fn render(&self, language: &str, title: &str, content: &str, stylesheet: &str) -> String {
+		let header = self.logo.as_ref().map_or(String::new(), |logo| {
+			format!(
+				"\t<header><img class=\"logo\" src=\"{}\" alt=\"\"></header>",
+				escape(logo)
+			)
+		});
+		let footer = self.footer.as_ref().map_or(String::new(), |footer| {
+			format!("\t<footer>{}</footer>", escape(footer))
+		});
+
+		fill(
+			std::include_str!("../assets/page.html"),
+			&[
+				("language", &escape(language)),
+				("title", title),
+				("stylesheet", &escape(stylesheet)),
+				("text", &self.text),
+				("background", &self.background),
+				("accent", &self.accent),
+				("dark-text", &self.dark_text),
+				("dark-background", &self.dark_background),
+				("dark-accent", &self.dark_accent),
+				("header", &header),
+				("content", content),
+				("footer", &footer),
+			],
+		)
+	}
+}
+
+/// Check that a color can't break out of the style sheet it's placed in
+pub fn parse_color(color: &str) -> Result<String, String> {
+	let color = color.trim();
+	let allowed = |x: char| x.is_ascii_alphanumeric() || "#(),.% ".contains(x);
+	if color.is_empty() || !color.chars().all(allowed) {
+		return Err(format!(
+			"'{color}' is not a color, like '#336699' or 'teal'"
+		));
+	}
+	Ok(color.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn render_theme() {
+		let page = Theme::default().render("de", "Title", "\t<p>Content</p>", "/static/style.css");
+		assert!(page.contains("<html lang=\"de\">"));
+		assert!(page.contains("<title>Title</title>"));
+		assert!(page.contains("<link rel=\"stylesheet\" href=\"/static/style.css\">"));
+		assert!(page.contains("<main>\n\t<p>Content</p>\n\t</main>"));
+
+		let theme = Theme {
+			accent: parse_color("rgb(10, 20, 30)").unwrap(),
+			logo: Some("https://example.com/logo.png?a&b".to_string()),
+			footer: Some("Run by <IT>".to_string()),
+			..Theme::default()
+		};
+		let page = theme.render("en", "Title", "", "/static/style.css");
+		assert!(page.contains("--accent: rgb(10, 20, 30);"));
+		assert!(page.contains("<img class=\"logo\" src=\"https://example.com/logo.png?a&amp;b\""));
+		assert!(page.contains("<footer>Run by &lt;IT&gt;</footer>"));
+
+		assert!(parse_color("#336699").is_ok());
+		assert!(parse_color("red; } body { display: none").is_err());
+		assert!(parse_color("</style>").is_err());
+		assert!(parse_color("").is_err());
+	}
+}
@@ -0,0 +1,196 @@
+use std::{
+	collections::HashMap,
+	str::FromStr,
+	sync::Mutex,
+	time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use axum::http::{header, Uri};
+use reqwest::{redirect, Client, Url};
+
+use crate::{
+	log_limit::LogLimit,
+	resolver::{Resolution, ResolveRequest, Resolver},
+};
+
+/// How long to wait on the upstream before giving up
+const UPSTREAM_TIMEOUT: Duration = Duration::from_secs(5);
+/// The most answers to remember, so random paths can't use up all the memory
+const MAX_CACHED_ANSWERS: usize = 10_000;
+/// Paths the upstream failed to resolve, printed a few at a time
+static FAILURE_LOG: LogLimit = LogLimit::new("upstream failures");
+
+/// What the upstream said about a path
+#[derive(Clone)]
+enum Answer {
+	Redirect(Uri),
+	Missing,
+}
+
+/// Asks another URL shortener about paths that aren't mapped locally, and remembers its answers
+pub struct UpstreamResolver {
+	base: Url,
+	client: Client,
+	cache_duration: Duration,
+	cache: Mutex<HashMap<String, (Instant, Answer)>>,
+}
+
+impl UpstreamResolver {
+	/// Create a new `UpstreamResolver` for the shortener at the base URL
+	pub fn new(base: Url, cache_duration: Duration) -> Result<UpstreamResolver, String> {
+		if base.cannot_be_a_base() {
+			return Err(format!("'{base}' can't be used as a base URL"));
+		}
+
+		let client = Client::builder()
+			.redirect(redirect::Policy::none())
+			.timeout(UPSTREAM_TIMEOUT)
+			.build()
+			.map_err(|error| error.to_string())?;
+
+		Ok(UpstreamResolver {
+			base,
+			client,
+			cache_duration,
+			cache: Mutex::new(HashMap::new()),
+		})
+	}
+
+	/// Get the remembered answer for the path, if it hasn't expired
+	fn cached(&self, path: &str, now: Instant) -> Option<Answer> {
+		let cache = self.cache.lock().unwrap();
+		match cache.get(path) {
+			Some((expires, answer)) if *expires > now => Some(answer.clone()),
+			_ => None,
+		}
+	}
+
+	/// Remember the answer for the path
+	fn remember(&self, path: &str, answer: Answer, now: Instant) {
+		let mut cache = self.cache.lock().unwrap();
+		if cache.len() >= MAX_CACHED_ANSWERS {
+			cache.retain(|_, (expires, _)| *expires > now);
+			if cache.len() >= MAX_CACHED_ANSWERS {
+				cache.clear();
+			}
+		}
+		cache.insert(path.to_string(), (now + self.cache_duration, answer));
+	}
+
+	/// Ask the upstream where the path redirects to. Errors are `Err` so they aren't remembered
+	async fn ask(&self, path: &str) -> Result<Answer, reqwest::Error> {
+		let mut url = self.base.clone();
+		url.path_segments_mut()
+			.expect("base was checked when created")
+			.pop_if_empty()
+			.push(path);
+
+		let response = self.client.get(url).send().await?;
+		if !response.status().is_redirection() {
+			return Ok(Answer::Missing);
+		}
+
+		Ok(response
+			.headers()
+			.get(header::LOCATION)
+			.and_then(|location| location.to_str().ok())
+			.and_then(|location| Uri::from_str(location).ok())
+			.map_or(Answer::Missing, Answer::Redirect))
+	}
+}
+
+#[async_trait]
+impl Resolver for UpstreamResolver {
+	async fn resolve(&self, request: &ResolveRequest<'_>) -> Option<Resolution> {
+		let answer = match self.cached(&request.path, Instant::now()) {
+			Some(answer) => answer,
+			None => match self.ask(&request.path).await {
+				Ok(answer) => {
+					self.remember(&request.path, answer.clone(), Instant::now());
+					answer
+				}
+				Err(error) => {
+					FAILURE_LOG.print(&request.path, || {
+						format!("Upstream failed to resolve '{}': {error}", request.path)
+					});
+					Answer::Missing
+				}
+			},
+		};
+
+		match answer {
+			Answer::Redirect(uri) => Some(Resolution::redirect(uri)),
+			Answer::Missing => None,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{
+		borrow::Cow,
+		net::{IpAddr, Ipv4Addr, SocketAddr},
+		sync::{
+			atomic::{AtomicUsize, Ordering},
+			Arc,
+		},
+	};
+
+	use axum::{extract::Path, http::HeaderMap, response::Redirect, routing::get, Router};
+
+	use super::*;
+	use crate::resolver::Target;
+
+	#[tokio::test]
+	async fn resolve_from_upstream() {
+		// A tiny upstream that only knows about `known`, counting how often it is asked
+		let asked = Arc::new(AtomicUsize::new(0));
+		let upstream_asked = asked.clone();
+		let app = Router::new().route(
+			"/:parameter",
+			get(move |Path(parameter): Path<String>| {
+				upstream_asked.fetch_add(1, Ordering::SeqCst);
+				async move {
+					match parameter.as_str() {
+						"known" => Ok(Redirect::temporary("https://example.com/known")),
+						_ => Err("Not found"),
+					}
+				}
+			}),
+		);
+		let server = axum::Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0)))
+			.serve(app.into_make_service());
+		let address = server.local_addr();
+		tokio::spawn(server);
+
+		let upstream = UpstreamResolver::new(
+			Url::parse(&format!("http://{address}/")).unwrap(),
+			Duration::from_mins(1),
+		)
+		.unwrap();
+		let query = HashMap::new();
+		let headers = HeaderMap::new();
+		let request = |path| ResolveRequest {
+			path: Cow::Borrowed(path),
+			query: &query,
+			headers: &headers,
+			client: IpAddr::V4(Ipv4Addr::LOCALHOST),
+		};
+
+		let resolved = upstream.resolve(&request("known")).await;
+		assert_eq!(
+			resolved.map(|x| x.target),
+			Some(Target::Redirect(
+				Uri::from_str("https://example.com/known").unwrap()
+			))
+		);
+		assert!(upstream.resolve(&request("unknown")).await.is_none());
+		assert_eq!(asked.load(Ordering::SeqCst), 2);
+
+		// Answers are remembered, including misses
+		assert!(upstream.resolve(&request("known")).await.is_some());
+		assert!(upstream.resolve(&request("unknown")).await.is_none());
+		assert_eq!(asked.load(Ordering::SeqCst), 2);
+	}
+}
@@ -1,18 +1,126 @@
-use std::{collections::HashMap, str::FromStr};
+use std::{
+	collections::HashMap,
+	net::IpAddr,
+	str::FromStr,
+	time::{Duration, Instant},
+};
 
-use axum::http::Uri;
+use async_trait::async_trait;
+use axum::http::{HeaderValue, StatusCode, Uri};
 use regex::Regex;
 
+use crate::{
+	auth_log::{log_auth_failure, AuthFailure},
+	ip_ranges::IpRanges,
+	pattern_guard::PatternGuard,
+	resolver::{Resolution, ResolveRequest, Resolver, Target},
+	signing::{Signer, SIGNED_TOKEN_QUERY_NAME},
+};
+
 /// Contains the mapping of URIs to redirect to
 pub struct UriMappings {
 	pub standard: HashMap<String, Uri>,
 	pub pattern: Vec<(Regex, String)>,
+	pub standard_options: HashMap<String, LinkOptions>,
+	pub pattern_options: HashMap<usize, LinkOptions>,
+	pub private: HashMap<String, Uri>,
+	/// Internal notes about the standard and pattern mappings, only shown to admins
+	pub standard_notes: HashMap<String, String>,
+	pub pattern_notes: HashMap<usize, String>,
+	signer: Option<Signer>,
+	/// Times the patterns as they match, if slow ones should be skipped
+	guard: Option<PatternGuard>,
+}
+
+/// Extra settings that can be attached to an individual mapping
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LinkOptions {
+	/// How long to wait before sending the redirect
+	pub delay: Option<Duration>,
+	/// The only clients allowed to use the mapping, if set
+	pub allowed: Option<IpRanges>,
+	/// Redirect requests of every method, not just `GET`, so signed fetches and posts follow it
+	pub passthrough: bool,
+	/// The status to redirect with, instead of `307 Temporary Redirect`
+	pub status: Option<StatusCode>,
+	/// Fetch the target and send it back, instead of redirecting to it
+	pub proxy: bool,
+	/// How much the target is told about where the client came from, if it's limited
+	pub referrer_policy: Option<HeaderValue>,
+}
+
+impl LinkOptions {
+	/// The status to redirect with
+	pub fn redirect_status(&self) -> StatusCode {
+		self.status.unwrap_or(StatusCode::TEMPORARY_REDIRECT)
+	}
+
+	/// Check if the client is allowed to use the mapping
+	pub fn allows(&self, client: IpAddr) -> bool {
+		match &self.allowed {
+			Some(allowed) => allowed.contains(client),
+			None => true,
+		}
+	}
 }
 
 impl UriMappings {
 	/// Create a new empty `UriMappings`
 	pub fn new(standard: HashMap<String, Uri>, pattern: Vec<(Regex, String)>) -> UriMappings {
-		UriMappings { standard, pattern }
+		UriMappings {
+			standard,
+			pattern,
+			standard_options: HashMap::new(),
+			pattern_options: HashMap::new(),
+			private: HashMap::new(),
+			standard_notes: HashMap::new(),
+			pattern_notes: HashMap::new(),
+			signer: None,
+			guard: None,
+		}
+	}
+
+	/// Add private URIs, which only match when given a token signed for them
+	pub fn with_private(mut self, private: HashMap<String, Uri>, signer: Signer) -> UriMappings {
+		self.private = private;
+		self.signer = Some(signer);
+		self
+	}
+
+	/// Attach options to the standard and pattern mappings with the matching path or place
+	pub fn with_options(
+		mut self,
+		standard_options: HashMap<String, LinkOptions>,
+		pattern_options: HashMap<usize, LinkOptions>,
+	) -> UriMappings {
+		self.standard_options = standard_options;
+		self.pattern_options = pattern_options;
+		self
+	}
+
+	/// Attach notes to the standard and pattern mappings with the matching path or place
+	pub fn with_notes(
+		mut self,
+		standard_notes: HashMap<String, String>,
+		pattern_notes: HashMap<usize, String>,
+	) -> UriMappings {
+		self.standard_notes = standard_notes;
+		self.pattern_notes = pattern_notes;
+		self
+	}
+
+	/// Skip patterns that take longer than the budget to match `strikes` times in a row
+	pub fn with_pattern_guard(mut self, budget: Duration, strikes: usize) -> UriMappings {
+		self.guard = Some(PatternGuard::new(self.pattern.len(), budget, strikes));
+		self
+	}
+
+	/// Check if the pattern in the place has been skipped for being too slow
+	#[cfg(feature = "admin")]
+	pub fn is_quarantined(&self, place: usize) -> bool {
+		self.guard
+			.as_ref()
+			.is_some_and(|guard| guard.is_quarantined(place))
 	}
 
 	/// Match standard URIs from the collection
@@ -23,17 +131,41 @@ impl UriMappings {
 		}
 	}
 
+	/// Match private URIs from the collection, if the token is valid for the parameter
+	pub fn match_private(&self, parameter: &str, token: &str) -> Result<Uri, &str> {
+		match (&self.signer, self.private.get(parameter)) {
+			(Some(signer), Some(x)) if signer.verify(parameter, token) => Ok(x.clone()),
+			_ => Err("No private found"),
+		}
+	}
+
 	/// Match pattern URIs from the collection
+	#[cfg(test)]
 	pub fn match_pattern(&self, parameter: &str) -> Result<Uri, &str> {
-		for (regex, uri_pattern) in &self.pattern {
-			if !regex.is_match(parameter) {
+		self.match_pattern_with_place(parameter).map(|(_, uri)| uri)
+	}
+
+	/// Match pattern URIs from the collection, including the place of the pattern that matched
+	fn match_pattern_with_place(&self, parameter: &str) -> Result<(usize, Uri), &str> {
+		for (place, (regex, uri_pattern)) in self.pattern.iter().enumerate() {
+			let matched = match &self.guard {
+				Some(guard) if guard.is_quarantined(place) => false,
+				Some(guard) => {
+					let start = Instant::now();
+					let matched = regex.is_match(parameter);
+					guard.record(place, regex, start.elapsed());
+					matched
+				}
+				None => regex.is_match(parameter),
+			};
+			if !matched {
 				continue;
 			}
 
 			let replacement = regex.replace(parameter, uri_pattern);
 
 			return match Uri::from_str(&replacement) {
-				Ok(new_uri) => Ok(new_uri),
+				Ok(new_uri) => Ok((place, new_uri)),
 				Err(_) => Err("Pattern did not create URI"),
 			};
 		}
@@ -43,11 +175,52 @@ impl UriMappings {
 
 	/// Match both standard and pattern URIs from the collection.
 	/// Standard URIs will match before patterns
+	#[cfg(test)]
 	pub fn match_anything(&self, parameter: &str) -> Result<Uri, &str> {
-		match self.match_standard(parameter) {
-			Ok(standard) => Ok(standard),
-			Err(_) => self.match_pattern(parameter),
+		self.match_with_options(parameter).map(|(uri, _)| uri)
+	}
+
+	/// Match both standard and pattern URIs from the collection, along with the options of the
+	/// mapping that matched. Standard URIs will match before patterns
+	pub fn match_with_options(&self, parameter: &str) -> Result<(Uri, LinkOptions), &str> {
+		if let Ok(standard) = self.match_standard(parameter) {
+			let options = self.standard_options.get(parameter).cloned();
+			return Ok((standard, options.unwrap_or_default()));
 		}
+
+		let (place, pattern) = self.match_pattern_with_place(parameter)?;
+		let options = self.pattern_options.get(&place).cloned();
+		Ok((pattern, options.unwrap_or_default()))
+	}
+}
+
+#[async_trait]
+impl Resolver for UriMappings {
+	/// Resolve private URIs when there is a token, then any standard and pattern URIs the client
+	/// is allowed to use
+	async fn resolve(&self, request: &ResolveRequest<'_>) -> Option<Resolution> {
+		if let Some(token) = request.query.get(SIGNED_TOKEN_QUERY_NAME) {
+			if let Ok(uri) = self.match_private(&request.path, token) {
+				return Some(Resolution::redirect(uri));
+			}
+			// Only private links are logged, as tokens on other links are just unused
+			if self.private.contains_key(request.path.as_ref()) {
+				log_auth_failure(
+					AuthFailure::LinkSignature,
+					request.client,
+					&format!("/{}", request.path),
+				);
+			}
+		}
+
+		// Restricted mappings are treated as missing for everyone else
+		self.match_with_options(&request.path)
+			.ok()
+			.filter(|(_, options)| options.allows(request.client))
+			.map(|(uri, options)| Resolution {
+				target: Target::Redirect(uri),
+				options,
+			})
 	}
 }
 
@@ -159,4 +332,97 @@ mod tests {
 
 		Ok(())
 	}
+
+	#[test]
+	fn match_with_options() -> Result<(), InvalidUri> {
+		let standard = HashMap::from([
+			(
+				"slow".to_string(),
+				Uri::from_str("https://example.com/slow")?,
+			),
+			(
+				"fast".to_string(),
+				Uri::from_str("https://example.com/fast")?,
+			),
+		]);
+		let pattern = vec![(
+			Regex::new(r"^i(?P<index>\d+)$").unwrap(),
+			"https://example.com/$index".to_string(),
+		)];
+		let slow = LinkOptions {
+			delay: Some(Duration::from_millis(500)),
+			..LinkOptions::default()
+		};
+		let uri_mappings = UriMappings::new(standard, pattern).with_options(
+			HashMap::from([("slow".to_string(), slow.clone())]),
+			HashMap::from([(0, slow.clone())]),
+		);
+
+		// Options follow the mapping that matched
+		assert_eq!(
+			uri_mappings.match_with_options("slow").unwrap(),
+			(Uri::from_str("https://example.com/slow")?, slow.clone())
+		);
+		assert_eq!(
+			uri_mappings.match_with_options("i7").unwrap(),
+			(Uri::from_str("https://example.com/7")?, slow)
+		);
+
+		// Mappings without options use the defaults
+		assert_eq!(
+			uri_mappings.match_with_options("fast").unwrap(),
+			(
+				Uri::from_str("https://example.com/fast")?,
+				LinkOptions::default()
+			)
+		);
+
+		assert!(uri_mappings.match_with_options("missing").is_err());
+
+		// Redirects are temporary unless the mapping says otherwise
+		assert_eq!(
+			LinkOptions::default().redirect_status(),
+			StatusCode::TEMPORARY_REDIRECT
+		);
+		let permanent = LinkOptions {
+			status: Some(StatusCode::PERMANENT_REDIRECT),
+			..LinkOptions::default()
+		};
+		assert_eq!(permanent.redirect_status(), StatusCode::PERMANENT_REDIRECT);
+
+		Ok(())
+	}
+
+	#[test]
+	fn redirect_private_uris() -> Result<(), InvalidUri> {
+		let signer = Signer::new(b"secret");
+		let private = HashMap::from([(
+			"internal".to_string(),
+			Uri::from_str("https://example.com/internal")?,
+		)]);
+		let uri_mappings =
+			UriMappings::new(HashMap::new(), Vec::new()).with_private(private, signer.clone());
+
+		// Private URIs need a valid token
+		assert!(uri_mappings.match_private("internal", "").is_err());
+		assert!(uri_mappings
+			.match_private("internal", &signer.sign("other"))
+			.is_err());
+		assert_eq!(
+			uri_mappings
+				.match_private("internal", &signer.sign("internal"))
+				.unwrap(),
+			Uri::from_str("https://example.com/internal")?
+		);
+
+		// A valid token for a path that isn't private does nothing
+		assert!(uri_mappings
+			.match_private("missing", &signer.sign("missing"))
+			.is_err());
+
+		// Private URIs are never matched normally
+		assert!(uri_mappings.match_with_options("internal").is_err());
+
+		Ok(())
+	}
 }
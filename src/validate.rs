@@ -0,0 +1,199 @@
+use std::{collections::HashSet, str::FromStr, sync::Arc};
+
+use axum::http::Uri;
+use regex::Regex;
+use serde::Serialize;
+
+use crate::uri_mappings::UriMappings;
+
+/// Paths that are taken by routes of the instance itself, when they're enabled
+const RESERVED_KEYS: [&str; 2] = ["api", "metrics"];
+
+/// A problem with a proposed mapping
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Conflict {
+	/// What kind of problem it is, like `duplicate`, `reserved`, `shadowed`, or `invalid`
+	pub kind: &'static str,
+	/// The variable with the problem
+	pub name: String,
+	pub message: String,
+}
+
+/// Checks proposed mappings against the live ones, so CI can catch conflicts before they're merged
+pub struct Validator {
+	live: Arc<UriMappings>,
+	standard_prefix: &'static str,
+	pattern_prefix: &'static str,
+}
+
+impl Validator {
+	/// Create a new `Validator` for the live mappings, with the prefixes of the standard URI and
+	/// pattern regex variables
+	pub fn new(
+		live: Arc<UriMappings>,
+		standard_prefix: &'static str,
+		pattern_prefix: &'static str,
+	) -> Validator {
+		Validator {
+			live,
+			standard_prefix,
+			pattern_prefix,
+		}
+	}
+
+	/// Find the conflicts in the text of a `.env` file, as if it was added to the live mappings
+	pub fn validate(&self, proposed: &str) -> Result<Vec<Conflict>, String> {
+		let mut conflicts = Vec::new();
+		let mut conflict = |kind, name: &str, message| {
+			conflicts.push(Conflict {
+				kind,
+				name: name.to_string(),
+				message,
+			});
+		};
+
+		let mut seen = HashSet::new();
+		let mut keys: Vec<&str> = self.live.standard.keys().map(String::as_str).collect();
+		let mut patterns = Vec::new();
+		let vars = parse_env_text(proposed)?;
+		for (name, value) in &vars {
+			if !seen.insert(name) {
+				conflict("duplicate", name, format!("'{name}' is set more than once"));
+				continue;
+			}
+
+			if let Some(key) = name.strip_prefix(self.standard_prefix) {
+				if RESERVED_KEYS.contains(&key) {
+					conflict(
+						"reserved",
+						name,
+						format!("'/{key}' is used by URShort itself"),
+					);
+				}
+				match (self.live.standard.get(key), Uri::from_str(value)) {
+					(Some(live), Ok(uri)) if *live != uri => conflict(
+						"duplicate",
+						name,
+						format!("'{key}' already redirects to {live}"),
+					),
+					(_, Err(error)) => conflict(
+						"invalid",
+						name,
+						format!("'{value}' is not a valid URI ({error})"),
+					),
+					_ => {}
+				}
+				keys.push(key);
+			} else if name.starts_with(self.pattern_prefix) {
+				match Regex::new(value) {
+					Ok(regex) => patterns.push((name, regex)),
+					Err(error) => conflict(
+						"invalid",
+						name,
+						format!("'{value}' is not a regex ({error})"),
+					),
+				}
+			}
+		}
+
+		// Standard mappings are matched before patterns, so a pattern never gets their keys
+		keys.sort_unstable();
+		keys.dedup();
+		for (name, regex) in patterns {
+			for key in keys.iter().filter(|key| regex.is_match(key)) {
+				conflict(
+					"shadowed",
+					name,
+					format!("'/{key}' matches, but always goes to the standard mapping"),
+				);
+			}
+		}
+		Ok(conflicts)
+	}
+}
+
+/// Read the variables out of the text of a `.env` file, in order and including any that are set
+/// more than once. Only quotes are removed from the values, variables in them aren't expanded
+fn parse_env_text(text: &str) -> Result<Vec<(String, String)>, String> {
+	text.lines()
+		.enumerate()
+		.map(|(index, line)| (index, line.trim()))
+		.filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+		.map(|(index, line)| {
+			let line = line.strip_prefix("export ").unwrap_or(line);
+			let (name, value) = line
+				.split_once('=')
+				.filter(|(name, _)| {
+					let name = name.trim();
+					!name.is_empty()
+						&& name
+							.chars()
+							.all(|x| x.is_ascii_alphanumeric() || x == '_' || x == '.')
+				})
+				.ok_or(format!(
+					"Line {} is not a variable, like 'NAME=value'",
+					index + 1
+				))?;
+			let value = value.trim();
+			let value = ['\'', '"']
+				.iter()
+				.find_map(|quote| value.strip_prefix(*quote)?.strip_suffix(*quote))
+				.unwrap_or(value);
+			Ok((name.trim().to_string(), value.to_string()))
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::HashMap;
+
+	use super::*;
+
+	#[test]
+	fn find_conflicts() {
+		let live = UriMappings::new(
+			HashMap::from([(
+				"docs".to_string(),
+				Uri::from_str("https://example.com/docs").unwrap(),
+			)]),
+			Vec::new(),
+		);
+		let validator = Validator::new(
+			Arc::new(live),
+			"URSHORT_STANDARD_URI_",
+			"URSHORT_PATTERN_REGEX_",
+		);
+		let kinds = |proposed: &str| -> Vec<(&'static str, String)> {
+			validator
+				.validate(proposed)
+				.unwrap()
+				.into_iter()
+				.map(|conflict| (conflict.kind, conflict.name))
+				.collect()
+		};
+
+		assert!(kinds("URSHORT_STANDARD_URI_blog=https://example.com/blog\n").is_empty());
+		// Setting a live key to the same target is fine
+		assert!(kinds("URSHORT_STANDARD_URI_docs=https://example.com/docs\n").is_empty());
+		assert_eq!(
+			kinds(
+				"URSHORT_STANDARD_URI_docs=https://example.com/other
+URSHORT_STANDARD_URI_api=https://example.com/api
+URSHORT_STANDARD_URI_blog=https://example.com/blog
+URSHORT_STANDARD_URI_blog=https://example.com/blog2
+URSHORT_PATTERN_REGEX_0='^d\\w+$'
+URSHORT_PATTERN_REGEX_1='(unclosed'
+"
+			),
+			vec![
+				("duplicate", "URSHORT_STANDARD_URI_docs".to_string()),
+				("reserved", "URSHORT_STANDARD_URI_api".to_string()),
+				("duplicate", "URSHORT_STANDARD_URI_blog".to_string()),
+				("invalid", "URSHORT_PATTERN_REGEX_1".to_string()),
+				("shadowed", "URSHORT_PATTERN_REGEX_0".to_string()),
+			]
+		);
+		assert!(validator.validate("not a variable").is_err());
+	}
+}
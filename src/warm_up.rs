@@ -0,0 +1,110 @@
+use std::{collections::HashMap, net::IpAddr};
+
+use axum::http::Uri;
+use tokio::net::lookup_host;
+
+/// Find the hosts that the most targets point to, most first, with the port they're reached on.
+/// Addresses are left out, since there's nothing to look up
+pub fn popular_hosts<'a>(
+	uris: impl IntoIterator<Item = &'a Uri>,
+	limit: usize,
+) -> Vec<(String, u16)> {
+	let mut counts: HashMap<(String, u16), usize> = HashMap::new();
+	for uri in uris {
+		let Some(host) = uri.host() else {
+			continue;
+		};
+		let host = host.trim_start_matches('[').trim_end_matches(']');
+		if host.parse::<IpAddr>().is_ok() {
+			continue;
+		}
+		let default_port = if uri.scheme_str() == Some("http") {
+			80
+		} else {
+			443
+		};
+		let port = uri.port_u16().unwrap_or(default_port);
+		*counts.entry((host.to_ascii_lowercase(), port)).or_default() += 1;
+	}
+
+	let mut hosts: Vec<((String, u16), usize)> = counts.into_iter().collect();
+	// Ties are broken by name, so the same hosts are picked every time
+	hosts.sort_by(|(x, x_count), (y, y_count)| y_count.cmp(x_count).then_with(|| x.cmp(y)));
+	hosts
+		.into_iter()
+		.take(limit)
+		.map(|(host, _)| host)
+		.collect()
+}
+
+/// Look up each host, so the answers are cached before the first request, and warn about any that
+/// can't be found, as they're likely a typo
+pub async fn warm_up(hosts: Vec<(String, u16)>) {
+	let lookups: Vec<_> = hosts
+		.into_iter()
+		.map(|(host, port)| {
+			tokio::spawn(async move {
+				let found = lookup_host((host.as_str(), port))
+					.await
+					.map(|mut addresses| addresses.next().is_some());
+				(host, found)
+			})
+		})
+		.collect();
+
+	let mut missing = 0;
+	let total = lookups.len();
+	for lookup in lookups {
+		match lookup.await {
+			Ok((_, Ok(true))) => {}
+			Ok((host, Ok(false))) => {
+				println!("Could not find '{host}', so links to it may not work");
+				missing += 1;
+			}
+			Ok((host, Err(error))) => {
+				println!("Could not find '{host}', so links to it may not work ({error})");
+				missing += 1;
+			}
+			Err(_) => missing += 1,
+		}
+	}
+	println!("Looked up {total} target hosts, {missing} could not be found");
+	println!();
+}
+
+#[cfg(test)]
+mod tests {
+	use std::str::FromStr;
+
+	use super::*;
+
+	#[test]
+	fn find_popular_hosts() {
+		let uris: Vec<Uri> = [
+			"https://docs.example.com/a",
+			"https://docs.example.com/b",
+			"https://Docs.Example.com/c",
+			"http://blog.example.com/",
+			"https://blog.example.com:8443/",
+			"https://blog.example.com:8443/post",
+			"https://192.168.1.10/",
+			"https://[::1]/",
+		]
+		.iter()
+		.map(|uri| Uri::from_str(uri).unwrap())
+		.collect();
+
+		assert_eq!(
+			popular_hosts(&uris, 10),
+			vec![
+				("docs.example.com".to_string(), 443),
+				("blog.example.com".to_string(), 8443),
+				("blog.example.com".to_string(), 80),
+			]
+		);
+		assert_eq!(
+			popular_hosts(&uris, 1),
+			vec![("docs.example.com".to_string(), 443)]
+		);
+	}
+}
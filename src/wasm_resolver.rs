@@ -0,0 +1,193 @@
+use std::{path::Path, str::FromStr};
+
+use async_trait::async_trait;
+use axum::http::Uri;
+use wasmtime::{
+	Config, Engine, InstancePre, Linker, Module, Store, StoreLimits, StoreLimitsBuilder,
+};
+
+use crate::{
+	log_limit::LogLimit,
+	resolver::{Resolution, ResolveRequest, Resolver},
+};
+
+/// The most memory a module can use for a single request
+const MAX_MEMORY: usize = 16 << 20;
+/// Modules failing for requests, printed a few at a time
+static FAILURE_LOG: LogLimit = LogLimit::new("WASM resolver failures");
+
+/// Resolves requests by calling a WebAssembly module.
+///
+/// The module can't import anything, so it has no access to the host. It must export:
+/// - `memory`, that the path and result are passed through
+/// - `alloc(len: i32) -> i32`, returning where to write a path of `len` bytes
+/// - `resolve(ptr: i32, len: i32) -> i64`, returning the result's pointer in the upper 32 bits
+///   and its length in the lower 32 bits, or `0` if there is no match
+///
+/// Every request gets a fresh instance, so no state is kept between them.
+#[derive(Clone)]
+pub struct WasmResolver {
+	instance: InstancePre<StoreLimits>,
+	fuel: u64,
+}
+
+impl WasmResolver {
+	/// Load the module at the path. Each request can use up to `fuel` units of work
+	pub fn new(path: &Path, fuel: u64) -> wasmtime::Result<WasmResolver> {
+		let mut config = Config::new();
+		config.consume_fuel(true);
+		let engine = Engine::new(&config)?;
+
+		let module = Module::from_file(&engine, path)?;
+		Self::from_module(&engine, &module, fuel)
+	}
+
+	fn from_module(engine: &Engine, module: &Module, fuel: u64) -> wasmtime::Result<WasmResolver> {
+		let linker = Linker::new(engine);
+		Ok(WasmResolver {
+			instance: linker.instantiate_pre(module)?,
+			fuel,
+		})
+	}
+
+	/// Run the module for the path
+	fn call(&self, path: &str) -> wasmtime::Result<Option<String>> {
+		let limits = StoreLimitsBuilder::new().memory_size(MAX_MEMORY).build();
+		let mut store = Store::new(self.instance.module().engine(), limits);
+		store.limiter(|limits| limits);
+		store.set_fuel(self.fuel)?;
+
+		let instance = self.instance.instantiate(&mut store)?;
+		let memory = instance
+			.get_memory(&mut store, "memory")
+			.ok_or_else(|| wasmtime::format_err!("module doesn't export memory"))?;
+		let alloc = instance.get_typed_func::<i32, i32>(&mut store, "alloc")?;
+		let resolve = instance.get_typed_func::<(i32, i32), i64>(&mut store, "resolve")?;
+
+		let length = i32::try_from(path.len())?;
+		let pointer = alloc.call(&mut store, length)?;
+		memory.write(&mut store, usize::try_from(pointer)?, path.as_bytes())?;
+
+		let result = resolve.call(&mut store, (pointer, length))?;
+		if result == 0 {
+			return Ok(None);
+		}
+
+		#[allow(clippy::cast_sign_loss)]
+		let result = result as u64;
+		// Read the result straight out of the module's memory, so a made up length can't make the
+		// host allocate more than the module itself could
+		let start = usize::try_from(result >> 32)?;
+		let length = usize::try_from(result & 0xFFFF_FFFF)?;
+		let uri = start
+			.checked_add(length)
+			.and_then(|end| memory.data(&store).get(start..end))
+			.ok_or_else(|| wasmtime::format_err!("module returned a result outside its memory"))?;
+		Ok(Some(std::str::from_utf8(uri)?.to_string()))
+	}
+}
+
+#[async_trait]
+impl Resolver for WasmResolver {
+	async fn resolve(&self, request: &ResolveRequest<'_>) -> Option<Resolution> {
+		// Modules can run for their whole fuel, so they're kept off the threads serving requests
+		let wasm = self.clone();
+		let path = request.path.to_string();
+		let called = tokio::task::spawn_blocking(move || wasm.call(&path))
+			.await
+			.unwrap_or_else(|error| Err(wasmtime::format_err!("{error}")));
+		let uri = match called {
+			Ok(uri) => uri?,
+			Err(error) => {
+				FAILURE_LOG.print(&request.path, || {
+					format!("WASM resolver failed for '{}': {error}", request.path)
+				});
+				return None;
+			}
+		};
+
+		if let Ok(uri) = Uri::from_str(&uri) {
+			return Some(Resolution::redirect(uri));
+		}
+		FAILURE_LOG.print(&request.path, || {
+			format!("WASM resolver returned '{uri}', which is not a URI")
+		});
+		None
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{
+		borrow::Cow,
+		collections::HashMap,
+		net::{IpAddr, Ipv4Addr},
+	};
+
+	use axum::http::HeaderMap;
+
+	use super::*;
+	use crate::resolver::Target;
+
+	/// Resolves `wasm` to `https://example.com/wasm`, loops forever on `loop`, and claims a result
+	/// far bigger than its memory on `huge`
+	const TEST_MODULE: &str = r#"
+		(module
+			(memory (export "memory") 1)
+			(data (i32.const 16) "https://example.com/wasm")
+			(func (export "alloc") (param i32) (result i32)
+				i32.const 1024)
+			(func (export "resolve") (param $ptr i32) (param $len i32) (result i64)
+				;; "loop" never finishes
+				(if (i32.and
+						(i32.eq (local.get $len) (i32.const 4))
+						(i32.eq (i32.load (local.get $ptr)) (i32.const 0x706f6f6c)))
+					(then (loop $forever (br $forever))))
+				;; "huge" returns 4 GiB from a 64 KiB memory
+				(if (i32.and
+						(i32.eq (local.get $len) (i32.const 4))
+						(i32.eq (i32.load (local.get $ptr)) (i32.const 0x65677568)))
+					(then (return (i64.or (i64.shl (i64.const 16) (i64.const 32)) (i64.const 0xFFFFFFFF)))))
+				;; "wasm" is the only match
+				(if (result i64)
+					(i32.and
+						(i32.eq (local.get $len) (i32.const 4))
+						(i32.eq (i32.load (local.get $ptr)) (i32.const 0x6d736177)))
+					(then (i64.or (i64.shl (i64.const 16) (i64.const 32)) (i64.const 24)))
+					(else (i64.const 0)))))
+	"#;
+
+	#[tokio::test]
+	async fn resolve_with_wasm() {
+		let mut config = Config::new();
+		config.consume_fuel(true);
+		let engine = Engine::new(&config).unwrap();
+		let module = Module::new(&engine, TEST_MODULE).unwrap();
+		let wasm = WasmResolver::from_module(&engine, &module, 100_000).unwrap();
+
+		let query = HashMap::new();
+		let headers = HeaderMap::new();
+		let request = |path| ResolveRequest {
+			path: Cow::Borrowed(path),
+			query: &query,
+			headers: &headers,
+			client: IpAddr::V4(Ipv4Addr::LOCALHOST),
+		};
+
+		let resolved = wasm.resolve(&request("wasm")).await;
+		assert_eq!(
+			resolved.map(|x| x.target),
+			Some(Target::Redirect(
+				Uri::from_str("https://example.com/wasm").unwrap()
+			))
+		);
+		assert!(wasm.resolve(&request("nope")).await.is_none());
+
+		// Running out of fuel stops the module instead of hanging
+		assert!(wasm.call("loop").is_err());
+		assert!(wasm.resolve(&request("loop")).await.is_none());
+
+		// Results outside the module's memory are refused instead of read
+		assert!(wasm.call("huge").is_err());
+	}
+}
@@ -0,0 +1,162 @@
+use std::path::PathBuf;
+
+use axum::{
+	extract::{Path, RawQuery},
+	http::{header, HeaderValue, StatusCode, Uri},
+	response::{IntoResponse, Response},
+	routing::get,
+	Router,
+};
+
+/// Answers the discovery files under `/.well-known/`, like the ones for finding accounts or a Matrix
+/// server, instead of treating them as links
+#[derive(Clone)]
+pub struct WellKnown {
+	/// The folder of files to serve
+	pub dir: Option<PathBuf>,
+	/// Where to send the requests for files that aren't in the folder, keeping their path
+	pub redirect: Option<Uri>,
+}
+
+impl WellKnown {
+	/// Answer a request for the file at the path under `/.well-known/`
+	async fn respond(&self, path: &str, query: Option<&str>) -> Response {
+		if let Some(file) = self.dir.as_ref().and_then(|dir| file_path(dir, path)) {
+			if let Ok(contents) = tokio::fs::read(&file).await {
+				return (
+					[
+						(
+							header::CONTENT_TYPE,
+							HeaderValue::from_static(content_type(path)),
+						),
+						(
+							header::ACCESS_CONTROL_ALLOW_ORIGIN,
+							HeaderValue::from_static("*"),
+						),
+					],
+					contents,
+				)
+					.into_response();
+			}
+		}
+
+		let Some(base) = &self.redirect else {
+			return StatusCode::NOT_FOUND.into_response();
+		};
+		let location = well_known_location(base, path, query);
+		match HeaderValue::try_from(location) {
+			Ok(location) => (
+				StatusCode::TEMPORARY_REDIRECT,
+				[
+					(header::LOCATION, location),
+					// Discovery files are often fetched by scripts on other sites
+					(
+						header::ACCESS_CONTROL_ALLOW_ORIGIN,
+						HeaderValue::from_static("*"),
+					),
+				],
+			)
+				.into_response(),
+			Err(_) => StatusCode::NOT_FOUND.into_response(),
+		}
+	}
+}
+
+/// Find the file for the path in the folder. Paths that could leave the folder, or reach hidden
+/// files, are never found
+fn file_path(dir: &std::path::Path, path: &str) -> Option<PathBuf> {
+	let mut file = dir.to_path_buf();
+	for segment in path.split('/').filter(|segment| !segment.is_empty()) {
+		if segment.starts_with('.') || segment.contains('\\') {
+			return None;
+		}
+		file.push(segment);
+	}
+	(file != dir).then_some(file)
+}
+
+/// The type of the file at the path, from the name of the standard files or else its extension
+fn content_type(path: &str) -> &'static str {
+	let name = path.trim_matches('/');
+	match name {
+		"webfinger" | "nodeinfo" => return "application/jrd+json",
+		"host-meta" => return "application/xrd+xml",
+		"matrix/server" | "matrix/client" | "apple-app-site-association" => {
+			return "application/json"
+		}
+		_ => {}
+	}
+	match name.rsplit_once('.').map(|(_, extension)| extension) {
+		Some("txt") => "text/plain; charset=utf-8",
+		Some("json") => "application/json",
+		Some("xml") => "application/xml",
+		Some("html") => "text/html; charset=utf-8",
+		_ => "application/octet-stream",
+	}
+}
+
+/// Where to find the file at the path under `/.well-known/` on the base, keeping the query
+fn well_known_location(base: &Uri, path: &str, query: Option<&str>) -> String {
+	let base = base.to_string();
+	let mut location = format!(
+		"{}/.well-known/{}",
+		base.trim_end_matches('/'),
+		path.trim_start_matches('/')
+	);
+	if let Some(query) = query {
+		location.push('?');
+		location.push_str(query);
+	}
+	location
+}
+
+/// The route for the files under `/.well-known/`
+pub fn router(well_known: WellKnown) -> Router {
+	Router::new().route(
+		"/.well-known/*path",
+		get(
+			move |Path(path): Path<String>, RawQuery(query): RawQuery| async move {
+				well_known.respond(&path, query.as_deref()).await
+			},
+		),
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::str::FromStr;
+
+	use super::*;
+
+	#[test]
+	fn redirect_well_known() {
+		let base = Uri::from_str("https://example.com/").unwrap();
+		assert_eq!(
+			well_known_location(&base, "/webfinger", Some("resource=acct:me@example.com")),
+			"https://example.com/.well-known/webfinger?resource=acct:me@example.com"
+		);
+		assert_eq!(
+			well_known_location(&base, "matrix/server", None),
+			"https://example.com/.well-known/matrix/server"
+		);
+	}
+
+	#[test]
+	fn serve_well_known_files() {
+		let dir = std::path::Path::new("/srv/well-known");
+		assert_eq!(
+			file_path(dir, "/matrix/server"),
+			Some(PathBuf::from("/srv/well-known/matrix/server"))
+		);
+		assert_eq!(file_path(dir, "../secret.txt"), None);
+		assert_eq!(file_path(dir, "matrix/../../secret.txt"), None);
+		assert_eq!(file_path(dir, ".git/config"), None);
+		assert_eq!(file_path(dir, "/"), None);
+
+		assert_eq!(content_type("security.txt"), "text/plain; charset=utf-8");
+		assert_eq!(content_type("matrix/server"), "application/json");
+		assert_eq!(content_type("webfinger"), "application/jrd+json");
+		assert_eq!(content_type("assetlinks.json"), "application/json");
+		assert_eq!(content_type("unknown"), "application/octet-stream");
+	}
+}